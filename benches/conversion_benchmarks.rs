@@ -1,5 +1,5 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use schematic_converter::converters::{schem_to_schematic, schematic_to_schem};
+use schematic_converter::converters::{litematic_to_schematic, schem_to_schematic, schematic_to_litematic, schematic_to_schem};
 use std::io::Cursor;
 
 fn benchmark_schem_to_schematic(c: &mut Criterion) {
@@ -40,5 +40,30 @@ fn benchmark_roundtrip(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, benchmark_schem_to_schematic, benchmark_schematic_to_schem, benchmark_roundtrip);
+// Run with and without `--features parallel` to compare the block unpacking/packing paths.
+fn benchmark_big_quarry_to_schematic(c: &mut Criterion) {
+    let big_quarry = include_bytes!("../tests/test_schematics/big_quarry.litematic");
+
+    c.bench_function("big_quarry_litematic_to_schematic", |b| {
+        b.iter(|| {
+            let mut output = Vec::new();
+            litematic_to_schematic(Cursor::new(black_box(big_quarry)), &mut output).unwrap();
+        })
+    });
+}
+
+fn benchmark_big_quarry_to_litematic(c: &mut Criterion) {
+    let big_quarry = include_bytes!("../tests/test_schematics/big_quarry.litematic");
+    let mut schematic_data = Vec::new();
+    litematic_to_schematic(Cursor::new(big_quarry), &mut schematic_data).unwrap();
+
+    c.bench_function("big_quarry_schematic_to_litematic", |b| {
+        b.iter(|| {
+            let mut output = Vec::new();
+            schematic_to_litematic(Cursor::new(black_box(&schematic_data)), &mut output).unwrap();
+        })
+    });
+}
+
+criterion_group!(benches, benchmark_schem_to_schematic, benchmark_schematic_to_schem, benchmark_roundtrip, benchmark_big_quarry_to_schematic, benchmark_big_quarry_to_litematic);
 criterion_main!(benches);
\ No newline at end of file
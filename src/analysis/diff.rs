@@ -0,0 +1,271 @@
+use quartz_nbt::{NbtCompound, NbtTag};
+use crate::models::{BlockState, Schematic};
+use crate::operations::{block_entity_position, set_block_entity_position, translate_entity};
+use crate::render::colors::is_air;
+use super::{bounding_box, json_escape};
+
+/// Block entity keys holding its position, ignored when comparing block entity contents.
+const POSITION_KEYS: [&str; 4] = ["Pos", "x", "y", "z"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl ChangeKind {
+    fn name(&self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Changed => "changed",
+        }
+    }
+}
+
+/// A block that differs between the two schematics. Air stands in for a missing block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockChange {
+    pub kind: ChangeKind,
+    pub position: [i32; 3],
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockEntityChange {
+    pub kind: ChangeKind,
+    pub position: [i32; 3],
+    pub before: Option<NbtCompound>,
+    pub after: Option<NbtCompound>,
+}
+
+/// An entity that was added, removed or changed. Entities are matched by UUID when they have
+/// one, and by their full NBT otherwise.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityChange {
+    pub kind: ChangeKind,
+    pub id: String,
+    pub before: Option<NbtCompound>,
+    pub after: Option<NbtCompound>,
+}
+
+/// Counts of each kind of change.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffSummary {
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+    pub block_entities: usize,
+    pub entities: usize,
+}
+
+/// Differences between two schematics, with positions in the coordinates of the old one.
+///
+/// Block entities and entities from the new schematic are stored moved into those
+/// coordinates as well.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchematicDiff {
+    /// Position of the new schematic's origin in the old schematic.
+    pub offset: [i32; 3],
+    /// Lowest corner of the box covering both schematics.
+    pub min: [i32; 3],
+    /// Size of the box covering both schematics.
+    pub size: [usize; 3],
+    pub blocks: Vec<BlockChange>,
+    pub block_entities: Vec<BlockEntityChange>,
+    pub entities: Vec<EntityChange>,
+}
+
+impl SchematicDiff {
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty() && self.block_entities.is_empty() && self.entities.is_empty()
+    }
+
+    pub fn summary(&self) -> DiffSummary {
+        let count = |kind: ChangeKind| self.blocks.iter().filter(|change| change.kind == kind).count();
+        DiffSummary {
+            added: count(ChangeKind::Added),
+            removed: count(ChangeKind::Removed),
+            changed: count(ChangeKind::Changed),
+            block_entities: self.block_entities.len(),
+            entities: self.entities.len(),
+        }
+    }
+
+    /// Summary counts and the list of block changes as JSON. NBT contents are left out.
+    pub fn to_json(&self) -> String {
+        let summary = self.summary();
+        let position = |[x, y, z]: [i32; 3]| format!("[{},{},{}]", x, y, z);
+        let blocks: Vec<String> = self.blocks.iter()
+            .map(|change| format!(
+                "{{\"kind\":\"{}\",\"position\":{},\"before\":\"{}\",\"after\":\"{}\"}}",
+                change.kind.name(), position(change.position), json_escape(&change.before), json_escape(&change.after)
+            ))
+            .collect();
+        let block_entities: Vec<String> = self.block_entities.iter()
+            .map(|change| format!("{{\"kind\":\"{}\",\"position\":{}}}", change.kind.name(), position(change.position)))
+            .collect();
+        let entities: Vec<String> = self.entities.iter()
+            .map(|change| format!("{{\"kind\":\"{}\",\"id\":\"{}\"}}", change.kind.name(), json_escape(&change.id)))
+            .collect();
+
+        format!(
+            "{{\"summary\":{{\"added\":{},\"removed\":{},\"changed\":{},\"blockEntities\":{},\"entities\":{}}},\"blocks\":[{}],\"blockEntities\":[{}],\"entities\":[{}]}}",
+            summary.added, summary.removed, summary.changed, summary.block_entities, summary.entities,
+            blocks.join(","), block_entities.join(","), entities.join(",")
+        )
+    }
+
+    /// Builds a schematic covering both inputs where added blocks are lime, removed blocks red
+    /// and changed blocks yellow concrete, and everything else is air.
+    pub fn to_schematic(&self) -> Schematic {
+        let mut schematic = Schematic::new(self.size[0], self.size[1], self.size[2]);
+        for change in &self.blocks {
+            let block = match change.kind {
+                ChangeKind::Added => "minecraft:lime_concrete",
+                ChangeKind::Removed => "minecraft:red_concrete",
+                ChangeKind::Changed => "minecraft:yellow_concrete",
+            };
+            let [x, y, z] = [0, 1, 2].map(|axis| (change.position[axis] - self.min[axis]) as usize);
+            schematic.set_block(x, y, z, block);
+        }
+        schematic
+    }
+}
+
+/// Compares block states regardless of the order their properties are written in, or of a
+/// left out `minecraft:` namespace.
+pub(crate) fn same_block_state(a: &str, b: &str) -> bool {
+    if a == b {
+        return true;
+    }
+    match (BlockState::parse(a), BlockState::parse(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn block_in(schematic: &Schematic, [x, y, z]: [i32; 3]) -> &str {
+    if x < 0 || y < 0 || z < 0 || x as usize >= schematic.width || y as usize >= schematic.height || z as usize >= schematic.length {
+        return "minecraft:air";
+    }
+    schematic.block_at(x as usize, y as usize, z as usize)
+}
+
+fn without_position(block_entity: &NbtCompound) -> NbtCompound {
+    let mut stripped = block_entity.clone();
+    for key in POSITION_KEYS {
+        stripped.inner_mut().remove(key);
+    }
+    stripped
+}
+
+fn moved_block_entity(block_entity: &NbtCompound, position: [i32; 3]) -> NbtCompound {
+    let mut moved = block_entity.clone();
+    set_block_entity_position(&mut moved, position);
+    moved
+}
+
+fn entity_id(entity: &NbtCompound) -> String {
+    entity.get::<_, &str>("Id").or_else(|_| entity.get::<_, &str>("id")).unwrap_or("unknown").to_string()
+}
+
+fn entity_uuid(entity: &NbtCompound) -> Option<Vec<i64>> {
+    match entity.get::<_, &NbtTag>("UUID") {
+        Ok(NbtTag::IntArray(uuid)) => Some(uuid.iter().map(|&part| part as i64).collect()),
+        _ => Some(vec![entity.get::<_, i64>("UUIDMost").ok()?, entity.get::<_, i64>("UUIDLeast").ok()?]),
+    }
+}
+
+/// Returns the offset that lines up the lowest corners of the non-air blocks of both
+/// schematics, for revisions that were saved with a different selection.
+pub fn align_by_bounds(old: &Schematic, new: &Schematic) -> [i32; 3] {
+    match (bounding_box(old, is_air), bounding_box(new, is_air)) {
+        (Some(old_bounds), Some(new_bounds)) => [0, 1, 2].map(|axis| old_bounds.min[axis] as i32 - new_bounds.min[axis] as i32),
+        _ => [0, 0, 0],
+    }
+}
+
+/// Compares two schematics block by block, with the new schematic's origin placed at
+/// `offset` in the old one.
+pub fn diff(old: &Schematic, new: &Schematic, offset: [i32; 3]) -> SchematicDiff {
+    let old_size = [old.width, old.height, old.length].map(|value| value as i32);
+    let new_size = [new.width, new.height, new.length].map(|value| value as i32);
+    let min = [0, 1, 2].map(|axis| offset[axis].min(0));
+    let max = [0, 1, 2].map(|axis| (offset[axis] + new_size[axis]).max(old_size[axis]));
+
+    let mut blocks = Vec::new();
+    for y in min[1]..max[1] {
+        for z in min[2]..max[2] {
+            for x in min[0]..max[0] {
+                let before = block_in(old, [x, y, z]);
+                let after = block_in(new, [x - offset[0], y - offset[1], z - offset[2]]);
+                if same_block_state(before, after) {
+                    continue;
+                }
+                let kind = match (is_air(before), is_air(after)) {
+                    (true, _) => ChangeKind::Added,
+                    (_, true) => ChangeKind::Removed,
+                    _ => ChangeKind::Changed,
+                };
+                blocks.push(BlockChange { kind, position: [x, y, z], before: before.to_string(), after: after.to_string() });
+            }
+        }
+    }
+
+    let new_block_entities: Vec<([i32; 3], &NbtCompound)> = new.block_entities.iter()
+        .filter_map(|block_entity| block_entity_position(block_entity).map(|[x, y, z]| ([x + offset[0], y + offset[1], z + offset[2]], block_entity)))
+        .collect();
+    let mut block_entities = Vec::new();
+    for before in &old.block_entities {
+        let Some(position) = block_entity_position(before) else {
+            continue;
+        };
+        match new_block_entities.iter().find(|(new_position, _)| *new_position == position) {
+            Some((_, after)) if without_position(before) == without_position(after) => {}
+            Some((_, after)) => block_entities.push(BlockEntityChange { kind: ChangeKind::Changed, position, before: Some(before.clone()), after: Some(moved_block_entity(after, position)) }),
+            None => block_entities.push(BlockEntityChange { kind: ChangeKind::Removed, position, before: Some(before.clone()), after: None }),
+        }
+    }
+    for (position, after) in &new_block_entities {
+        if !old.block_entities.iter().any(|before| block_entity_position(before) == Some(*position)) {
+            block_entities.push(BlockEntityChange { kind: ChangeKind::Added, position: *position, before: None, after: Some(moved_block_entity(after, *position)) });
+        }
+    }
+
+    let mut entities = Vec::new();
+    // New entities are moved into the old schematic's coordinates before comparing
+    let moved: Vec<NbtCompound> = new.entities.iter()
+        .map(|entity| {
+            let mut entity = entity.clone();
+            translate_entity(&mut entity, offset);
+            entity
+        })
+        .collect();
+    let mut unmatched: Vec<&NbtCompound> = moved.iter().collect();
+    for before in &old.entities {
+        let matched = match entity_uuid(before) {
+            Some(uuid) => unmatched.iter().position(|after| entity_uuid(after).as_ref() == Some(&uuid)),
+            None => unmatched.iter().position(|after| *after == before),
+        };
+        match matched.map(|index| unmatched.remove(index)) {
+            Some(after) if after == before => {}
+            Some(after) => entities.push(EntityChange { kind: ChangeKind::Changed, id: entity_id(after), before: Some(before.clone()), after: Some(after.clone()) }),
+            None => entities.push(EntityChange { kind: ChangeKind::Removed, id: entity_id(before), before: Some(before.clone()), after: None }),
+        }
+    }
+    for after in unmatched {
+        entities.push(EntityChange { kind: ChangeKind::Added, id: entity_id(after), before: None, after: Some(after.clone()) });
+    }
+
+    SchematicDiff {
+        offset,
+        min,
+        size: [0, 1, 2].map(|axis| (max[axis] - min[axis]) as usize),
+        blocks,
+        block_entities,
+        entities,
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+use quartz_nbt::{NbtCompound, NbtTag};
+use crate::models::{BlockState, Schematic};
+use super::json_escape;
+
+/// Blocks that have no item form or are never gathered, so they are left out of the list.
+const EXCLUDED_BLOCKS: [&str; 16] = [
+    "air",
+    "cave_air",
+    "void_air",
+    "water",
+    "lava",
+    "fire",
+    "soul_fire",
+    "bubble_column",
+    "piston_head",
+    "moving_piston",
+    "nether_portal",
+    "end_portal",
+    "end_gateway",
+    "frosted_ice",
+    "structure_void",
+    "light",
+];
+
+/// Blocks whose item has a different id than the placed block.
+const BLOCK_ITEMS: [(&str, &str); 24] = [
+    ("redstone_wire", "redstone"),
+    ("wall_torch", "torch"),
+    ("redstone_wall_torch", "redstone_torch"),
+    ("soul_wall_torch", "soul_torch"),
+    ("tripwire", "string"),
+    ("wheat", "wheat_seeds"),
+    ("carrots", "carrot"),
+    ("potatoes", "potato"),
+    ("beetroots", "beetroot_seeds"),
+    ("cocoa", "cocoa_beans"),
+    ("sweet_berry_bush", "sweet_berries"),
+    ("pumpkin_stem", "pumpkin_seeds"),
+    ("attached_pumpkin_stem", "pumpkin_seeds"),
+    ("melon_stem", "melon_seeds"),
+    ("attached_melon_stem", "melon_seeds"),
+    ("cave_vines", "glow_berries"),
+    ("cave_vines_plant", "glow_berries"),
+    ("bamboo_sapling", "bamboo"),
+    ("kelp_plant", "kelp"),
+    ("twisting_vines_plant", "twisting_vines"),
+    ("weeping_vines_plant", "weeping_vines"),
+    ("tall_seagrass", "seagrass"),
+    ("farmland", "dirt"),
+    ("dirt_path", "dirt"),
+];
+
+/// Properties holding how many items a single block is made of.
+const COUNT_PROPERTIES: [(&str, &str); 5] = [
+    ("snow", "layers"),
+    ("sea_pickle", "pickles"),
+    ("turtle_egg", "eggs"),
+    ("pink_petals", "flower_amount"),
+    ("candle", "candles"),
+];
+
+/// One line of a material list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaterialEntry {
+    /// Item id, such as `minecraft:oak_planks`.
+    pub item: String,
+    /// Number of items needed.
+    pub count: u64,
+    /// How many of the placed blocks are waterlogged.
+    pub waterlogged: u64,
+}
+
+/// Items needed to build a schematic, sorted by descending count and then by item id.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MaterialList {
+    pub entries: Vec<MaterialEntry>,
+}
+
+impl MaterialList {
+    /// Total number of items over all entries.
+    pub fn total(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.count).sum()
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("item,count,waterlogged\n");
+        for entry in &self.entries {
+            csv.push_str(&format!("{},{},{}\n", entry.item, entry.count, entry.waterlogged));
+        }
+        csv
+    }
+
+    pub fn to_json(&self) -> String {
+        let entries: Vec<String> = self.entries.iter()
+            .map(|entry| format!("{{\"item\":\"{}\",\"count\":{},\"waterlogged\":{}}}", json_escape(&entry.item), entry.count, entry.waterlogged))
+            .collect();
+        format!("[{}]", entries.join(","))
+    }
+}
+
+/// Converts a placed block state into the items it takes, the way Litematica counts them.
+///
+/// Multi-block structures such as doors, beds and tall plants are only counted on one of
+/// their halves, double slabs count as two slabs, and blocks holding several items (snow
+/// layers, sea pickles, candles, ...) count each of them. Potted plants count the pot and
+/// the plant.
+fn block_items(block_state: &BlockState) -> Vec<(String, u64)> {
+    let (namespace, id) = block_state.name().split_once(':').unwrap_or(("minecraft", block_state.name()));
+    let property = |key: &str| block_state.property(key);
+    let item = |id: &str| format!("{}:{}", namespace, id);
+
+    if EXCLUDED_BLOCKS.contains(&id) {
+        return Vec::new();
+    }
+    if property("half") == Some("upper") || property("part") == Some("head") {
+        return Vec::new();
+    }
+    if id.ends_with("_slab") && property("type") == Some("double") {
+        return vec![(item(id), 2)];
+    }
+    if let Some(plant) = id.strip_prefix("potted_") {
+        return vec![(item("flower_pot"), 1), (item(plant), 1)];
+    }
+    if let Some((_, key)) = COUNT_PROPERTIES.iter().find(|(block, _)| *block == id || (id.ends_with("_candle") && *block == "candle")) {
+        let count = property(key).and_then(|value| value.parse().ok()).unwrap_or(1);
+        return vec![(item(id), count)];
+    }
+    if let Some(cake) = id.strip_suffix("_candle_cake").or((id == "candle_cake").then_some("")) {
+        let candle = if cake.is_empty() { "candle".to_string() } else { format!("{}_candle", cake) };
+        return vec![(item("cake"), 1), (item(&candle), 1)];
+    }
+    if let Some((_, mapped)) = BLOCK_ITEMS.iter().find(|(block, _)| *block == id) {
+        return vec![(item(mapped), 1)];
+    }
+
+    // Wall-mounted variants use the item of the standing block
+    for (wall, standing) in [("_wall_sign", "_sign"), ("_wall_hanging_sign", "_hanging_sign"), ("_wall_banner", "_banner"), ("_wall_head", "_head"), ("_wall_skull", "_skull"), ("_wall_fan", "_fan")] {
+        if let Some(base) = id.strip_suffix(wall) {
+            return vec![(item(&format!("{}{}", base, standing)), 1)];
+        }
+    }
+    vec![(item(id), 1)]
+}
+
+/// Returns the `Items` list of a container block entity, looking inside the Sponge v3 `Data`
+/// compound as well.
+fn container_items(block_entity: &NbtCompound) -> Vec<(String, u64)> {
+    let items = match block_entity.get::<_, &NbtTag>("Items") {
+        Ok(NbtTag::List(items)) => items,
+        _ => match block_entity.get::<_, &NbtCompound>("Data").map(|data| data.get::<_, &NbtTag>("Items")) {
+            Ok(Ok(NbtTag::List(items))) => items,
+            _ => return Vec::new(),
+        },
+    };
+
+    items.iter()
+        .filter_map(|item| match item {
+            NbtTag::Compound(item) => {
+                let id = item.get::<_, &str>("id").ok()?;
+                let count = match item.get::<_, &NbtTag>("Count").or_else(|_| item.get::<_, &NbtTag>("count")) {
+                    Ok(NbtTag::Byte(count)) => *count as u64,
+                    Ok(NbtTag::Int(count)) => *count as u64,
+                    _ => 1,
+                };
+                Some((id.to_string(), count))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Builds the list of items needed to build the schematic.
+///
+/// With `include_containers` the contents of chests, barrels and other containers are added
+/// to the list as well.
+pub fn material_list(schematic: &Schematic, include_containers: bool) -> MaterialList {
+    let mut block_counts = vec![0u64; schematic.palette.len()];
+    for &block in &schematic.blocks {
+        block_counts[block as usize] += 1;
+    }
+
+    let mut totals: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+    for (block_state, &blocks) in schematic.palette.iter().zip(&block_counts) {
+        if blocks == 0 {
+            continue;
+        }
+        // Entries that are not block states are counted as an item of their own
+        let (items, waterlogged) = match BlockState::parse(block_state) {
+            Ok(parsed) => (block_items(&parsed), parsed.property("waterlogged") == Some("true")),
+            Err(_) => (vec![(block_state.clone(), 1)], false),
+        };
+        for (item, per_block) in items {
+            let total = totals.entry(item).or_default();
+            total.0 += per_block * blocks;
+            if waterlogged {
+                total.1 += blocks;
+            }
+        }
+    }
+
+    if include_containers {
+        for block_entity in &schematic.block_entities {
+            for (item, count) in container_items(block_entity) {
+                totals.entry(item).or_default().0 += count;
+            }
+        }
+    }
+
+    let mut entries: Vec<MaterialEntry> = totals.into_iter()
+        .map(|(item, (count, waterlogged))| MaterialEntry { item, count, waterlogged })
+        .collect();
+    entries.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.item.cmp(&b.item)));
+    MaterialList { entries }
+}
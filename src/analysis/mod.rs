@@ -0,0 +1,26 @@
+mod diff;
+mod materials;
+mod stats;
+mod validate;
+
+pub use diff::{align_by_bounds, diff, BlockChange, BlockEntityChange, ChangeKind, DiffSummary, EntityChange, SchematicDiff};
+pub use materials::{material_list, MaterialEntry, MaterialList};
+pub use stats::{bounding_box, schematic_stats, BoundingBox, SchematicStats};
+pub use validate::{validate, validate_schematic, Finding, Severity, ValidationReport};
+
+/// Escapes a string for use inside a JSON string literal.
+pub(crate) fn json_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
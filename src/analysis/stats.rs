@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+use quartz_nbt::{NbtCompound, NbtTag};
+use crate::models::Schematic;
+use crate::render::colors::is_air;
+use super::json_escape;
+
+/// Inclusive bounds of a group of blocks in a schematic, as `[x, y, z]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoundingBox {
+    pub min: [usize; 3],
+    pub max: [usize; 3],
+}
+
+impl BoundingBox {
+    /// Size of the box along each axis.
+    pub fn size(&self) -> [usize; 3] {
+        [
+            self.max[0] - self.min[0] + 1,
+            self.max[1] - self.min[1] + 1,
+            self.max[2] - self.min[2] + 1,
+        ]
+    }
+}
+
+/// Summary of a schematic's contents, for inspecting a file without converting it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchematicStats {
+    pub width: usize,
+    pub height: usize,
+    pub length: usize,
+    pub total_blocks: u64,
+    pub non_air_blocks: u64,
+    /// Tight bounds of the non-air blocks, `None` when the schematic is empty.
+    pub bounding_box: Option<BoundingBox>,
+    pub palette_size: usize,
+    /// Block states and how often they occur, most common first.
+    pub block_counts: Vec<(String, u64)>,
+    /// Block entity ids and how often they occur, sorted by id.
+    pub block_entity_counts: Vec<(String, u64)>,
+    /// Entity ids and how often they occur, sorted by id.
+    pub entity_counts: Vec<(String, u64)>,
+    pub data_version: Option<i32>,
+    /// Scalar metadata entries such as `Name` or `Author`, rendered as strings.
+    pub metadata: Vec<(String, String)>,
+}
+
+impl SchematicStats {
+    pub fn to_json(&self) -> String {
+        let counts = |counts: &[(String, u64)]| {
+            let entries: Vec<String> = counts.iter()
+                .map(|(id, count)| format!("{{\"id\":\"{}\",\"count\":{}}}", json_escape(id), count))
+                .collect();
+            format!("[{}]", entries.join(","))
+        };
+        let bounding_box = match &self.bounding_box {
+            Some(bounds) => format!(
+                "{{\"min\":[{},{},{}],\"max\":[{},{},{}]}}",
+                bounds.min[0], bounds.min[1], bounds.min[2], bounds.max[0], bounds.max[1], bounds.max[2]
+            ),
+            None => "null".to_string(),
+        };
+        let data_version = self.data_version.map_or("null".to_string(), |version| version.to_string());
+        let metadata: Vec<String> = self.metadata.iter()
+            .map(|(key, value)| format!("\"{}\":\"{}\"", json_escape(key), json_escape(value)))
+            .collect();
+
+        format!(
+            "{{\"width\":{},\"height\":{},\"length\":{},\"totalBlocks\":{},\"nonAirBlocks\":{},\"boundingBox\":{},\"paletteSize\":{},\"blockCounts\":{},\"blockEntityCounts\":{},\"entityCounts\":{},\"dataVersion\":{},\"metadata\":{{{}}}}}",
+            self.width,
+            self.height,
+            self.length,
+            self.total_blocks,
+            self.non_air_blocks,
+            bounding_box,
+            self.palette_size,
+            counts(&self.block_counts),
+            counts(&self.block_entity_counts),
+            counts(&self.entity_counts),
+            data_version,
+            metadata.join(",")
+        )
+    }
+}
+
+/// Returns the id of a block entity or entity, looking inside the Sponge v3 `Data` compound
+/// as well.
+fn nbt_id(compound: &NbtCompound) -> String {
+    compound.get::<_, &str>("Id")
+        .or_else(|_| compound.get::<_, &str>("id"))
+        .or_else(|_| compound.get::<_, &NbtCompound>("Data").and_then(|data| data.get::<_, &str>("id")))
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+fn count_ids(compounds: &[NbtCompound]) -> Vec<(String, u64)> {
+    let mut counts: BTreeMap<String, u64> = BTreeMap::new();
+    for compound in compounds {
+        *counts.entry(nbt_id(compound)).or_default() += 1;
+    }
+    counts.into_iter().collect()
+}
+
+fn metadata_value(tag: &NbtTag) -> Option<String> {
+    match tag {
+        NbtTag::String(value) => Some(value.clone()),
+        NbtTag::Byte(value) => Some(value.to_string()),
+        NbtTag::Short(value) => Some(value.to_string()),
+        NbtTag::Int(value) => Some(value.to_string()),
+        NbtTag::Long(value) => Some(value.to_string()),
+        NbtTag::Float(value) => Some(value.to_string()),
+        NbtTag::Double(value) => Some(value.to_string()),
+        // Arrays such as PreviewImageData and nested compounds are too large to show
+        _ => None,
+    }
+}
+
+/// Returns the tight bounds of the blocks for which `is_empty` is false.
+pub fn bounding_box(schematic: &Schematic, is_empty: impl Fn(&str) -> bool) -> Option<BoundingBox> {
+    let empty: Vec<bool> = schematic.palette.iter().map(|block| is_empty(block)).collect();
+    let mut bounds: Option<BoundingBox> = None;
+
+    for y in 0..schematic.height {
+        for z in 0..schematic.length {
+            for x in 0..schematic.width {
+                if empty[schematic.blocks[schematic.index(x, y, z)] as usize] {
+                    continue;
+                }
+                let bounds = bounds.get_or_insert(BoundingBox { min: [x, y, z], max: [x, y, z] });
+                for (axis, value) in [x, y, z].into_iter().enumerate() {
+                    bounds.min[axis] = bounds.min[axis].min(value);
+                    bounds.max[axis] = bounds.max[axis].max(value);
+                }
+            }
+        }
+    }
+    bounds
+}
+
+/// Collects dimensions, block counts, bounds and metadata of a schematic.
+pub fn schematic_stats(schematic: &Schematic) -> SchematicStats {
+    let mut palette_counts = vec![0u64; schematic.palette.len()];
+    for &block in &schematic.blocks {
+        palette_counts[block as usize] += 1;
+    }
+    let air: Vec<bool> = schematic.palette.iter().map(|block| is_air(block)).collect();
+
+    let non_air_blocks = palette_counts.iter().zip(&air)
+        .filter(|(_, &air)| !air)
+        .map(|(&count, _)| count)
+        .sum();
+    let mut block_counts: Vec<(String, u64)> = schematic.palette.iter().cloned()
+        .zip(palette_counts)
+        .filter(|(_, count)| *count > 0)
+        .collect();
+    block_counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut metadata: Vec<(String, String)> = schematic.metadata.inner().iter()
+        .filter_map(|(key, tag)| metadata_value(tag).map(|value| (key.clone(), value)))
+        .collect();
+    metadata.sort();
+
+    SchematicStats {
+        width: schematic.width,
+        height: schematic.height,
+        length: schematic.length,
+        total_blocks: schematic.volume() as u64,
+        non_air_blocks,
+        bounding_box: bounding_box(schematic, is_air),
+        palette_size: schematic.palette.len(),
+        block_counts,
+        block_entity_counts: count_ids(&schematic.block_entities),
+        entity_counts: count_ids(&schematic.entities),
+        data_version: schematic.data_version,
+        metadata,
+    }
+}
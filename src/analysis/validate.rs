@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+use quartz_nbt::io::Flavor;
+use quartz_nbt::{NbtCompound, NbtList, NbtTag};
+use crate::analysis::json_escape;
+use crate::converters::{decode_varints, litematic_bits_per_block, read_decompressed, sponge_v2_layout, unpack_block_states};
+use crate::models::{BlockState, Schematic};
+use crate::operations::{block_entity_position, can_hold};
+use crate::render::colors::is_air;
+use crate::SchematicFormat;
+
+/// How serious a finding is. Errors make the file unreadable or its conversion wrong, warnings
+/// point at data that is probably broken, and infos are worth knowing but often intended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// The findings of `validate`, in the order they were found.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationReport {
+    pub findings: Vec<Finding>,
+}
+
+impl ValidationReport {
+    /// Whether nothing was found that is an error.
+    pub fn is_valid(&self) -> bool {
+        self.findings.iter().all(|finding| finding.severity != Severity::Error)
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.findings.iter().filter(|finding| finding.severity == severity).count()
+    }
+
+    pub fn to_json(&self) -> String {
+        let findings: Vec<String> = self.findings.iter()
+            .map(|finding| format!("{{\"severity\":\"{}\",\"message\":\"{}\"}}", finding.severity.as_str(), json_escape(&finding.message)))
+            .collect();
+        format!("{{\"valid\":{},\"findings\":[{}]}}", self.is_valid(), findings.join(","))
+    }
+
+    fn push(&mut self, severity: Severity, message: impl Into<String>) {
+        self.findings.push(Finding { severity, message: message.into() });
+    }
+}
+
+/// Checks raw schematic data for problems, without failing on malformed input.
+///
+/// The layout of the file is checked first: dimensions against the data length, palette
+/// indices, the packed `BlockStates` length and duplicate palette entries. When the layout is
+/// sound the schematic is read and its contents are checked with `validate_schematic`; for
+/// litematics that covers the region the converters read.
+pub fn validate(input: &[u8], format: SchematicFormat) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let read = || -> Result<NbtCompound, Box<dyn std::error::Error>> {
+        let data = read_decompressed(Cursor::new(input))?;
+        Ok(quartz_nbt::io::read_nbt(&mut Cursor::new(data), Flavor::Uncompressed)?.0)
+    };
+    let nbt = match read() {
+        Ok(nbt) => nbt,
+        Err(e) => {
+            report.push(Severity::Error, format!("Not readable as NBT: {}", e));
+            return report;
+        }
+    };
+
+    match format {
+        SchematicFormat::Litematic => validate_litematic_layout(&nbt, &mut report),
+        SchematicFormat::Schematic | SchematicFormat::Schem => validate_sponge_layout(&nbt, &mut report),
+    }
+    if !report.is_valid() {
+        return report;
+    }
+
+    match Schematic::read(input, format) {
+        Ok(schematic) => report.findings.extend(validate_schematic(&schematic).findings),
+        Err(e) => report.push(Severity::Error, format!("Could not be read: {}", e)),
+    }
+    report
+}
+
+/// Checks the contents of an in-memory schematic: block and biome data against the size and
+/// palettes, duplicate palette entries, block ids, and block entity positions. With the
+/// `registry` feature, vanilla block states are also checked against the blocks of the
+/// schematic's release.
+pub fn validate_schematic(schematic: &Schematic) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let volume = schematic.volume();
+
+    if schematic.blocks.len() != volume {
+        report.push(Severity::Error, format!("{} blocks for a {}x{}x{} schematic, which holds {}", schematic.blocks.len(), schematic.width, schematic.height, schematic.length, volume));
+    }
+    check_indices(&schematic.blocks, schematic.palette.len(), "Block", &mut report);
+    if !schematic.biomes.is_empty() {
+        if schematic.biomes.len() != volume {
+            report.push(Severity::Error, format!("{} biomes for a schematic of {} blocks", schematic.biomes.len(), volume));
+        }
+        check_indices(&schematic.biomes, schematic.biome_palette.len(), "Biome", &mut report);
+    }
+
+    let mut seen: HashMap<String, &str> = HashMap::new();
+    for block_state in &schematic.palette {
+        let parsed = match BlockState::parse(block_state) {
+            Ok(parsed) => parsed,
+            Err(problem) => {
+                report.push(Severity::Error, format!("Unknown block id {}", problem));
+                continue;
+            }
+        };
+        if !parsed.name().starts_with("minecraft:") {
+            report.push(Severity::Info, format!("'{}' is not a vanilla block", parsed.name()));
+        }
+        #[cfg(feature = "registry")]
+        if let Some(problem) = registry_problem(schematic.data_version, &parsed) {
+            report.push(Severity::Warning, format!("'{}' is not valid in DataVersion {}: {}", block_state, schematic.data_version.unwrap_or_default(), problem));
+        }
+        if let Some(first) = seen.insert(parsed.to_string(), block_state) {
+            report.push(Severity::Warning, format!("Palette entries '{}' and '{}' are the same block state", first, block_state));
+        }
+    }
+
+    let size = [schematic.width, schematic.height, schematic.length];
+    for block_entity in &schematic.block_entities {
+        let id = block_entity.get::<_, &str>("Id").or_else(|_| block_entity.get::<_, &str>("id")).ok();
+        let Some(position) = block_entity_position(block_entity) else {
+            report.push(Severity::Error, format!("Block entity {} has no position", id.unwrap_or("without id")));
+            continue;
+        };
+        let Some(id) = id else {
+            report.push(Severity::Warning, format!("Block entity at {:?} has no id", position));
+            continue;
+        };
+        if position.iter().zip(size).any(|(&value, size)| value < 0 || value as usize >= size) {
+            report.push(Severity::Error, format!("Block entity {} at {:?} is outside the schematic", id, position));
+            continue;
+        }
+        let index = schematic.index(position[0] as usize, position[1] as usize, position[2] as usize);
+        let Some(block_state) = schematic.blocks.get(index).and_then(|&block| schematic.palette.get(block as usize)) else {
+            continue;
+        };
+        if is_air(block_state) {
+            report.push(Severity::Warning, format!("Block entity {} at {:?} sits on air", id, position));
+        } else if BlockState::parse(block_state).is_ok_and(|parsed| !can_hold(&parsed, id)) {
+            report.push(Severity::Warning, format!("Block entity {} at {:?} sits on {}, which cannot hold it", id, position, block_state));
+        }
+    }
+    report
+}
+
+fn check_indices(values: &[u32], palette_length: usize, what: &str, report: &mut ValidationReport) {
+    let out_of_range: Vec<u32> = values.iter().copied().filter(|&value| value as usize >= palette_length).collect();
+    if let Some(first) = out_of_range.first() {
+        report.push(Severity::Error, format!("{} palette index {} is out of range for a palette of {} ({} positions affected)", what, first, palette_length, out_of_range.len()));
+    }
+}
+
+/// Checks a vanilla block state against the registry of the schematic's release. Schematics
+/// without a DataVersion, from before the flattening or newer than the bundled registry are not
+/// checked.
+#[cfg(feature = "registry")]
+fn registry_problem(data_version: Option<i32>, block_state: &BlockState) -> Option<String> {
+    use crate::registry::BlockRegistry;
+    let data_version = data_version?;
+    if data_version > BlockRegistry::latest()?.data_version() || !block_state.name().starts_with("minecraft:") {
+        return None;
+    }
+    BlockRegistry::for_data_version(data_version)?.check_state(&block_state.to_string()).err()
+}
+
+fn validate_sponge_layout(nbt: &NbtCompound, report: &mut ValidationReport) {
+    // Version 3 is checked in the version 2 layout, with `BlockData` standing for `Blocks.Data`
+    let nbt = &*sponge_v2_layout(nbt);
+
+    let mut size = [0usize; 3];
+    for (axis, key) in ["Width", "Height", "Length"].into_iter().enumerate() {
+        match nbt.get::<_, &NbtTag>(key) {
+            Ok(NbtTag::Short(value)) if *value != 0 => size[axis] = *value as u16 as usize,
+            Ok(NbtTag::Int(value)) if *value > 0 => size[axis] = *value as usize,
+            Ok(tag) => report.push(Severity::Error, format!("{} is {}, which is not a positive size", key, tag)),
+            Err(_) => report.push(Severity::Error, format!("{} is missing", key)),
+        }
+    }
+
+    let Ok(palette) = nbt.get::<_, &NbtCompound>("Palette") else {
+        report.push(Severity::Error, "Palette is missing");
+        return;
+    };
+    let mut names_by_id: HashMap<i32, &str> = HashMap::new();
+    for (name, id) in palette.inner() {
+        match id {
+            NbtTag::Int(id) if *id >= 0 => {
+                if let Some(other) = names_by_id.insert(*id, name) {
+                    report.push(Severity::Error, format!("Palette entries '{}' and '{}' share id {}", other, name, id));
+                }
+            }
+            _ => report.push(Severity::Error, format!("Palette entry '{}' has id {}, which is not a valid id", name, id)),
+        }
+    }
+    if let Ok(palette_max) = nbt.get::<_, i32>("PaletteMax") {
+        if palette_max as usize != palette.len() {
+            report.push(Severity::Warning, format!("PaletteMax is {} but the palette has {} entries", palette_max, palette.len()));
+        }
+    }
+
+    let Ok(block_data) = nbt.get::<_, &[i8]>("BlockData") else {
+        report.push(Severity::Error, "BlockData is missing");
+        return;
+    };
+    if size.contains(&0) {
+        return;
+    }
+    match decode_varints(block_data, size.iter().product()) {
+        Ok(blocks) => {
+            let missing: Vec<u32> = blocks.iter().copied().filter(|block| !names_by_id.contains_key(&(*block as i32))).collect();
+            if let Some(first) = missing.first() {
+                report.push(Severity::Error, format!("BlockData references palette id {} which is not in the palette ({} blocks affected)", first, missing.len()));
+            }
+        }
+        Err(e) => report.push(Severity::Error, format!("BlockData does not match the {}x{}x{} size: {}", size[0], size[1], size[2], e)),
+    }
+}
+
+fn validate_litematic_layout(nbt: &NbtCompound, report: &mut ValidationReport) {
+    let enclosing_size = match nbt.get::<_, &NbtCompound>("Metadata") {
+        Ok(metadata) => match metadata.get::<_, &NbtCompound>("EnclosingSize") {
+            Ok(enclosing_size) => Some(["x", "y", "z"].map(|axis| enclosing_size.get::<_, i32>(axis).unwrap_or(0))),
+            Err(_) => {
+                report.push(Severity::Error, "Metadata has no EnclosingSize");
+                None
+            }
+        },
+        Err(_) => {
+            report.push(Severity::Error, "Metadata is missing");
+            None
+        }
+    };
+
+    let regions = match nbt.get::<_, &NbtCompound>("Regions") {
+        Ok(regions) if !regions.is_empty() => regions,
+        _ => {
+            report.push(Severity::Error, "There are no regions");
+            return;
+        }
+    };
+    let mut total_volume = 0i64;
+    for (name, region) in regions.inner() {
+        let NbtTag::Compound(region) = region else {
+            report.push(Severity::Error, format!("Region '{}' is not a compound", name));
+            continue;
+        };
+        if let Some(volume) = validate_region(name, region, enclosing_size, report) {
+            total_volume = total_volume.saturating_add(i64::try_from(volume).unwrap_or(i64::MAX));
+        }
+    }
+
+    if let Ok(metadata) = nbt.get::<_, &NbtCompound>("Metadata") {
+        if let Ok(declared) = metadata.get::<_, i32>("TotalVolume") {
+            if declared as i64 != total_volume && report.is_valid() {
+                report.push(Severity::Warning, format!("Metadata TotalVolume is {} but the regions hold {} blocks", declared, total_volume));
+            }
+        }
+    }
+}
+
+/// Checks one litematic region and returns its volume when its size is usable.
+fn validate_region(name: &str, region: &NbtCompound, enclosing_size: Option<[i32; 3]>, report: &mut ValidationReport) -> Option<usize> {
+    let size = match region.get::<_, &NbtCompound>("Size") {
+        Ok(size) => ["x", "y", "z"].map(|axis| size.get::<_, i32>(axis).unwrap_or(0)),
+        Err(_) => {
+            report.push(Severity::Error, format!("Region '{}' has no Size", name));
+            return None;
+        }
+    };
+    if size.contains(&0) {
+        report.push(Severity::Error, format!("Region '{}' has size {:?}, which holds no blocks", name, size));
+        return None;
+    }
+    if let Some(enclosing_size) = enclosing_size {
+        if size.iter().zip(enclosing_size).any(|(length, enclosing)| length.unsigned_abs() > enclosing.unsigned_abs()) {
+            report.push(Severity::Warning, format!("Region '{}' of size {:?} is larger than the enclosing size {:?}", name, size, enclosing_size));
+        }
+    }
+    if !region.contains_key("Position") {
+        report.push(Severity::Warning, format!("Region '{}' has no Position", name));
+    }
+    let Some(volume) = size.iter().try_fold(1usize, |volume, length| volume.checked_mul(length.unsigned_abs() as usize)) else {
+        report.push(Severity::Error, format!("Region '{}' has size {:?}, which holds more blocks than can be counted", name, size));
+        return None;
+    };
+
+    let palette = match region.get::<_, &NbtList>("BlockStatePalette") {
+        Ok(palette) if !palette.is_empty() => palette,
+        _ => {
+            report.push(Severity::Error, format!("Region '{}' has no BlockStatePalette", name));
+            return Some(volume);
+        }
+    };
+    let mut seen: Vec<&NbtTag> = Vec::new();
+    for (index, entry) in palette.iter().enumerate() {
+        match entry {
+            NbtTag::Compound(block_state) if block_state.get::<_, &str>("Name").is_ok() => {}
+            _ => report.push(Severity::Error, format!("Region '{}' palette entry {} has no Name", name, index)),
+        }
+        if let Some(first) = seen.iter().position(|other| *other == entry) {
+            report.push(Severity::Error, format!("Region '{}' palette entries {} and {} are the same block state", name, first, index));
+        }
+        seen.push(entry);
+    }
+
+    let Ok(block_states) = region.get::<_, &[i64]>("BlockStates") else {
+        report.push(Severity::Error, format!("Region '{}' has no BlockStates", name));
+        return Some(volume);
+    };
+    let bits_per_block = litematic_bits_per_block(palette.len());
+    let Some(expected) = volume.checked_mul(bits_per_block).map(|bits| bits.div_ceil(64)) else {
+        report.push(Severity::Error, format!("Region '{}' has {} blocks at {} bits, more than BlockStates can hold", name, volume, bits_per_block));
+        return Some(volume);
+    };
+    if block_states.len() < expected {
+        report.push(Severity::Error, format!("Region '{}' BlockStates has {} longs but {} blocks at {} bits need {}", name, block_states.len(), volume, bits_per_block, expected));
+        return Some(volume);
+    }
+    if block_states.len() > expected {
+        report.push(Severity::Warning, format!("Region '{}' BlockStates has {} longs where {} are needed", name, block_states.len(), expected));
+    }
+    if let Ok(indices) = unpack_block_states(block_states, bits_per_block, volume) {
+        check_indices(&indices, palette.len(), &format!("Region '{}' block", name), report);
+    }
+    Some(volume)
+}
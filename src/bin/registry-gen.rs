@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+use serde_json::Value;
+use schematic_converter::models::BlockState;
+use schematic_converter::operations::block_entity_of;
+use schematic_converter::registry::{newest_at_or_before, parse_registries, BlockInfo, BlockRegistry, Property};
+use schematic_converter::versions;
+
+const USAGE: &str = "Usage:
+  registry-gen <blocks.json> <release|data-version> [blocks.txt]
+
+Reads the block report of the vanilla data generator (`java -DbundlerMainClass=net.minecraft.data.Main
+-jar server.jar --reports`) and prints the section to append to blocks.txt, src/registry/blocks.txt by
+default: the blocks the release adds or changes compared to the newest release before it in the file,
+and the ones it removes. With no release before it, every block is printed.";
+
+type GenResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Reads the blocks of a `reports/blocks.json` file. The report says nothing about block
+/// entities or opacity, so those are taken from `known`. New blocks get a block entity when
+/// they match the crate's block entity table and are left not opaque.
+fn read_report(report: &Value, known: Option<&BlockRegistry>) -> GenResult<Vec<BlockInfo>> {
+    let blocks = report.as_object().ok_or("The report is not a JSON object")?;
+    let mut read = Vec::new();
+    for (id, block) in blocks {
+        let mut properties = Vec::new();
+        if let Some(listed) = block.get("properties").and_then(Value::as_object) {
+            let default_state = block.get("states").and_then(Value::as_array)
+                .and_then(|states| states.iter().find(|state| state.get("default").and_then(Value::as_bool) == Some(true)))
+                .and_then(|state| state.get("properties"))
+                .ok_or_else(|| format!("{} has no default state", id))?;
+            for (name, values) in listed {
+                let values: Vec<String> = values.as_array()
+                    .ok_or_else(|| format!("{} lists no values for {}", id, name))?
+                    .iter()
+                    .filter_map(|value| value.as_str().map(str::to_string))
+                    .collect();
+                let default_value = default_state.get(name).and_then(Value::as_str).unwrap_or_default();
+                let default = values.iter().position(|value| value == default_value).unwrap_or(0);
+                properties.push(Property { name: name.clone(), values, default });
+            }
+        }
+        properties.sort_by(|a, b| a.name.cmp(&b.name));
+        let previous = known.and_then(|known| known.block(id));
+        read.push(BlockInfo {
+            id: id.clone(),
+            properties,
+            has_block_entity: match previous {
+                Some(block) => block.has_block_entity,
+                None => block_entity_of(&BlockState::new(id)?).is_some(),
+            },
+            opaque: previous.is_some_and(|block| block.opaque),
+        });
+    }
+    Ok(read)
+}
+
+fn run(args: &[String]) -> GenResult<()> {
+    let (report_path, target, registry_path) = match args {
+        [report_path, target] => (report_path, target, "src/registry/blocks.txt"),
+        [report_path, target, registry_path] => (report_path, target, registry_path.as_str()),
+        _ => return Err(USAGE.into()),
+    };
+    let data_version = versions::parse_data_version(target).ok_or_else(|| format!("Unknown release or DataVersion: {}", target))?;
+    let registries = parse_registries(&std::fs::read_to_string(registry_path)?)?;
+    if registries.last().is_some_and(|newest| newest.data_version() >= data_version) {
+        return Err(format!("{} already has a section for DataVersion {} or later", registry_path, data_version).into());
+    }
+    let previous = newest_at_or_before(&registries, data_version - 1);
+    let report: Value = serde_json::from_slice(&std::fs::read(report_path)?)?;
+    let blocks: BTreeMap<String, BlockInfo> = read_report(&report, previous)?.into_iter().map(|block| (block.id.clone(), block)).collect();
+
+    println!("@version {}", data_version);
+    for block in previous.into_iter().flat_map(BlockRegistry::blocks).filter(|block| !blocks.contains_key(&block.id)) {
+        println!("-{}", block.id.strip_prefix("minecraft:").unwrap_or(&block.id));
+    }
+    let mut added = 0;
+    for block in blocks.values() {
+        match previous.and_then(|previous| previous.block(&block.id)) {
+            Some(known) if known == block => {}
+            Some(_) => println!("{}", block),
+            None => {
+                println!("{}", block);
+                added += 1;
+            }
+        }
+    }
+    if added > 0 {
+        eprintln!("{} new blocks are not marked opaque; add `o` where it applies", added);
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,481 @@
+use std::path::Path;
+use schematic_converter::models::Schematic;
+use schematic_converter::analysis::BoundingBox;
+use schematic_converter::operations::{self, Axis, ConflictPolicy, Patch, ReplaceOptions, ReplaceRule, Rotation};
+use schematic_converter::{analysis, render, versions, CompressionKind, ConvertOptions, SchematicConverter, SchematicFormat};
+
+const USAGE: &str = "Usage:
+  schematic-cli convert <input> <output> [--from <format>] [--to <format>] [--compression none|gzip|zlib] [--level <0-9>]
+  schematic-cli render <input> <output.png> [--from <format>] [--scale <pixels>] [--layer <y>]
+  schematic-cli render-layers <input> <output-dir> [--from <format>] [--scale <pixels>]
+  schematic-cli render-isometric <input> <output.png> [--from <format>] [--width <pixels>] [--height <pixels>]
+                                 [--yaw <degrees>] [--pitch <degrees>] [--resource-pack <pack.zip>]
+  schematic-cli materials <input> [<output>] [--from <format>] [--output-format csv|json] [--containers true|false]
+  schematic-cli stats <input> [--from <format>]
+  schematic-cli validate <input> [--from <format>] [--output-format text|json]
+  schematic-cli rotate <input> <output> [--from <format>] [--to <format>] [--degrees 90|180|270] [--axis x|y|z]
+  schematic-cli mirror <input> <output> [--from <format>] [--to <format>] [--axis x|z]
+  schematic-cli crop <input> <output> --min <x,y,z> --size <w,h,l> [--from <format>] [--to <format>]
+  schematic-cli trim <input> <output> [--filler <block>] [--from <format>] [--to <format>]
+  schematic-cli merge <output> <input>[@x,y,z]... [--to <format>] [--policy first|last|non-air|error]
+  schematic-cli diff <old> <new> [--offset <x,y,z>|--align true] [--output-format summary|json] [--highlight <diff-output>]
+  schematic-cli create-patch <old> <new> <patch> [--offset <x,y,z>|--align true]
+  schematic-cli apply-patch <base> <patch> <output> [--from <format>] [--to <format>]
+  schematic-cli resize <input> <output> --size <w,h,l> [--offset <x,y,z>] [--from <format>] [--to <format>]
+  schematic-cli replace <input> <output> --rule '<pattern> -> <replacement> [probability]'... [--seed <n>]
+                        [--mask <x,y,z>:<x,y,z>]... [--from <format>] [--to <format>]
+  schematic-cli upgrade <input> <output> --target <release|data-version> [--from <format>] [--to <format>]
+  schematic-cli downgrade <input> <output> --target <release|data-version> [--substitute '<pattern> -> <block>']...
+                          [--fallback <block>] [--from <format>] [--to <format>]
+
+Formats: litematic, schematic, schem (guessed from the file extension when omitted)";
+
+type CliResult = Result<(), Box<dyn std::error::Error>>;
+
+/// Positional arguments and `--name value` flags of a command.
+struct Args {
+    positional: Vec<String>,
+    flags: Vec<(String, String)>,
+}
+
+impl Args {
+    fn parse(raw: &[String]) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut positional = Vec::new();
+        let mut flags = Vec::new();
+        let mut iter = raw.iter();
+        while let Some(arg) = iter.next() {
+            match arg.strip_prefix("--") {
+                Some(name) => {
+                    let value = iter.next().ok_or_else(|| format!("Missing value for --{}", name))?;
+                    flags.push((name.to_string(), value.clone()));
+                }
+                None => positional.push(arg.clone()),
+            }
+        }
+        Ok(Args { positional, flags })
+    }
+
+    fn flag(&self, name: &str) -> Option<&str> {
+        self.flags.iter().rev().find(|(flag, _)| flag == name).map(|(_, value)| value.as_str())
+    }
+
+    /// All values of a flag that may be given more than once, in order.
+    fn flags(&self, name: &str) -> Vec<&str> {
+        self.flags.iter().filter(|(flag, _)| flag == name).map(|(_, value)| value.as_str()).collect()
+    }
+
+    fn positional(&self, index: usize, what: &str) -> Result<&str, Box<dyn std::error::Error>> {
+        self.positional.get(index).map(String::as_str).ok_or_else(|| format!("Missing {}\n\n{}", what, USAGE).into())
+    }
+
+    fn number<T: std::str::FromStr>(&self, name: &str, default: T) -> Result<T, Box<dyn std::error::Error>> {
+        match self.flag(name) {
+            Some(value) => value.parse().map_err(|_| format!("Invalid value for --{}: {}", name, value).into()),
+            None => Ok(default),
+        }
+    }
+}
+
+fn parse_format(name: &str) -> Result<SchematicFormat, Box<dyn std::error::Error>> {
+    SchematicFormat::from_extension(name).ok_or_else(|| format!("Unknown format: {}", name).into())
+}
+
+/// Uses the explicit `--<flag>` format if given, otherwise guesses it from the file extension.
+fn format_for(args: &Args, flag: &str, path: &str) -> Result<SchematicFormat, Box<dyn std::error::Error>> {
+    match args.flag(flag) {
+        Some(name) => parse_format(name),
+        None => {
+            let extension = Path::new(path).extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+            parse_format(extension).map_err(|_| format!("Cannot guess the format of {}, pass --{}", path, flag).into())
+        }
+    }
+}
+
+fn read_schematic(args: &Args, path: &str) -> Result<Schematic, Box<dyn std::error::Error>> {
+    Schematic::read(&std::fs::read(path)?, format_for(args, "from", path)?)
+}
+
+fn convert(args: &Args) -> CliResult {
+    let input = args.positional(0, "input file")?;
+    let output = args.positional(1, "output file")?;
+
+    let mut options = ConvertOptions::new().with_level(args.number("level", ConvertOptions::default().level)?);
+    if let Some(compression) = args.flag("compression") {
+        options = options.with_compression(match compression {
+            "none" => CompressionKind::None,
+            "gzip" => CompressionKind::Gzip,
+            "zlib" => CompressionKind::Zlib,
+            _ => return Err(format!("Unknown compression: {}", compression).into()),
+        });
+    }
+
+    let data = std::fs::read(input)?;
+    let (from, to) = (format_for(args, "from", input)?, format_for(args, "to", output)?);
+    // Ticks only fit in litematics and biomes only in Sponge schematics
+    if (from == SchematicFormat::Litematic) != (to == SchematicFormat::Litematic) {
+        for lost in Schematic::read(&data, from)?.lost_in(to) {
+            eprintln!("Warning: {} cannot be stored in the output and are dropped", lost);
+        }
+    }
+    let converted = SchematicConverter::new().convert_with_options(&data, from, to, &options)?;
+    std::fs::write(output, converted)?;
+    Ok(())
+}
+
+fn render_image(args: &Args) -> CliResult {
+    let schematic = read_schematic(args, args.positional(0, "input file")?)?;
+    let output = args.positional(1, "output file")?;
+    let scale = args.number("scale", 4)?;
+
+    let png = match args.flag("layer") {
+        Some(_) => render::render_layer(&schematic, args.number("layer", 0)?, scale)?,
+        None => render::render_top_down(&schematic, scale)?,
+    };
+    std::fs::write(output, png)?;
+    Ok(())
+}
+
+fn render_layers(args: &Args) -> CliResult {
+    let schematic = read_schematic(args, args.positional(0, "input file")?)?;
+    let output_dir = Path::new(args.positional(1, "output directory")?);
+
+    std::fs::create_dir_all(output_dir)?;
+    for (y, png) in render::render_layers(&schematic, args.number("scale", 4)?)?.into_iter().enumerate() {
+        std::fs::write(output_dir.join(format!("layer_{:03}.png", y)), png)?;
+    }
+    Ok(())
+}
+
+fn render_isometric(args: &Args) -> CliResult {
+    let schematic = read_schematic(args, args.positional(0, "input file")?)?;
+    let output = args.positional(1, "output file")?;
+
+    let defaults = render::IsometricOptions::default();
+    let options = render::IsometricOptions {
+        width: args.number("width", defaults.width)?,
+        height: args.number("height", defaults.height)?,
+        yaw: args.number("yaw", defaults.yaw)?,
+        pitch: args.number("pitch", defaults.pitch)?,
+    };
+
+    let resource_pack = match args.flag("resource-pack") {
+        #[cfg(feature = "resource-pack")]
+        Some(path) => Some(render::ResourcePack::from_zip(&std::fs::read(path)?)?),
+        #[cfg(not(feature = "resource-pack"))]
+        Some(_) => return Err("Resource packs need the resource-pack feature".into()),
+        None => None,
+    };
+
+    std::fs::write(output, render::render_isometric(&schematic, &options, resource_pack.as_ref())?)?;
+    Ok(())
+}
+
+fn materials(args: &Args) -> CliResult {
+    let schematic = read_schematic(args, args.positional(0, "input file")?)?;
+    let list = analysis::material_list(&schematic, args.number("containers", false)?);
+
+    let exported = match args.flag("output-format").unwrap_or("csv") {
+        "csv" => list.to_csv(),
+        "json" => list.to_json(),
+        other => return Err(format!("Unknown output format: {}", other).into()),
+    };
+    match args.positional.get(1) {
+        Some(output) => std::fs::write(output, exported)?,
+        None => print!("{}", exported),
+    }
+    Ok(())
+}
+
+fn stats(args: &Args) -> CliResult {
+    let schematic = read_schematic(args, args.positional(0, "input file")?)?;
+    println!("{}", analysis::schematic_stats(&schematic).to_json());
+    Ok(())
+}
+
+fn validate(args: &Args) -> CliResult {
+    let input = args.positional(0, "input file")?;
+    let report = analysis::validate(&std::fs::read(input)?, format_for(args, "from", input)?);
+    match args.flag("output-format").unwrap_or("text") {
+        "json" => println!("{}", report.to_json()),
+        "text" => {
+            for finding in &report.findings {
+                println!("{}: {}", finding.severity.as_str(), finding.message);
+            }
+            println!(
+                "{} errors, {} warnings, {} infos",
+                report.count(analysis::Severity::Error),
+                report.count(analysis::Severity::Warning),
+                report.count(analysis::Severity::Info)
+            );
+        }
+        other => return Err(format!("Unknown output format: {}", other).into()),
+    }
+    if !report.is_valid() {
+        return Err("The schematic is not valid".into());
+    }
+    Ok(())
+}
+
+fn parse_axis(name: &str) -> Result<Axis, Box<dyn std::error::Error>> {
+    Axis::from_name(name).ok_or_else(|| format!("Unknown axis: {}", name).into())
+}
+
+fn rotate(args: &Args) -> CliResult {
+    let output = args.positional(1, "output file")?;
+    let schematic = read_schematic(args, args.positional(0, "input file")?)?;
+
+    let degrees = args.number("degrees", 90)?;
+    let rotation = Rotation::from_degrees(degrees).ok_or_else(|| format!("Rotation must be a multiple of 90 degrees, got {}", degrees))?;
+    let axis = parse_axis(args.flag("axis").unwrap_or("y"))?;
+
+    let rotated = operations::rotate(&schematic, axis, rotation);
+    std::fs::write(output, rotated.write(format_for(args, "to", output)?, &ConvertOptions::default())?)?;
+    Ok(())
+}
+
+fn mirror(args: &Args) -> CliResult {
+    let output = args.positional(1, "output file")?;
+    let schematic = read_schematic(args, args.positional(0, "input file")?)?;
+    let mirrored = operations::mirror(&schematic, parse_axis(args.flag("axis").unwrap_or("x"))?);
+    std::fs::write(output, mirrored.write(format_for(args, "to", output)?, &ConvertOptions::default())?)?;
+    Ok(())
+}
+
+/// Parses a `--<name> x,y,z` flag.
+fn triple<T: std::str::FromStr + Copy + Default>(args: &Args, name: &str) -> Result<Option<[T; 3]>, Box<dyn std::error::Error>> {
+    let Some(value) = args.flag(name) else {
+        return Ok(None);
+    };
+    let invalid = || format!("Invalid value for --{}: {}, expected x,y,z", name, value);
+    let parts: Vec<T> = value.split(',').map(|part| part.trim().parse().map_err(|_| invalid())).collect::<Result<_, _>>()?;
+    match parts[..] {
+        [x, y, z] => Ok(Some([x, y, z])),
+        _ => Err(invalid().into()),
+    }
+}
+
+fn write_cropped(args: &Args, output: &str, (schematic, report): (Schematic, operations::CropReport)) -> CliResult {
+    if !report.dropped_block_entities.is_empty() || !report.dropped_entities.is_empty() || !report.dropped_ticks.is_empty() {
+        eprintln!(
+            "Dropped {} block entities, {} entities and {} pending ticks outside the new bounds",
+            report.dropped_block_entities.len(),
+            report.dropped_entities.len(),
+            report.dropped_ticks.len()
+        );
+    }
+    std::fs::write(output, schematic.write(format_for(args, "to", output)?, &ConvertOptions::default())?)?;
+    Ok(())
+}
+
+fn crop(args: &Args) -> CliResult {
+    let output = args.positional(1, "output file")?;
+    let schematic = read_schematic(args, args.positional(0, "input file")?)?;
+    let min = triple(args, "min")?.ok_or("Missing --min")?;
+    let size = triple(args, "size")?.ok_or("Missing --size")?;
+    write_cropped(args, output, operations::crop(&schematic, min, size)?)
+}
+
+fn trim(args: &Args) -> CliResult {
+    let output = args.positional(1, "output file")?;
+    let schematic = read_schematic(args, args.positional(0, "input file")?)?;
+    write_cropped(args, output, operations::trim(&schematic, args.flag("filler"))?)
+}
+
+fn resize(args: &Args) -> CliResult {
+    let output = args.positional(1, "output file")?;
+    let schematic = read_schematic(args, args.positional(0, "input file")?)?;
+    let size = triple(args, "size")?.ok_or("Missing --size")?;
+    let offset = triple(args, "offset")?.unwrap_or_default();
+    write_cropped(args, output, operations::resize(&schematic, offset, size))
+}
+
+fn replace(args: &Args) -> CliResult {
+    let output = args.positional(1, "output file")?;
+    let schematic = read_schematic(args, args.positional(0, "input file")?)?;
+    let rules = args.flags("rule").into_iter().map(ReplaceRule::parse).collect::<Result<Vec<_>, _>>()?;
+    if rules.is_empty() {
+        return Err("Missing --rule".into());
+    }
+
+    let mut mask = Vec::new();
+    for value in args.flags("mask") {
+        let invalid = || format!("Invalid value for --mask: {}, expected x,y,z:x,y,z", value);
+        let corner = |corner: &str| -> Result<[usize; 3], String> {
+            let parts: Vec<usize> = corner.split(',').map(|part| part.trim().parse().map_err(|_| invalid())).collect::<Result<_, _>>()?;
+            parts.try_into().map_err(|_| invalid())
+        };
+        let (a, b) = value.split_once(':').ok_or_else(invalid)?;
+        let (a, b) = (corner(a)?, corner(b)?);
+        mask.push(BoundingBox { min: [0, 1, 2].map(|axis| a[axis].min(b[axis])), max: [0, 1, 2].map(|axis| a[axis].max(b[axis])) });
+    }
+
+    let options = ReplaceOptions { seed: args.number("seed", 0)?, mask };
+    let (replaced, count) = operations::replace_blocks(&schematic, &rules, &options);
+    println!("Replaced {} blocks", count);
+    std::fs::write(output, replaced.write(format_for(args, "to", output)?, &ConvertOptions::default())?)?;
+    Ok(())
+}
+
+fn upgrade(args: &Args) -> CliResult {
+    let output = args.positional(1, "output file")?;
+    let schematic = read_schematic(args, args.positional(0, "input file")?)?;
+    let target = args.flag("target").ok_or("Missing --target")?;
+    let target = versions::parse_data_version(target).ok_or_else(|| format!("Unknown version: {}", target))?;
+
+    let (upgraded, report) = versions::upgrade(&schematic, target)?;
+    println!("Upgraded from DataVersion {} to {}", report.from, report.to);
+    for name in &report.applied {
+        println!("  applied {}", name);
+    }
+    println!("{} block states and {} block entities changed", report.block_states, report.block_entities);
+    std::fs::write(output, upgraded.write(format_for(args, "to", output)?, &ConvertOptions::default())?)?;
+    Ok(())
+}
+
+fn downgrade(args: &Args) -> CliResult {
+    let output = args.positional(1, "output file")?;
+    let schematic = read_schematic(args, args.positional(0, "input file")?)?;
+    let target = args.flag("target").ok_or("Missing --target")?;
+    let target = versions::parse_data_version(target).ok_or_else(|| format!("Unknown version: {}", target))?;
+
+    let mut options = versions::DowngradeOptions {
+        substitutes: args.flags("substitute").into_iter().map(ReplaceRule::parse).collect::<Result<Vec<_>, _>>()?,
+        ..Default::default()
+    };
+    if let Some(fallback) = args.flag("fallback") {
+        options.fallback = fallback.to_string();
+    }
+
+    let (downgraded, report) = versions::downgrade(&schematic, target, &options)?;
+    println!("Downgraded from DataVersion {} to {}", report.from, report.to);
+    for substitution in &report.substitutions {
+        let reason = match substitution.reason {
+            versions::SubstitutionReason::DataFix => "renamed",
+            versions::SubstitutionReason::Properties => "properties",
+            versions::SubstitutionReason::Substitute => "substitute",
+            versions::SubstitutionReason::Fallback => "fallback",
+        };
+        println!("  {:>8}  {} -> {} ({})", substitution.count, substitution.original, substitution.replacement, reason);
+    }
+    if !report.dropped_block_entities.is_empty() {
+        eprintln!("Dropped {} block entities the target does not know", report.dropped_block_entities.len());
+    }
+    std::fs::write(output, downgraded.write(format_for(args, "to", output)?, &ConvertOptions::default())?)?;
+    Ok(())
+}
+
+fn merge(args: &Args) -> CliResult {
+    let output = args.positional(0, "output file")?;
+    let policy = args.flag("policy").unwrap_or("non-air");
+    let policy = ConflictPolicy::from_name(policy).ok_or_else(|| format!("Unknown conflict policy: {}", policy))?;
+
+    let mut placements = Vec::new();
+    for input in args.positional.iter().skip(1) {
+        let (path, offset) = match input.rsplit_once('@') {
+            Some((path, offset)) => {
+                let offset: Vec<i32> = offset.split(',').map(|part| part.trim().parse()).collect::<Result<_, _>>()
+                    .map_err(|_| format!("Invalid offset in {}, expected path@x,y,z", input))?;
+                match offset[..] {
+                    [x, y, z] => (path, [x, y, z]),
+                    _ => return Err(format!("Invalid offset in {}, expected path@x,y,z", input).into()),
+                }
+            }
+            None => (input.as_str(), [0, 0, 0]),
+        };
+        placements.push((read_schematic(args, path)?, offset));
+    }
+    if placements.is_empty() {
+        return Err(format!("Missing input files\n\n{}", USAGE).into());
+    }
+
+    let placements: Vec<(&Schematic, [i32; 3])> = placements.iter().map(|(schematic, offset)| (schematic, *offset)).collect();
+    let merged = operations::merge(&placements, policy)?;
+    std::fs::write(output, merged.write(format_for(args, "to", output)?, &ConvertOptions::default())?)?;
+    Ok(())
+}
+
+fn diff(args: &Args) -> CliResult {
+    let old_path = args.positional(0, "old file")?;
+    let new_path = args.positional(1, "new file")?;
+    let old = read_schematic(args, old_path)?;
+    let new = read_schematic(args, new_path)?;
+
+    let offset = match triple(args, "offset")? {
+        Some(offset) => offset,
+        None if args.number("align", false)? => analysis::align_by_bounds(&old, &new),
+        None => [0, 0, 0],
+    };
+    let diff = analysis::diff(&old, &new, offset);
+
+    match args.flag("output-format").unwrap_or("summary") {
+        "summary" => {
+            let summary = diff.summary();
+            println!("{} added, {} removed, {} changed blocks", summary.added, summary.removed, summary.changed);
+            println!("{} block entity changes, {} entity changes", summary.block_entities, summary.entities);
+        }
+        "json" => println!("{}", diff.to_json()),
+        other => return Err(format!("Unknown output format: {}", other).into()),
+    }
+    if let Some(highlight) = args.flag("highlight") {
+        std::fs::write(highlight, diff.to_schematic().write(format_for(args, "to", highlight)?, &ConvertOptions::default())?)?;
+    }
+    Ok(())
+}
+
+fn create_patch(args: &Args) -> CliResult {
+    let old = read_schematic(args, args.positional(0, "old file")?)?;
+    let new = read_schematic(args, args.positional(1, "new file")?)?;
+    let output = args.positional(2, "patch file")?;
+
+    let offset = match triple(args, "offset")? {
+        Some(offset) => offset,
+        None if args.number("align", false)? => analysis::align_by_bounds(&old, &new),
+        None => [0, 0, 0],
+    };
+    let patch = Patch::create(&old, &new, offset);
+    println!("{} block changes", patch.blocks.len());
+    std::fs::write(output, patch.to_bytes()?)?;
+    Ok(())
+}
+
+fn apply_patch(args: &Args) -> CliResult {
+    let base = read_schematic(args, args.positional(0, "base file")?)?;
+    let patch = Patch::from_bytes(&std::fs::read(args.positional(1, "patch file")?)?)?;
+    let output = args.positional(2, "output file")?;
+    std::fs::write(output, patch.apply(&base)?.write(format_for(args, "to", output)?, &ConvertOptions::default())?)?;
+    Ok(())
+}
+
+fn main() {
+    let raw: Vec<String> = std::env::args().skip(1).collect();
+    let result = match raw.split_first() {
+        Some((command, rest)) => Args::parse(rest).and_then(|args| match command.as_str() {
+            "convert" => convert(&args),
+            "render" => render_image(&args),
+            "render-layers" => render_layers(&args),
+            "render-isometric" => render_isometric(&args),
+            "materials" => materials(&args),
+            "stats" => stats(&args),
+            "validate" => validate(&args),
+            "rotate" => rotate(&args),
+            "mirror" => mirror(&args),
+            "crop" => crop(&args),
+            "trim" => trim(&args),
+            "resize" => resize(&args),
+            "replace" => replace(&args),
+            "upgrade" => upgrade(&args),
+            "downgrade" => downgrade(&args),
+            "merge" => merge(&args),
+            "diff" => diff(&args),
+            "create-patch" => create_patch(&args),
+            "apply-patch" => apply_patch(&args),
+            _ => Err(format!("Unknown command: {}\n\n{}", command, USAGE).into()),
+        }),
+        None => Err(USAGE.into()),
+    };
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
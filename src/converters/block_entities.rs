@@ -0,0 +1,72 @@
+use quartz_nbt::{NbtCompound, NbtList, NbtTag};
+
+/// Reads the block entities of Sponge NBT, which version 1 stores as `TileEntities` and
+/// version 2 as `BlockEntities`.
+pub(crate) fn sponge_block_entities(nbt: &NbtCompound) -> Vec<NbtCompound> {
+    ["BlockEntities", "TileEntities"].into_iter()
+        .filter_map(|key| nbt.get::<_, &NbtList>(key).ok())
+        .flat_map(|list| list.iter())
+        .filter_map(|tag| match tag {
+            NbtTag::Compound(compound) => Some(compound.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Converts a block entity to the Sponge v2 layout: `Pos` as an int array and `Id`, with the
+/// rest of the data next to them. Litematica tile entities keep `x`, `y`, `z` and `id`
+/// inside the tag, and Sponge v3 nests the data in `Data`.
+pub(crate) fn to_sponge_block_entity(block_entity: &NbtCompound) -> NbtCompound {
+    let mut converted = flatten_data(block_entity);
+    let inner = converted.inner_mut();
+    if !inner.contains_key("Pos") {
+        if let (Some(NbtTag::Int(x)), Some(NbtTag::Int(y)), Some(NbtTag::Int(z))) = (inner.get("x"), inner.get("y"), inner.get("z")) {
+            let position = vec![*x, *y, *z];
+            for key in ["x", "y", "z"] {
+                inner.remove(key);
+            }
+            inner.insert("Pos".to_string(), NbtTag::IntArray(position));
+        }
+    }
+    if !inner.contains_key("Id") {
+        if let Some(id) = inner.remove("id") {
+            inner.insert("Id".to_string(), id);
+        }
+    }
+    converted
+}
+
+/// Converts a block entity to the Litematica layout, the inverse of `to_sponge_block_entity`.
+pub(crate) fn to_litematic_tile_entity(block_entity: &NbtCompound) -> NbtCompound {
+    let mut converted = flatten_data(block_entity);
+    let inner = converted.inner_mut();
+    if let Some(NbtTag::IntArray(position)) = inner.get("Pos") {
+        if let [x, y, z] = position[..] {
+            inner.remove("Pos");
+            inner.insert("x".to_string(), NbtTag::Int(x));
+            inner.insert("y".to_string(), NbtTag::Int(y));
+            inner.insert("z".to_string(), NbtTag::Int(z));
+        }
+    }
+    if let Some(id) = inner.remove("Id") {
+        inner.insert("id".to_string(), id);
+    }
+    converted
+}
+
+/// Moves the contents of a Sponge v3 `Data` compound up into the block entity or entity itself.
+pub(super) fn flatten_data(block_entity: &NbtCompound) -> NbtCompound {
+    let mut flattened = block_entity.clone();
+    if let Some(NbtTag::Compound(data)) = flattened.inner_mut().remove("Data") {
+        for (key, value) in data.inner() {
+            if !flattened.contains_key(key) {
+                flattened.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    flattened
+}
+
+pub(crate) fn compound_list(compounds: impl IntoIterator<Item = NbtCompound>) -> NbtTag {
+    NbtTag::List(NbtList::from(compounds.into_iter().map(NbtTag::Compound).collect::<Vec<_>>()))
+}
@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+use quartz_nbt::{NbtCompound, NbtList, NbtTag};
+use super::block_entities::flatten_data;
+
+/// Converts an entity to the Sponge v2 layout: `Id` and `Pos` with the rest of the data next
+/// to them. Litematica keeps the vanilla lowercase `id`, and Sponge v3 nests the data in `Data`.
+pub(crate) fn to_sponge_entity(entity: &NbtCompound) -> NbtCompound {
+    let mut converted = flatten_data(entity);
+    let inner = converted.inner_mut();
+    // The vanilla data in `Data` repeats the id, which `Id` already holds
+    if let Some(id) = inner.remove("id") {
+        inner.entry("Id".to_string()).or_insert(id);
+    }
+    converted
+}
+
+/// Converts an entity to the Litematica layout, the inverse of `to_sponge_entity`.
+pub(crate) fn to_litematic_entity(entity: &NbtCompound) -> NbtCompound {
+    let mut converted = flatten_data(entity);
+    let inner = converted.inner_mut();
+    if let Some(id) = inner.remove("Id") {
+        inner.insert("id".to_string(), id);
+    }
+    converted
+}
+
+/// Moves entities by `offset`, shifting `Pos` and the block positions hanging entities and
+/// leashes point at.
+///
+/// Block positions are not always in the frame of `Pos`: Litematica and WorldEdit keep the
+/// world coordinates of `TileX`/`TileY`/`TileZ` and leashes. The world offset is recovered from
+/// the hanging entities, whose tile is the block their `Pos` sits in, and removed so every
+/// position ends up in the same frame.
+pub(crate) fn rebase_entities(entities: &[NbtCompound], offset: [i32; 3]) -> Vec<NbtCompound> {
+    let frame = block_position_frame(entities);
+    entities.iter().map(|entity| rebase_entity(entity, offset, frame)).collect()
+}
+
+/// Returns the most common difference between the tile of a hanging entity and the block its
+/// `Pos` is in, or zero when there are no hanging entities.
+fn block_position_frame(entities: &[NbtCompound]) -> [i32; 3] {
+    let mut differences: BTreeMap<[i32; 3], usize> = BTreeMap::new();
+    for entity in entities {
+        if let (Some(position), Some(tile)) = (entity_position(entity), tile_position(entity)) {
+            let difference = [0, 1, 2].map(|axis| tile[axis] - position[axis].floor() as i32);
+            *differences.entry(difference).or_default() += 1;
+        }
+    }
+    differences.into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(difference, _)| difference)
+        .unwrap_or([0, 0, 0])
+}
+
+fn rebase_entity(entity: &NbtCompound, offset: [i32; 3], frame: [i32; 3]) -> NbtCompound {
+    let mut rebased = entity.clone();
+    let shift = [0, 1, 2].map(|axis| offset[axis] - frame[axis]);
+    if let Some(position) = entity_position(entity) {
+        let moved: Vec<NbtTag> = (0..3).map(|axis| NbtTag::Double(position[axis] + offset[axis] as f64)).collect();
+        rebased.insert("Pos", NbtTag::List(NbtList::from(moved)));
+    }
+    if let Some(tile) = tile_position(entity) {
+        for (axis, key) in ["TileX", "TileY", "TileZ"].into_iter().enumerate() {
+            rebased.insert(key, NbtTag::Int(tile[axis] + shift[axis]));
+        }
+    }
+    // Leashes were a compound of X, Y and Z before 1.20.5 and an int array since
+    if let Some(NbtTag::Compound(leash)) = rebased.inner_mut().get_mut("Leash") {
+        for (axis, key) in ["X", "Y", "Z"].into_iter().enumerate() {
+            if let Some(NbtTag::Int(value)) = leash.inner_mut().get_mut(key) {
+                *value += shift[axis];
+            }
+        }
+    }
+    if let Some(NbtTag::IntArray(leash)) = rebased.inner_mut().get_mut("leash") {
+        if leash.len() == 3 {
+            for (axis, value) in leash.iter_mut().enumerate() {
+                *value += shift[axis];
+            }
+        }
+    }
+    if let Ok(passengers) = entity.get::<_, &NbtList>("Passengers") {
+        let passengers: Vec<NbtTag> = passengers.iter()
+            .map(|passenger| match passenger {
+                NbtTag::Compound(passenger) => NbtTag::Compound(rebase_entity(passenger, offset, frame)),
+                other => other.clone(),
+            })
+            .collect();
+        rebased.insert("Passengers", NbtTag::List(NbtList::from(passengers)));
+    }
+    rebased
+}
+
+fn entity_position(entity: &NbtCompound) -> Option<[f64; 3]> {
+    let coordinates: Vec<f64> = entity.get::<_, &NbtList>("Pos").ok()?
+        .iter()
+        .map(|coordinate| match coordinate {
+            NbtTag::Double(value) => Some(*value),
+            NbtTag::Float(value) => Some(*value as f64),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    coordinates.try_into().ok()
+}
+
+fn tile_position(entity: &NbtCompound) -> Option<[i32; 3]> {
+    Some([
+        entity.get::<_, i32>("TileX").ok()?,
+        entity.get::<_, i32>("TileY").ok()?,
+        entity.get::<_, i32>("TileZ").ok()?,
+    ])
+}
@@ -1,25 +1,61 @@
 use std::io::{Cursor, Read, Write};
 use log::debug;
-use quartz_nbt::{NbtCompound, NbtTag};
+use quartz_nbt::{NbtCompound, NbtList, NbtTag};
 use quartz_nbt::io::Flavor;
+use super::block_entities::{compound_list, to_sponge_block_entity};
+use super::entities::{rebase_entities, to_sponge_entity};
+use super::offset::set_sponge_offset;
+use super::PENDING_TICK_KEYS;
+use super::options::{read_decompressed, write_compressed, CompressionKind, ConvertOptions};
+use super::packing::{encode_varints, litematic_bits_per_block, unpack_block_states};
+use crate::models::BlockState;
+use crate::render::argb_to_png;
 
-pub fn litematic_to_schematic<R: Read, W: Write>( input: R, mut output: W) -> Result<(), Box<dyn std::error::Error>> {
-    let mut decoder = flate2::read::GzDecoder::new(input);
-    let mut decompressed = Vec::new();
-    std::io::copy(&mut decoder, &mut decompressed)?;
+pub fn litematic_to_schematic<R: Read, W: Write>(input: R, output: W) -> Result<(), Box<dyn std::error::Error>> {
+    litematic_to_schematic_with_options(input, output, &ConvertOptions::default())
+}
+
+pub fn litematic_to_schematic_with_options<R: Read, W: Write>(input: R, output: W, options: &ConvertOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let decompressed = read_decompressed(input)?;
 
     let (litematic_nbt, _) = quartz_nbt::io::read_nbt(&mut Cursor::new(decompressed), Flavor::Uncompressed)?;
 
+    let mut schematic_nbt = litematic_nbt_to_sponge(&litematic_nbt)?;
+    // Sponge schematics have no tick lists
+    for key in PENDING_TICK_KEYS {
+        schematic_nbt.inner_mut().remove(key);
+    }
+
+    let mut schematic_data = Vec::new();
+    quartz_nbt::io::write_nbt(&mut schematic_data, None, &schematic_nbt, Flavor::Uncompressed)?;
+    write_compressed(output, &schematic_data, options.compression_or(CompressionKind::None), options.level)?;
+    Ok(())
+}
+
+/// Builds Sponge NBT from litematic NBT. The pending ticks of the region are kept under their
+/// Litematica keys so the model can pick them up; they are not part of the Sponge format.
+pub(crate) fn litematic_nbt_to_sponge(litematic_nbt: &NbtCompound) -> Result<NbtCompound, Box<dyn std::error::Error>> {
     let mut schematic_nbt = NbtCompound::new();
     debug!("litematic: {:?}", litematic_nbt);
-    convert_metadata_to_schematic(&litematic_nbt, &mut schematic_nbt)?;
-    convert_palette_to_schematic(&litematic_nbt, &mut schematic_nbt)?;
-    unpack_block_data_to_schematic(&litematic_nbt, &mut schematic_nbt)?;
-    convert_entities_to_schematic(&litematic_nbt, &mut schematic_nbt)?;
+    convert_metadata_to_schematic(litematic_nbt, &mut schematic_nbt)?;
+    convert_palette_to_schematic(litematic_nbt, &mut schematic_nbt)?;
+    unpack_block_data_to_schematic(litematic_nbt, &mut schematic_nbt)?;
+    convert_entities_to_schematic(litematic_nbt, &mut schematic_nbt)?;
+    convert_position_to_schematic(litematic_nbt, &mut schematic_nbt);
+    Ok(schematic_nbt)
+}
 
-    quartz_nbt::io::write_nbt(&mut output, None, &schematic_nbt, quartz_nbt::io::Flavor::Uncompressed)?;
-    //debug print the output
-    Ok(())
+/// Extracts the `PreviewImageData` thumbnail of a litematic as PNG bytes, if it has one.
+pub fn litematic_preview_png<R: Read>(input: R) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+    let decompressed = read_decompressed(input)?;
+    let (litematic_nbt, _) = quartz_nbt::io::read_nbt(&mut Cursor::new(decompressed), Flavor::Uncompressed)?;
+
+    let metadata = litematic_nbt.get::<_, &NbtCompound>("Metadata")
+        .map_err(|_| "Missing or invalid Metadata")?;
+    match metadata.get::<_, &NbtTag>("PreviewImageData") {
+        Ok(NbtTag::IntArray(preview)) if !preview.is_empty() => Ok(Some(argb_to_png(preview)?)),
+        _ => Ok(None),
+    }
 }
 
 fn convert_metadata_to_schematic(litematic: &NbtCompound, schematic: &mut NbtCompound) -> Result<(), Box<dyn std::error::Error>> {
@@ -32,12 +68,34 @@ fn convert_metadata_to_schematic(litematic: &NbtCompound, schematic: &mut NbtCom
             return Err("Missing EnclosingSize in Metadata".into());
         }
 
+        // Sponge keeps name, author and creation date in its Metadata compound
+        let mut sponge_metadata = NbtCompound::new();
+
+        if let Ok(NbtTag::String(name)) = metadata.get::<_, &NbtTag>("Name") {
+            sponge_metadata.insert("Name", NbtTag::String(name.clone()));
+        }
+
         if let Ok(NbtTag::String(author)) = metadata.get::<_, &NbtTag>("Author") {
             schematic.insert("Author", NbtTag::String(author.clone()));
+            sponge_metadata.insert("Author", NbtTag::String(author.clone()));
         }
 
         if let Ok(NbtTag::String(description)) = metadata.get::<_, &NbtTag>("Description") {
             schematic.insert("Description", NbtTag::String(description.clone()));
+            sponge_metadata.insert("Description", NbtTag::String(description.clone()));
+        }
+
+        if let Ok(NbtTag::Long(time_created)) = metadata.get::<_, &NbtTag>("TimeCreated") {
+            sponge_metadata.insert("Date", NbtTag::Long(*time_created));
+        }
+
+        // Sponge has no thumbnail, so keep Litematica's preview in the free-form Metadata compound
+        if let Ok(NbtTag::IntArray(preview)) = metadata.get::<_, &NbtTag>("PreviewImageData") {
+            sponge_metadata.insert("PreviewImageData", NbtTag::IntArray(preview.clone()));
+        }
+
+        if !sponge_metadata.is_empty() {
+            schematic.insert("Metadata", NbtTag::Compound(sponge_metadata));
         }
     } else {
         return Err("Missing or invalid Metadata".into());
@@ -60,10 +118,9 @@ fn convert_palette_to_schematic(litematic: &NbtCompound, schematic: &mut NbtComp
                 for (i, block_state) in block_state_palette.iter().enumerate() {
                     if let NbtTag::Compound(block_state_compound) = block_state {
                         if let Ok(Some(NbtTag::String(name))) = block_state_compound.get("Name") {
-                            let mut full_name = name.clone();
+                            let mut full_name = BlockState::new(name).map_err(|e| format!("Palette entry {}", e))?;
                             if let Ok(Some(NbtTag::Compound(properties))) = block_state_compound.get("Properties") {
                                 debug!("properties: {:?}", properties);
-                                full_name.push('[');
                                 for (key, value) in properties.inner().iter() {
                                     let value_str = match value {
                                         NbtTag::String(s) => s.clone(),
@@ -75,12 +132,10 @@ fn convert_palette_to_schematic(litematic: &NbtCompound, schematic: &mut NbtComp
                                         NbtTag::Double(d) => d.to_string(),
                                         _ => return Err("Unexpected property value type".into()),
                                     };
-                                    full_name.push_str(&format!("{}={},", key, value_str));
+                                    full_name.set_property(key, &value_str).map_err(|e| format!("Palette entry {} has {}", name, e))?;
                                 }
-                                full_name.pop(); // Remove last comma
-                                full_name.push(']');
                             }
-                            schematic_palette.insert(full_name, NbtTag::Int(i as i32));
+                            schematic_palette.insert(full_name.to_string(), NbtTag::Int(i as i32));
                         }
                     }
                 }
@@ -122,47 +177,13 @@ fn unpack_block_data_to_schematic(litematic: &NbtCompound, schematic: &mut NbtCo
 
                 // Process blocks
                 if let Ok(NbtTag::LongArray(block_states)) = region.get::<_, &NbtTag>("BlockStates") {
-                    let bits_per_block = if palette_length <= 1 {
-                        1
-                    } else {
-                        std::cmp::max((palette_length as f64).log2().ceil() as usize, 1)
+                    let litematic_palette_length = match region.get::<_, &NbtTag>("BlockStatePalette") {
+                        Ok(NbtTag::List(block_state_palette)) => block_state_palette.len(),
+                        _ => palette_length,
                     };
-                    let mask = (1u64 << bits_per_block) - 1;
-
-                    let mut block_data = Vec::new();
-                    let mut bit_buffer = 0u64;
-                    let mut bits_in_buffer = 0;
-                    let mut block_states_iter = block_states.iter();
-
-                    for _ in 0..volume {
-                        while bits_in_buffer < bits_per_block {
-                            match block_states_iter.next() {
-                                Some(&long_value) => {
-                                    bit_buffer |= (long_value as u64) << bits_in_buffer;
-                                    bits_in_buffer += 64;
-                                }
-                                None => return Err("BlockStates array is too short.".into()),
-                            }
-                        }
-
-                        let block_state_index = bit_buffer & mask;
-                        bit_buffer >>= bits_per_block;
-                        bits_in_buffer -= bits_per_block;
-
-                        // Encode block_state_index as VarInt
-                        let mut varint = block_state_index;
-                        loop {
-                            let mut byte = (varint & 0x7F) as u8;
-                            varint >>= 7;
-                            if varint != 0 {
-                                byte |= 0x80;
-                            }
-                            block_data.push(byte as i8);
-                            if varint == 0 {
-                                break;
-                            }
-                        }
-                    }
+                    let bits_per_block = litematic_bits_per_block(litematic_palette_length);
+                    let block_state_indices = unpack_block_states(block_states, bits_per_block, volume)?;
+                    let block_data = encode_varints(&block_state_indices);
 
                     schematic.insert("BlockData", NbtTag::ByteArray(block_data));
                 } else {
@@ -188,13 +209,53 @@ fn unpack_block_data_to_schematic(litematic: &NbtCompound, schematic: &mut NbtCo
 fn convert_entities_to_schematic(litematic: &NbtCompound, schematic: &mut NbtCompound) -> Result<(), Box<dyn std::error::Error>> {
     if let Ok(NbtTag::Compound(regions)) = litematic.get::<_, &NbtTag>("Regions") {
         if let Some(NbtTag::Compound(region)) = regions.inner().values().next() {
-            if let Ok(Some(entities)) = region.get("Entities") {
-                schematic.insert("Entities", entities.clone());
+            if let Ok(entities) = region.get::<_, &NbtList>("Entities") {
+                let entities: Vec<NbtCompound> = entities.iter().filter_map(|tag| match tag {
+                    NbtTag::Compound(entity) => Some(to_sponge_entity(entity)),
+                    _ => None,
+                }).collect();
+                schematic.insert("Entities", compound_list(rebase_entities(&entities, region_origin_offset(region))));
+            }
+            if let Ok(tile_entities) = region.get::<_, &NbtList>("TileEntities") {
+                let block_entities = tile_entities.iter().filter_map(|tag| match tag {
+                    NbtTag::Compound(tile_entity) => Some(to_sponge_block_entity(tile_entity)),
+                    _ => None,
+                });
+                schematic.insert("BlockEntities", compound_list(block_entities));
             }
-            if let Ok(Some(tile_entities)) = region.get("TileEntities") {
-                schematic.insert("TileEntities", tile_entities.clone());
+            // Ticks use x, y and z from the minimum corner, like tile entities
+            for key in PENDING_TICK_KEYS {
+                if let Ok(ticks) = region.get::<_, &NbtList>(key) {
+                    schematic.insert(key, NbtTag::List(ticks.clone()));
+                }
             }
         }
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Litematica places the region `Position` relative to the placement origin, which is what the
+/// Sponge and WorldEdit offsets hold for the minimum corner.
+fn convert_position_to_schematic(litematic: &NbtCompound, schematic: &mut NbtCompound) {
+    if let Ok(NbtTag::Compound(regions)) = litematic.get::<_, &NbtTag>("Regions") {
+        if let Some(NbtTag::Compound(region)) = regions.inner().values().next() {
+            let position = region.get::<_, &NbtCompound>("Position").ok();
+            let origin_offset = region_origin_offset(region);
+            let offset = [("x", 0), ("y", 1), ("z", 2)].map(|(axis, index)| {
+                position.and_then(|position| position.get::<_, i32>(axis).ok()).unwrap_or(0) - origin_offset[index]
+            });
+            set_sponge_offset(schematic, offset);
+        }
+    }
+}
+
+/// Returns where the region `Position` lies relative to the minimum corner of the region.
+/// Block data and tile entities start at the minimum corner, but entity positions are relative
+/// to `Position`, which is the far corner along any axis with a negative `Size`.
+fn region_origin_offset(region: &NbtCompound) -> [i32; 3] {
+    let size = region.get::<_, &NbtCompound>("Size").ok();
+    ["x", "y", "z"].map(|axis| match size.and_then(|size| size.get::<_, i32>(axis).ok()) {
+        Some(length) if length < 0 => -length - 1,
+        _ => 0,
+    })
+}
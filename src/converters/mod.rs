@@ -1,8 +1,27 @@
+mod block_entities;
+mod entities;
 mod schem;
 mod schematic;
 mod litematic;
+mod offset;
+mod options;
+mod packing;
+mod sponge_v3;
 
-pub use schem::schem_to_schematic;
-pub use schematic::schematic_to_schem;
-pub use litematic::litematic_to_schematic;
-pub use schematic::schematic_to_litematic;
\ No newline at end of file
+pub use schem::{schem_to_schematic, schem_to_schematic_with_options};
+pub use schematic::{schematic_to_schem, schematic_to_schem_with_options};
+pub use litematic::{litematic_preview_png, litematic_to_schematic, litematic_to_schematic_with_options};
+pub use schematic::{schematic_to_litematic, schematic_to_litematic_with_options};
+pub use options::{CompressionKind, ConvertOptions};
+
+pub(crate) use block_entities::{compound_list, sponge_block_entities, to_sponge_block_entity};
+pub(crate) use entities::{rebase_entities, to_sponge_entity};
+pub(crate) use litematic::litematic_nbt_to_sponge;
+pub(crate) use offset::{set_sponge_offset, sponge_offset, strip_we_offset};
+pub(crate) use options::{read_decompressed, write_compressed};
+pub(crate) use packing::{decode_varints, encode_varints, litematic_bits_per_block, unpack_block_states};
+pub(crate) use sponge_v3::sponge_v2_layout;
+
+/// Keys of the Litematica region lists of scheduled block and fluid ticks. The same keys carry
+/// them in Sponge NBT passed between the model and the converters.
+pub(crate) const PENDING_TICK_KEYS: [&str; 2] = ["PendingBlockTicks", "PendingFluidTicks"];
@@ -0,0 +1,36 @@
+use quartz_nbt::{NbtCompound, NbtTag};
+
+const WE_OFFSET_KEYS: [&str; 3] = ["WEOffsetX", "WEOffsetY", "WEOffsetZ"];
+
+/// Reads where the minimum corner sits relative to the paste origin. WorldEdit keeps this in
+/// `Metadata.WEOffsetX/Y/Z` and uses `Offset` for the world position it was copied from, so its
+/// keys win over the Sponge `Offset`.
+pub(crate) fn sponge_offset(schematic: &NbtCompound) -> [i32; 3] {
+    if let Ok(metadata) = schematic.get::<_, &NbtCompound>("Metadata") {
+        if let [Ok(x), Ok(y), Ok(z)] = WE_OFFSET_KEYS.map(|key| metadata.get::<_, i32>(key)) {
+            return [x, y, z];
+        }
+    }
+    match schematic.get::<_, &[i32]>("Offset") {
+        Ok(&[x, y, z]) => [x, y, z],
+        _ => [0, 0, 0],
+    }
+}
+
+/// Writes the offset of the minimum corner from the paste origin as both the Sponge `Offset`
+/// and the WorldEdit metadata keys.
+pub(crate) fn set_sponge_offset(schematic: &mut NbtCompound, offset: [i32; 3]) {
+    schematic.insert("Offset", NbtTag::IntArray(offset.to_vec()));
+    let mut metadata = schematic.get::<_, &NbtCompound>("Metadata").cloned().unwrap_or_default();
+    for (key, value) in WE_OFFSET_KEYS.into_iter().zip(offset) {
+        metadata.insert(key, NbtTag::Int(value));
+    }
+    schematic.insert("Metadata", NbtTag::Compound(metadata));
+}
+
+/// Removes the WorldEdit offset keys from a Sponge `Metadata` compound.
+pub(crate) fn strip_we_offset(metadata: &mut NbtCompound) {
+    for key in WE_OFFSET_KEYS {
+        metadata.inner_mut().remove(key);
+    }
+}
@@ -0,0 +1,97 @@
+use std::io::{Read, Write};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionKind {
+    None,
+    Gzip,
+    Zlib,
+}
+
+/// Options shared by every converter.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConvertOptions {
+    /// Compression applied to the output. `None` keeps the usual compression of the target format.
+    pub compression: Option<CompressionKind>,
+    /// Compression level from 0 (fastest) to 9 (smallest).
+    pub level: u32,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        ConvertOptions {
+            compression: None,
+            level: 6,
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl ConvertOptions {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_compression(mut self, compression: CompressionKind) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    pub fn with_level(mut self, level: u32) -> Self {
+        self.level = level;
+        self
+    }
+}
+
+impl ConvertOptions {
+    /// Resolves the compression to use for a writer whose format normally uses `native`.
+    pub(crate) fn compression_or(&self, native: CompressionKind) -> CompressionKind {
+        self.compression.unwrap_or(native)
+    }
+}
+
+/// Reads the whole input, transparently removing gzip or zlib compression if present.
+pub(crate) fn read_decompressed<R: Read>(mut input: R) -> std::io::Result<Vec<u8>> {
+    let mut raw = Vec::new();
+    input.read_to_end(&mut raw)?;
+
+    let mut decompressed = Vec::new();
+    match raw.as_slice() {
+        [0x1f, 0x8b, ..] => {
+            GzDecoder::new(raw.as_slice()).read_to_end(&mut decompressed)?;
+        }
+        [cmf, flg, ..] if cmf & 0x0f == 8 && ((u16::from(*cmf) << 8) | u16::from(*flg)) % 31 == 0 => {
+            ZlibDecoder::new(raw.as_slice()).read_to_end(&mut decompressed)?;
+        }
+        _ => return Ok(raw),
+    }
+    Ok(decompressed)
+}
+
+/// Writes `data` to `output` using the given compression kind and level.
+pub(crate) fn write_compressed<W: Write>(mut output: W, data: &[u8], compression: CompressionKind, level: u32) -> std::io::Result<()> {
+    let level = Compression::new(level.min(9));
+    match compression {
+        CompressionKind::None => output.write_all(data),
+        CompressionKind::Gzip => {
+            let mut encoder = GzEncoder::new(output, level);
+            encoder.write_all(data)?;
+            encoder.finish()?;
+            Ok(())
+        }
+        CompressionKind::Zlib => {
+            let mut encoder = ZlibEncoder::new(output, level);
+            encoder.write_all(data)?;
+            encoder.finish()?;
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,188 @@
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Number of blocks handled by one parallel work item. It is a multiple of 64 so that
+/// every chunk starts on a long boundary when packing.
+#[cfg(feature = "parallel")]
+const CHUNK_BLOCKS: usize = 64 * 1024;
+
+/// Bits per entry Litematica uses for a palette of the given size (never fewer than 2).
+pub(crate) fn litematic_bits_per_block(palette_length: usize) -> usize {
+    let needed = usize::BITS - palette_length.saturating_sub(1).leading_zeros();
+    std::cmp::max(needed as usize, 2)
+}
+
+/// Reads the palette index stored at `index` in a tightly packed long array, where entries
+/// may span two longs.
+fn read_packed(block_states: &[i64], bits_per_block: usize, mask: u64, index: usize) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    let start_bit = index * bits_per_block;
+    let long_index = start_bit / 64;
+    let bit_offset = start_bit % 64;
+
+    let low = *block_states.get(long_index).ok_or("BlockStates array is too short.")? as u64;
+    let mut value = low >> bit_offset;
+    if bit_offset + bits_per_block > 64 {
+        let high = *block_states.get(long_index + 1).ok_or("BlockStates array is too short.")? as u64;
+        value |= high << (64 - bit_offset);
+    }
+    Ok(value & mask)
+}
+
+fn unpack_range(block_states: &[i64], bits_per_block: usize, start: usize, out: &mut [u32]) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mask = (1u64 << bits_per_block) - 1;
+    for (offset, slot) in out.iter_mut().enumerate() {
+        *slot = read_packed(block_states, bits_per_block, mask, start + offset)? as u32;
+    }
+    Ok(())
+}
+
+/// Unpacks `volume` palette indices from a Litematica `BlockStates` long array.
+pub(crate) fn unpack_block_states(block_states: &[i64], bits_per_block: usize, volume: usize) -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+    let mut indices = vec![0u32; volume];
+
+    #[cfg(feature = "parallel")]
+    indices
+        .par_chunks_mut(CHUNK_BLOCKS)
+        .enumerate()
+        .try_for_each(|(chunk, out)| unpack_range(block_states, bits_per_block, chunk * CHUNK_BLOCKS, out))
+        .map_err(|e| e.to_string())?;
+
+    #[cfg(not(feature = "parallel"))]
+    unpack_range(block_states, bits_per_block, 0, &mut indices).map_err(|e| e.to_string())?;
+
+    Ok(indices)
+}
+
+fn pack_range(indices: &[u32], bits_per_block: usize) -> Vec<i64> {
+    let mask = (1u64 << bits_per_block) - 1;
+    let mut block_states = vec![0u64; (indices.len() * bits_per_block).div_ceil(64)];
+
+    for (i, &index) in indices.iter().enumerate() {
+        let value = index as u64 & mask;
+        let start_bit = i * bits_per_block;
+        let long_index = start_bit / 64;
+        let bit_offset = start_bit % 64;
+
+        block_states[long_index] |= value << bit_offset;
+        if bit_offset + bits_per_block > 64 {
+            block_states[long_index + 1] |= value >> (64 - bit_offset);
+        }
+    }
+
+    block_states.into_iter().map(|long| long as i64).collect()
+}
+
+/// Packs palette indices into a Litematica `BlockStates` long array.
+pub(crate) fn pack_block_states(indices: &[u32], bits_per_block: usize) -> Vec<i64> {
+    #[cfg(feature = "parallel")]
+    {
+        // Each chunk covers a whole number of longs, so the chunks can simply be concatenated.
+        indices
+            .par_chunks(CHUNK_BLOCKS)
+            .map(|chunk| pack_range(chunk, bits_per_block))
+            .collect::<Vec<_>>()
+            .concat()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    pack_range(indices, bits_per_block)
+}
+
+fn encode_range(indices: &[u32]) -> Vec<i8> {
+    let mut block_data = Vec::with_capacity(indices.len());
+    for &index in indices {
+        let mut varint = index;
+        loop {
+            let mut byte = (varint & 0x7F) as u8;
+            varint >>= 7;
+            if varint != 0 {
+                byte |= 0x80;
+            }
+            block_data.push(byte as i8);
+            if varint == 0 {
+                break;
+            }
+        }
+    }
+    block_data
+}
+
+/// Encodes palette indices as the VarInt byte stream used by Sponge `BlockData`.
+pub(crate) fn encode_varints(indices: &[u32]) -> Vec<i8> {
+    #[cfg(feature = "parallel")]
+    {
+        // Varints never straddle chunks on the encoding side, so stitching is a concatenation.
+        indices
+            .par_chunks(CHUNK_BLOCKS)
+            .map(encode_range)
+            .collect::<Vec<_>>()
+            .concat()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    encode_range(indices)
+}
+
+fn decode_range(block_data: &[i8]) -> Result<Vec<u32>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut indices = Vec::with_capacity(block_data.len());
+    let mut value = 0u32;
+    let mut shift = 0;
+
+    for &byte in block_data {
+        let byte = byte as u8;
+        if shift >= 32 {
+            return Err("VarInt in BlockData is too long.".into());
+        }
+        value |= ((byte & 0x7F) as u32) << shift;
+        if byte & 0x80 == 0 {
+            indices.push(value);
+            value = 0;
+            shift = 0;
+        } else {
+            shift += 7;
+        }
+    }
+
+    if shift != 0 {
+        return Err("BlockData ends in the middle of a VarInt.".into());
+    }
+    Ok(indices)
+}
+
+/// Splits a VarInt byte stream into segments of roughly `CHUNK_BLOCKS` bytes, moving each
+/// boundary forward so that it always falls right after the last byte of a VarInt.
+#[cfg(feature = "parallel")]
+fn varint_segments(block_data: &[i8]) -> Vec<&[i8]> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+
+    while start < block_data.len() {
+        let mut end = std::cmp::min(start + CHUNK_BLOCKS, block_data.len());
+        while end < block_data.len() && block_data[end - 1] as u8 & 0x80 != 0 {
+            end += 1;
+        }
+        segments.push(&block_data[start..end]);
+        start = end;
+    }
+    segments
+}
+
+/// Decodes a Sponge `BlockData` VarInt stream back into `volume` palette indices.
+pub(crate) fn decode_varints(block_data: &[i8], volume: usize) -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+    #[cfg(feature = "parallel")]
+    let indices = varint_segments(block_data)
+        .into_par_iter()
+        .map(decode_range)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?
+        .concat();
+
+    #[cfg(not(feature = "parallel"))]
+    let indices = decode_range(block_data).map_err(|e| e.to_string())?;
+
+    if indices.len() != volume {
+        return Err(format!("BlockData holds {} blocks but the schematic volume is {}.", indices.len(), volume).into());
+    }
+    Ok(indices)
+}
+
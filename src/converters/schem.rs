@@ -1,13 +1,16 @@
 use std::io::{Read, Write};
-use flate2::read::GzDecoder;
+use super::options::{read_decompressed, write_compressed, CompressionKind, ConvertOptions};
 
-pub fn schem_to_schematic<R: Read, W: Write>(input: R, mut output: W) -> Result<(), Box<dyn std::error::Error>> {
+pub fn schem_to_schematic<R: Read, W: Write>(input: R, output: W) -> Result<(), Box<dyn std::error::Error>> {
+    schem_to_schematic_with_options(input, output, &ConvertOptions::default())
+}
+
+pub fn schem_to_schematic_with_options<R: Read, W: Write>(input: R, output: W, options: &ConvertOptions) -> Result<(), Box<dyn std::error::Error>> {
     // Decompress the gzipped input
-    let mut decoder = GzDecoder::new(input);
+    let decompressed = read_decompressed(input)?;
 
-    // Copy the decompressed data directly to the output
-    std::io::copy(&mut decoder, &mut output)?;
+    // A .schematic is the same NBT, uncompressed unless requested otherwise
+    write_compressed(output, &decompressed, options.compression_or(CompressionKind::None), options.level)?;
 
     Ok(())
 }
-
@@ -1,23 +1,38 @@
-use std::io::{Read, Write};
-use flate2::write::GzEncoder;
-use flate2::Compression;
+use std::io::{Cursor, Read, Write};
+use std::collections::HashMap;
 use quartz_nbt::{NbtCompound, NbtList, NbtTag};
+use super::block_entities::{compound_list, sponge_block_entities, to_litematic_tile_entity};
+use super::offset::sponge_offset;
+use super::PENDING_TICK_KEYS;
+use super::entities::{rebase_entities, to_litematic_entity, to_sponge_entity};
+use super::options::{read_decompressed, write_compressed, CompressionKind, ConvertOptions};
+use super::packing::{decode_varints, litematic_bits_per_block, pack_block_states};
+use super::sponge_v3::sponge_v2_layout;
+use crate::models::BlockState;
+use crate::render::colors::is_air;
+use crate::render::preview_image_data;
+
+pub fn schematic_to_schem<R: Read, W: Write>(input: R, output: W) -> Result<(), Box<dyn std::error::Error>> {
+    schematic_to_schem_with_options(input, output, &ConvertOptions::default())
+}
 
-pub fn schematic_to_schem<R: Read, W: Write>(mut input: R, output: W) -> Result<(), Box<dyn std::error::Error>> {
-    // Create a gzip encoder
-    let mut encoder = GzEncoder::new(output, Compression::default());
-
-    // Copy the input directly to the gzip encoder
-    std::io::copy(&mut input, &mut encoder)?;
+pub fn schematic_to_schem_with_options<R: Read, W: Write>(input: R, output: W, options: &ConvertOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let schematic_data = read_decompressed(input)?;
 
-    // Finish the compression
-    encoder.finish()?;
+    // A .schem is the same NBT, gzipped unless requested otherwise
+    write_compressed(output, &schematic_data, options.compression_or(CompressionKind::Gzip), options.level)?;
 
     Ok(())
 }
 
-pub fn schematic_to_litematic<R: Read, W: Write>(mut input: R, mut output: W) -> Result<(), Box<dyn std::error::Error>> {
-    let (schematic_nbt, _) = quartz_nbt::io::read_nbt(&mut input, quartz_nbt::io::Flavor::Uncompressed)?;
+pub fn schematic_to_litematic<R: Read, W: Write>(input: R, output: W) -> Result<(), Box<dyn std::error::Error>> {
+    schematic_to_litematic_with_options(input, output, &ConvertOptions::default())
+}
+
+pub fn schematic_to_litematic_with_options<R: Read, W: Write>(input: R, output: W, options: &ConvertOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let schematic_data = read_decompressed(input)?;
+    let (schematic_nbt, _) = quartz_nbt::io::read_nbt(&mut Cursor::new(schematic_data), quartz_nbt::io::Flavor::Uncompressed)?;
+    let schematic_nbt = sponge_v2_layout(&schematic_nbt);
 
     let mut litematic_nbt = NbtCompound::new();
 
@@ -26,31 +41,107 @@ pub fn schematic_to_litematic<R: Read, W: Write>(mut input: R, mut output: W) ->
     pack_block_data_to_litematic(&schematic_nbt, &mut litematic_nbt)?;
     convert_entities_to_litematic(&schematic_nbt, &mut litematic_nbt)?;
 
-    quartz_nbt::io::write_nbt(&mut output, None, &litematic_nbt, quartz_nbt::io::Flavor::Uncompressed)?;
+    let mut litematic_data = Vec::new();
+    quartz_nbt::io::write_nbt(&mut litematic_data, None, &litematic_nbt, quartz_nbt::io::Flavor::Uncompressed)?;
+    // Litematica always stores its files gzipped
+    write_compressed(output, &litematic_data, options.compression_or(CompressionKind::Gzip), options.level)?;
 
     Ok(())
 }
 
+/// Litematica schematic format version written to the root `Version` tag.
+const LITEMATIC_VERSION: i32 = 6;
+/// Litematica schematic format sub-version written to the root `SubVersion` tag.
+const LITEMATIC_SUB_VERSION: i32 = 1;
+
+fn current_time_millis() -> i64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        js_sys::Date::now() as i64
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as i64)
+            .unwrap_or(0)
+    }
+}
+
+fn schematic_dimension(schematic: &NbtCompound, key: &str) -> Result<i32, Box<dyn std::error::Error>> {
+    match schematic.get::<_, &NbtTag>(key)? {
+        NbtTag::Short(value) => Ok(*value as u16 as i32),
+        NbtTag::Int(value) => Ok(*value),
+        _ => Err(format!("Invalid {}", key).into()),
+    }
+}
+
+/// Looks up a string tag at the schematic root, falling back to the Sponge `Metadata` compound.
+fn schematic_metadata_string<'a>(schematic: &'a NbtCompound, key: &str) -> Option<&'a String> {
+    if let Ok(NbtTag::String(value)) = schematic.get::<_, &NbtTag>(key) {
+        return Some(value);
+    }
+    match schematic.get::<_, &NbtCompound>("Metadata") {
+        Ok(sponge_metadata) => match sponge_metadata.get::<_, &NbtTag>(key) {
+            Ok(NbtTag::String(value)) => Some(value),
+            _ => None,
+        },
+        Err(_) => None,
+    }
+}
+
 fn convert_metadata_to_litematic(schematic: &NbtCompound, litematic: &mut NbtCompound) -> Result<(), Box<dyn std::error::Error>> {
     let mut metadata = NbtCompound::new();
     let mut enclosing_size = NbtCompound::new();
 
-    enclosing_size.insert("x", schematic.get::<_, &NbtTag>("Width")?.clone());
-    enclosing_size.insert("y", schematic.get::<_, &NbtTag>("Height")?.clone());
-    enclosing_size.insert("z", schematic.get::<_, &NbtTag>("Length")?.clone());
+    let width = schematic_dimension(schematic, "Width")?;
+    let height = schematic_dimension(schematic, "Height")?;
+    let length = schematic_dimension(schematic, "Length")?;
 
-    metadata.insert("EnclosingSize", NbtTag::Compound(enclosing_size));
+    enclosing_size.insert("x", NbtTag::Int(width));
+    enclosing_size.insert("y", NbtTag::Int(height));
+    enclosing_size.insert("z", NbtTag::Int(length));
 
-    if let Ok(NbtTag::String(author)) = schematic.get::<_, &NbtTag>("Author") {
-        metadata.insert("Author", NbtTag::String(author.clone()));
-    }
+    metadata.insert("EnclosingSize", NbtTag::Compound(enclosing_size));
 
-    if let Ok(NbtTag::String(description)) = schematic.get::<_, &NbtTag>("Description") {
-        metadata.insert("Description", NbtTag::String(description.clone()));
+    let name = schematic_metadata_string(schematic, "Name").cloned().unwrap_or_else(|| "Unnamed".to_string());
+    metadata.insert("Name", NbtTag::String(name));
+
+    let author = schematic_metadata_string(schematic, "Author").cloned().unwrap_or_default();
+    metadata.insert("Author", NbtTag::String(author));
+
+    let description = schematic_metadata_string(schematic, "Description").cloned().unwrap_or_default();
+    metadata.insert("Description", NbtTag::String(description));
+
+    // Only a single region is written; TotalBlocks is filled in once the blocks are packed
+    metadata.insert("RegionCount", NbtTag::Int(1));
+    let total_volume = width.checked_mul(height).and_then(|area| area.checked_mul(length))
+        .ok_or_else(|| format!("Region too large: {}x{}x{} blocks do not fit in a litematic region", width, height, length))?;
+    metadata.insert("TotalVolume", NbtTag::Int(total_volume));
+    metadata.insert("TotalBlocks", NbtTag::Int(0));
+
+    let now = current_time_millis();
+    let time_created = match schematic.get::<_, &NbtCompound>("Metadata").map(|sponge_metadata| sponge_metadata.get::<_, &NbtTag>("Date")) {
+        Ok(Ok(NbtTag::Long(date))) => *date,
+        _ => now,
+    };
+    metadata.insert("TimeCreated", NbtTag::Long(time_created));
+    metadata.insert("TimeModified", NbtTag::Long(now));
+
+    // A preview carried over from an earlier litematic is kept, otherwise one is drawn once the
+    // blocks are packed
+    if let Ok(sponge_metadata) = schematic.get::<_, &NbtCompound>("Metadata") {
+        if let Ok(NbtTag::IntArray(preview)) = sponge_metadata.get::<_, &NbtTag>("PreviewImageData") {
+            metadata.insert("PreviewImageData", NbtTag::IntArray(preview.clone()));
+        }
     }
 
     litematic.insert("Metadata", NbtTag::Compound(metadata));
 
+    litematic.insert("Version", NbtTag::Int(LITEMATIC_VERSION));
+    litematic.insert("SubVersion", NbtTag::Int(LITEMATIC_SUB_VERSION));
+
     if let Ok(NbtTag::Int(data_version)) = schematic.get::<_, &NbtTag>("DataVersion") {
         litematic.insert("MinecraftDataVersion", NbtTag::Int(*data_version));
     }
@@ -64,96 +155,128 @@ fn convert_palette_to_litematic(schematic: &NbtCompound, litematic: &mut NbtComp
     let mut block_state_palette = NbtList::new();
 
     if let Ok(NbtTag::Compound(palette)) = schematic.get::<_, &NbtTag>("Palette") {
-        for (full_name, _) in palette.inner().iter() {
+        for (full_name, _) in palette_by_index(palette) {
+            let parsed = BlockState::parse(full_name).map_err(|e| format!("Palette entry {}", e))?;
             let mut block_state = NbtCompound::new();
-            let mut name = full_name.clone();
             let mut properties = NbtCompound::new();
-
-            if let Some(bracket_index) = full_name.find('[') {
-                name = full_name[..bracket_index].to_string();
-                let props_str = &full_name[bracket_index+1..full_name.len()-1];
-                for prop in props_str.split(',') {
-                    let mut key_value = prop.split('=');
-                    if let (Some(key), Some(value)) = (key_value.next(), key_value.next()) {
-                        properties.insert(key, NbtTag::String(value.to_string()));
-                    }
-                }
+            for (key, value) in parsed.properties() {
+                properties.insert(key, NbtTag::String(value.to_string()));
+            }
+            if !properties.is_empty() {
                 block_state.insert("Properties", NbtTag::Compound(properties));
             }
-
-            block_state.insert("Name", NbtTag::String(name));
+            block_state.insert("Name", NbtTag::String(parsed.name().to_string()));
             block_state_palette.push(NbtTag::Compound(block_state));
         }
     }
 
+    // The region starts at its minimum corner, so its position is the offset from the paste origin
+    let [x, y, z] = sponge_offset(schematic);
+    let mut position = NbtCompound::new();
+    position.insert("x", NbtTag::Int(x));
+    position.insert("y", NbtTag::Int(y));
+    position.insert("z", NbtTag::Int(z));
+    region.insert("Position", NbtTag::Compound(position));
+
     region.insert("BlockStatePalette", NbtTag::List(block_state_palette));
+    for key in PENDING_TICK_KEYS {
+        let ticks = schematic.get::<_, &NbtList>(key).cloned().unwrap_or_default();
+        region.insert(key, NbtTag::List(ticks));
+    }
     regions.insert("main", NbtTag::Compound(region));
     litematic.insert("Regions", NbtTag::Compound(regions));
 
     Ok(())
 }
 
+/// Returns the palette entries ordered by their block id, which is the order the litematic
+/// `BlockStatePalette` uses.
+fn palette_by_index(palette: &NbtCompound) -> Vec<(&String, i32)> {
+    let mut entries: Vec<(&String, i32)> = palette.inner().iter()
+        .filter_map(|(name, id)| match id {
+            NbtTag::Int(id) => Some((name, *id)),
+            _ => None,
+        })
+        .collect();
+    entries.sort_by_key(|(_, id)| *id);
+    entries
+}
+
 fn pack_block_data_to_litematic(schematic: &NbtCompound, litematic: &mut NbtCompound) -> Result<(), Box<dyn std::error::Error>> {
+    let mut total_blocks = 0;
+    let mut preview = None;
     if let Ok(NbtTag::ByteArray(block_data)) = schematic.get::<_, &NbtTag>("BlockData") {
         if let Ok(Some(NbtTag::Compound(ref mut regions))) = litematic.get_mut("Regions") {
             if let Ok(Some(NbtTag::Compound(ref mut region))) = regions.get_mut("main") {
-                let width = match schematic.get::<_, &NbtTag>("Width")? {
-                    NbtTag::Short(w) => *w as usize,
-                    _ => return Err("Invalid Width".into()),
-                };
-                let height = match schematic.get::<_, &NbtTag>("Height")? {
-                    NbtTag::Short(h) => *h as usize,
-                    _ => return Err("Invalid Height".into()),
-                };
-                let length = match schematic.get::<_, &NbtTag>("Length")? {
-                    NbtTag::Short(l) => *l as usize,
-                    _ => return Err("Invalid Length".into()),
-                };
-
-                let bits_per_block = (block_data.len() as f64 / (width * height * length) as f64).ceil() as usize;
-                let mask = (1 << bits_per_block) - 1;
-
-                let mut block_states = Vec::new();
-                let mut current_long = 0i64;
-                let mut bits_filled = 0;
-
-                for &block in block_data {
-                    current_long |= (block as i64 & mask) << bits_filled;
-                    bits_filled += bits_per_block;
-
-                    if bits_filled >= 64 {
-                        block_states.push(current_long);
-                        current_long = 0;
-                        bits_filled = 0;
-                    }
+                let width = schematic_dimension(schematic, "Width")? as usize;
+                let height = schematic_dimension(schematic, "Height")? as usize;
+                let length = schematic_dimension(schematic, "Length")? as usize;
+
+                // Block ids in the schematic palette may be sparse, while the litematic palette is
+                // a dense list, so remap ids to their position in the ordered palette.
+                let palette = schematic.get::<_, &NbtCompound>("Palette")
+                    .map_err(|_| "Palette not found in schematic.")?;
+                let ordered_palette = palette_by_index(palette);
+                let positions: HashMap<u32, u32> = ordered_palette.iter()
+                    .enumerate()
+                    .map(|(position, (_, id))| (*id as u32, position as u32))
+                    .collect();
+
+                let mut block_state_indices = decode_varints(block_data, width * height * length)?;
+                for index in block_state_indices.iter_mut() {
+                    *index = *positions.get(index)
+                        .ok_or_else(|| format!("BlockData references block id {} which is not in the palette.", index))?;
                 }
 
-                if bits_filled > 0 {
-                    block_states.push(current_long);
-                }
+                let air_positions: Vec<u32> = ordered_palette.iter()
+                    .enumerate()
+                    .filter(|(_, (name, _))| is_air(name))
+                    .map(|(position, _)| position as u32)
+                    .collect();
+                total_blocks = block_state_indices.iter().filter(|index| !air_positions.contains(index)).count();
+
+                let palette_names: Vec<String> = ordered_palette.iter().map(|(name, _)| name.to_string()).collect();
+                preview = Some(preview_image_data(width, height, length, &palette_names, &block_state_indices));
+
+                let bits_per_block = litematic_bits_per_block(ordered_palette.len());
+                let block_states = pack_block_states(&block_state_indices, bits_per_block);
 
                 region.insert("BlockStates", NbtTag::LongArray(block_states));
 
                 let mut size = NbtCompound::new();
-                size.insert("x", NbtTag::Short(width as i16));
-                size.insert("y", NbtTag::Short(height as i16));
-                size.insert("z", NbtTag::Short(length as i16));
+                size.insert("x", NbtTag::Int(width as i32));
+                size.insert("y", NbtTag::Int(height as i32));
+                size.insert("z", NbtTag::Int(length as i32));
                 region.insert("Size", NbtTag::Compound(size));
             }
         }
     }
+
+    if let Ok(Some(NbtTag::Compound(ref mut metadata))) = litematic.get_mut("Metadata") {
+        metadata.insert("TotalBlocks", NbtTag::Int(total_blocks as i32));
+        if let Some(preview) = preview {
+            if !metadata.contains_key("PreviewImageData") {
+                metadata.insert("PreviewImageData", NbtTag::IntArray(preview));
+            }
+        }
+    }
     Ok(())
 }
 
 fn convert_entities_to_litematic(schematic: &NbtCompound, litematic: &mut NbtCompound) -> Result<(), Box<dyn std::error::Error>> {
     if let Ok(Some(NbtTag::Compound(ref mut regions))) = litematic.get_mut("Regions") {
         if let Ok(Some(NbtTag::Compound(ref mut region))) = regions.get_mut("main") {
-            if let Ok(entities) = schematic.get::<_, &NbtTag>("Entities") {
-                region.insert("Entities", entities.clone());
-            }
-            if let Ok(tile_entities) = schematic.get::<_, &NbtTag>("TileEntities") {
-                region.insert("TileEntities", tile_entities.clone());
-            }
+            // Entities are relative to the region position, which is its minimum corner here
+            let entities: Vec<NbtCompound> = match schematic.get::<_, &NbtList>("Entities") {
+                Ok(entities) => entities.iter().filter_map(|tag| match tag {
+                    NbtTag::Compound(entity) => Some(to_sponge_entity(entity)),
+                    _ => None,
+                }).collect(),
+                Err(_) => Vec::new(),
+            };
+            region.insert("Entities", compound_list(rebase_entities(&entities, [0, 0, 0]).iter().map(to_litematic_entity)));
+            let tile_entities = sponge_block_entities(schematic).iter().map(to_litematic_tile_entity).collect::<Vec<_>>();
+            region.insert("TileEntities", compound_list(tile_entities));
         }
     }
     Ok(())
@@ -0,0 +1,31 @@
+use std::borrow::Cow;
+use quartz_nbt::{NbtCompound, NbtTag};
+
+/// Rewrites Sponge version 3 NBT into the version 2 layout the readers work with, returning
+/// version 1 and 2 NBT unchanged.
+///
+/// Version 3 nests the schematic in a `Schematic` compound and keeps the blocks in a `Blocks`
+/// container of `Palette`, `Data` and `BlockEntities`. Those move to the root as `Palette`,
+/// `BlockData` and `BlockEntities`; the per-block `Biomes` container and the `Data` compounds
+/// of block entities and entities are left for the readers, which understand both layouts.
+pub(crate) fn sponge_v2_layout(nbt: &NbtCompound) -> Cow<'_, NbtCompound> {
+    let schematic = nbt.get::<_, &NbtCompound>("Schematic").unwrap_or(nbt);
+    let Ok(blocks) = schematic.get::<_, &NbtCompound>("Blocks") else {
+        return Cow::Borrowed(schematic);
+    };
+
+    let mut flattened = schematic.clone();
+    flattened.inner_mut().remove("Blocks");
+    flattened.insert("Version", NbtTag::Int(2));
+    if let Ok(palette) = blocks.get::<_, &NbtCompound>("Palette") {
+        flattened.insert("PaletteMax", NbtTag::Int(palette.len() as i32));
+        flattened.insert("Palette", NbtTag::Compound(palette.clone()));
+    }
+    if let Ok(data) = blocks.get::<_, &NbtTag>("Data") {
+        flattened.insert("BlockData", data.clone());
+    }
+    if let Ok(block_entities) = blocks.get::<_, &NbtTag>("BlockEntities") {
+        flattened.insert("BlockEntities", block_entities.clone());
+    }
+    Cow::Owned(flattened)
+}
@@ -3,16 +3,37 @@ use std::io::Cursor;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+pub mod analysis;
 pub mod converters;
+pub mod models;
+pub mod operations;
+pub mod parsers;
+#[cfg(feature = "registry")]
+pub mod registry;
+pub mod render;
+pub mod versions;
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SchematicFormat {
     Litematic,
     Schematic,
     Schem,
 }
 
+impl SchematicFormat {
+    /// Guesses the format from a file extension such as `litematic` or `schem`.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "litematic" => Some(SchematicFormat::Litematic),
+            "schematic" => Some(SchematicFormat::Schematic),
+            "schem" => Some(SchematicFormat::Schem),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
 pub struct SchematicConverter;
 
 #[cfg(target_arch = "wasm32")]
@@ -24,7 +45,161 @@ impl SchematicConverter {
     }
 
     pub fn convert(&self, input: &[u8], from: SchematicFormat, to: SchematicFormat) -> Result<Vec<u8>, JsValue> {
-        self.convert_internal(input, from, to)
+        self.convert_internal(input, from, to, &ConvertOptions::default())
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    pub fn convert_with_options(&self, input: &[u8], from: SchematicFormat, to: SchematicFormat, options: &ConvertOptions) -> Result<Vec<u8>, JsValue> {
+        self.convert_internal(input, from, to, options)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    pub fn extract_preview(&self, input: &[u8]) -> Result<Option<Vec<u8>>, JsValue> {
+        converters::litematic_preview_png(Cursor::new(input))
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    pub fn render_top_down(&self, input: &[u8], format: SchematicFormat, scale: u32) -> Result<Vec<u8>, JsValue> {
+        models::Schematic::read(input, format)
+            .and_then(|schematic| render::render_top_down(&schematic, scale))
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    pub fn render_layer(&self, input: &[u8], format: SchematicFormat, y: usize, scale: u32) -> Result<Vec<u8>, JsValue> {
+        models::Schematic::read(input, format)
+            .and_then(|schematic| render::render_layer(&schematic, y, scale))
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    pub fn render_isometric(&self, input: &[u8], format: SchematicFormat, options: &render::IsometricOptions) -> Result<Vec<u8>, JsValue> {
+        models::Schematic::read(input, format)
+            .and_then(|schematic| render::render_isometric(&schematic, options, None))
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    #[cfg(feature = "resource-pack")]
+    pub fn render_isometric_with_resource_pack(&self, input: &[u8], format: SchematicFormat, options: &render::IsometricOptions, resource_pack: &[u8]) -> Result<Vec<u8>, JsValue> {
+        render::ResourcePack::from_zip(resource_pack)
+            .and_then(|pack| render::render_isometric(&models::Schematic::read(input, format)?, options, Some(&pack)))
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    pub fn material_list_csv(&self, input: &[u8], format: SchematicFormat, include_containers: bool) -> Result<String, JsValue> {
+        models::Schematic::read(input, format)
+            .map(|schematic| analysis::material_list(&schematic, include_containers).to_csv())
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    pub fn material_list_json(&self, input: &[u8], format: SchematicFormat, include_containers: bool) -> Result<String, JsValue> {
+        models::Schematic::read(input, format)
+            .map(|schematic| analysis::material_list(&schematic, include_containers).to_json())
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    pub fn stats_json(&self, input: &[u8], format: SchematicFormat) -> Result<String, JsValue> {
+        models::Schematic::read(input, format)
+            .map(|schematic| analysis::schematic_stats(&schematic).to_json())
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Checks an upload for problems and returns the findings as JSON. Malformed input is
+    /// reported as findings rather than as an error.
+    pub fn validate_json(&self, input: &[u8], format: SchematicFormat) -> String {
+        analysis::validate(input, format).to_json()
+    }
+
+    pub fn rotate(&self, input: &[u8], format: SchematicFormat, axis: operations::Axis, degrees: i32) -> Result<Vec<u8>, JsValue> {
+        self.rotate_internal(input, format, axis, degrees)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    pub fn mirror(&self, input: &[u8], format: SchematicFormat, axis: operations::Axis) -> Result<Vec<u8>, JsValue> {
+        models::Schematic::read(input, format)
+            .and_then(|schematic| operations::mirror(&schematic, axis).write(format, &ConvertOptions::default()))
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Cuts out a box, given as `[x, y, z]` arrays, keeping the format.
+    pub fn crop(&self, input: &[u8], format: SchematicFormat, min: &[u32], size: &[u32]) -> Result<Vec<u8>, JsValue> {
+        let triple = |values: &[u32]| -> Result<[usize; 3], Box<dyn std::error::Error>> {
+            match values {
+                [x, y, z] => Ok([*x as usize, *y as usize, *z as usize]),
+                _ => Err("Expected an [x, y, z] array".into()),
+            }
+        };
+        models::Schematic::read(input, format)
+            .and_then(|schematic| operations::crop(&schematic, triple(min)?, triple(size)?))
+            .and_then(|(cropped, _)| cropped.write(format, &ConvertOptions::default()))
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Compares two schematics, lining up their builds when `align` is set, and returns the
+    /// changes as JSON.
+    pub fn diff_json(&self, old: &[u8], old_format: SchematicFormat, new: &[u8], new_format: SchematicFormat, align: bool) -> Result<String, JsValue> {
+        self.diff_internal(old, old_format, new, new_format, align)
+            .map(|diff| diff.to_json())
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Like `diff_json`, but returns a schematic highlighting the changed blocks.
+    pub fn diff_schematic(&self, old: &[u8], old_format: SchematicFormat, new: &[u8], new_format: SchematicFormat, align: bool, to: SchematicFormat) -> Result<Vec<u8>, JsValue> {
+        self.diff_internal(old, old_format, new, new_format, align)
+            .and_then(|diff| diff.to_schematic().write(to, &ConvertOptions::default()))
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Creates a patch turning `old` into `new`, lining up their builds when `align` is set.
+    pub fn create_patch(&self, old: &[u8], old_format: SchematicFormat, new: &[u8], new_format: SchematicFormat, align: bool) -> Result<Vec<u8>, JsValue> {
+        self.create_patch_internal(old, old_format, new, new_format, align)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    pub fn apply_patch(&self, base: &[u8], format: SchematicFormat, patch: &[u8]) -> Result<Vec<u8>, JsValue> {
+        self.apply_patch_internal(base, format, patch)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Upgrades block states and block entities to the given DataVersion, keeping the format.
+    pub fn upgrade(&self, input: &[u8], format: SchematicFormat, target: i32) -> Result<Vec<u8>, JsValue> {
+        self.upgrade_internal(input, format, target)
+            .map(|(output, _)| output)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Downgrades to an older DataVersion. Substitutes are written one per line as
+    /// `pattern -> replacement` and tried before the bundled ones.
+    pub fn downgrade(&self, input: &[u8], format: SchematicFormat, target: i32, substitutes: &str, fallback: Option<String>) -> Result<Vec<u8>, JsValue> {
+        substitutes.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(operations::ReplaceRule::parse)
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|substitutes| {
+                let defaults = versions::DowngradeOptions::default();
+                let options = versions::DowngradeOptions { substitutes, fallback: fallback.unwrap_or(defaults.fallback) };
+                self.downgrade_internal(input, format, target, &options)
+            })
+            .map(|(output, _)| output)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Replaces blocks using rules written one per line as `pattern -> replacement [probability]`.
+    pub fn replace(&self, input: &[u8], format: SchematicFormat, rules: &str, seed: u64) -> Result<Vec<u8>, JsValue> {
+        rules.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(operations::ReplaceRule::parse)
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|rules| {
+                let options = operations::ReplaceOptions { seed, ..Default::default() };
+                self.replace_internal(input, format, &rules, &options)
+            })
+            .map(|(output, _)| output)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    pub fn trim(&self, input: &[u8], format: SchematicFormat, filler: Option<String>) -> Result<Vec<u8>, JsValue> {
+        models::Schematic::read(input, format)
+            .and_then(|schematic| operations::trim(&schematic, filler.as_deref()))
+            .and_then(|(trimmed, _)| trimmed.write(format, &ConvertOptions::default()))
             .map_err(|e| JsValue::from_str(&e.to_string()))
     }
 }
@@ -36,27 +211,221 @@ impl SchematicConverter {
     }
 
     pub fn convert(&self, input: &[u8], from: SchematicFormat, to: SchematicFormat) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-        self.convert_internal(input, from, to)
+        self.convert_internal(input, from, to, &ConvertOptions::default())
+    }
+
+    pub fn convert_with_options(&self, input: &[u8], from: SchematicFormat, to: SchematicFormat, options: &ConvertOptions) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        self.convert_internal(input, from, to, options)
+    }
+
+    pub fn extract_preview(&self, input: &[u8]) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+        converters::litematic_preview_png(Cursor::new(input))
+    }
+
+    pub fn render_top_down(&self, input: &[u8], format: SchematicFormat, scale: u32) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        render::render_top_down(&models::Schematic::read(input, format)?, scale)
+    }
+
+    pub fn render_layer(&self, input: &[u8], format: SchematicFormat, y: usize, scale: u32) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        render::render_layer(&models::Schematic::read(input, format)?, y, scale)
+    }
+
+    pub fn render_isometric(&self, input: &[u8], format: SchematicFormat, options: &render::IsometricOptions) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        render::render_isometric(&models::Schematic::read(input, format)?, options, None)
+    }
+
+    #[cfg(feature = "resource-pack")]
+    pub fn render_isometric_with_resource_pack(&self, input: &[u8], format: SchematicFormat, options: &render::IsometricOptions, resource_pack: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let pack = render::ResourcePack::from_zip(resource_pack)?;
+        render::render_isometric(&models::Schematic::read(input, format)?, options, Some(&pack))
+    }
+
+    pub fn material_list(&self, input: &[u8], format: SchematicFormat, include_containers: bool) -> Result<analysis::MaterialList, Box<dyn std::error::Error>> {
+        Ok(analysis::material_list(&models::Schematic::read(input, format)?, include_containers))
+    }
+
+    pub fn stats(&self, input: &[u8], format: SchematicFormat) -> Result<analysis::SchematicStats, Box<dyn std::error::Error>> {
+        Ok(analysis::schematic_stats(&models::Schematic::read(input, format)?))
+    }
+
+    /// Checks a schematic for problems, reporting malformed input as findings.
+    pub fn validate(&self, input: &[u8], format: SchematicFormat) -> analysis::ValidationReport {
+        analysis::validate(input, format)
+    }
+
+    /// Rotates a schematic by a multiple of 90 degrees, keeping its format.
+    pub fn rotate(&self, input: &[u8], format: SchematicFormat, axis: operations::Axis, degrees: i32) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        self.rotate_internal(input, format, axis, degrees)
+    }
+
+    /// Mirrors a schematic across the plane perpendicular to `axis`, keeping its format.
+    pub fn mirror(&self, input: &[u8], format: SchematicFormat, axis: operations::Axis) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        operations::mirror(&models::Schematic::read(input, format)?, axis).write(format, &ConvertOptions::default())
+    }
+
+    /// Cuts out the box starting at `min` with the given size, keeping the format.
+    pub fn crop(&self, input: &[u8], format: SchematicFormat, min: [usize; 3], size: [usize; 3]) -> Result<(Vec<u8>, operations::CropReport), Box<dyn std::error::Error>> {
+        let (cropped, report) = operations::crop(&models::Schematic::read(input, format)?, min, size)?;
+        Ok((cropped.write(format, &ConvertOptions::default())?, report))
+    }
+
+    /// Compares two schematics, lining up their builds when `align` is set.
+    pub fn diff(&self, old: &[u8], old_format: SchematicFormat, new: &[u8], new_format: SchematicFormat, align: bool) -> Result<analysis::SchematicDiff, Box<dyn std::error::Error>> {
+        self.diff_internal(old, old_format, new, new_format, align)
+    }
+
+    /// Creates a patch turning `old` into `new`, lining up their builds when `align` is set.
+    pub fn create_patch(&self, old: &[u8], old_format: SchematicFormat, new: &[u8], new_format: SchematicFormat, align: bool) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        self.create_patch_internal(old, old_format, new, new_format, align)
+    }
+
+    /// Applies a patch from `create_patch`, keeping the format of the base.
+    pub fn apply_patch(&self, base: &[u8], format: SchematicFormat, patch: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        self.apply_patch_internal(base, format, patch)
+    }
+
+    /// Upgrades block states and block entities to the given DataVersion, keeping the format.
+    pub fn upgrade(&self, input: &[u8], format: SchematicFormat, target: i32) -> Result<(Vec<u8>, versions::UpgradeReport), Box<dyn std::error::Error>> {
+        self.upgrade_internal(input, format, target)
+    }
+
+    /// Downgrades to an older DataVersion, keeping the format, and reports what was substituted.
+    pub fn downgrade(&self, input: &[u8], format: SchematicFormat, target: i32, options: &versions::DowngradeOptions) -> Result<(Vec<u8>, versions::DowngradeReport), Box<dyn std::error::Error>> {
+        self.downgrade_internal(input, format, target, options)
+    }
+
+    /// Applies replacement rules, keeping the format, and returns the number of blocks replaced.
+    pub fn replace(&self, input: &[u8], format: SchematicFormat, rules: &[operations::ReplaceRule], options: &operations::ReplaceOptions) -> Result<(Vec<u8>, usize), Box<dyn std::error::Error>> {
+        self.replace_internal(input, format, rules, options)
+    }
+
+    /// Trims surrounding air, or the given filler block, keeping the format.
+    pub fn trim(&self, input: &[u8], format: SchematicFormat, filler: Option<&str>) -> Result<(Vec<u8>, operations::CropReport), Box<dyn std::error::Error>> {
+        let (trimmed, report) = operations::trim(&models::Schematic::read(input, format)?, filler)?;
+        Ok((trimmed.write(format, &ConvertOptions::default())?, report))
+    }
+}
+
+/// Collects schematics in any format and merges them into one.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Default)]
+pub struct SchematicMerger {
+    policy: operations::ConflictPolicy,
+    placements: Vec<(models::Schematic, [i32; 3])>,
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl SchematicMerger {
+    #[wasm_bindgen(constructor)]
+    pub fn new(policy: operations::ConflictPolicy) -> Self {
+        SchematicMerger { policy, placements: Vec::new() }
+    }
+
+    pub fn add(&mut self, input: &[u8], format: SchematicFormat, x: i32, y: i32, z: i32) -> Result<(), JsValue> {
+        self.add_internal(input, format, [x, y, z])
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    pub fn finish(&self, to: SchematicFormat) -> Result<Vec<u8>, JsValue> {
+        self.finish_internal(to)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SchematicMerger {
+    pub fn new(policy: operations::ConflictPolicy) -> Self {
+        SchematicMerger { policy, placements: Vec::new() }
+    }
+
+    /// Places a schematic with its origin at the given offset.
+    pub fn add(&mut self, input: &[u8], format: SchematicFormat, offset: [i32; 3]) -> Result<(), Box<dyn std::error::Error>> {
+        self.add_internal(input, format, offset)
+    }
+
+    /// Merges everything added so far and writes it in the given format.
+    pub fn finish(&self, to: SchematicFormat) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        self.finish_internal(to)
+    }
+}
+
+impl SchematicMerger {
+    fn add_internal(&mut self, input: &[u8], format: SchematicFormat, offset: [i32; 3]) -> Result<(), Box<dyn std::error::Error>> {
+        self.placements.push((models::Schematic::read(input, format)?, offset));
+        Ok(())
+    }
+
+    fn finish_internal(&self, to: SchematicFormat) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let placements: Vec<(&models::Schematic, [i32; 3])> = self.placements.iter().map(|(schematic, offset)| (schematic, *offset)).collect();
+        operations::merge(&placements, self.policy)?.write(to, &ConvertOptions::default())
     }
 }
 
 impl SchematicConverter {
-    fn convert_internal(&self, input: &[u8], from: SchematicFormat, to: SchematicFormat) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    fn convert_internal(&self, input: &[u8], from: SchematicFormat, to: SchematicFormat, options: &ConvertOptions) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
         let mut output = Vec::new();
         let result = match (from, to) {
             (SchematicFormat::Litematic, SchematicFormat::Schematic) => {
-                converters::litematic_to_schematic(Cursor::new(input), &mut output)
+                converters::litematic_to_schematic_with_options(Cursor::new(input), &mut output, options)
             }
             (SchematicFormat::Schematic, SchematicFormat::Schem) => {
-                converters::schematic_to_schem(Cursor::new(input), &mut output)
+                converters::schematic_to_schem_with_options(Cursor::new(input), &mut output, options)
             }
             (SchematicFormat::Schematic, SchematicFormat::Litematic) => {
-                converters::schematic_to_litematic(Cursor::new(input), &mut output)
+                converters::schematic_to_litematic_with_options(Cursor::new(input), &mut output, options)
             }
-            _ => Err("Unsupported conversion path".into()),
+            (SchematicFormat::Schem, SchematicFormat::Schematic) => {
+                converters::schem_to_schematic_with_options(Cursor::new(input), &mut output, options)
+            }
+            // Remaining paths go through the in-memory model
+            (from, to) => models::Schematic::read(input, from)
+                .and_then(|schematic| schematic.write(to, options))
+                .map(|converted| output = converted),
         };
         result.map(|_| output)
     }
+
+    fn diff_internal(&self, old: &[u8], old_format: SchematicFormat, new: &[u8], new_format: SchematicFormat, align: bool) -> Result<analysis::SchematicDiff, Box<dyn std::error::Error>> {
+        let old = models::Schematic::read(old, old_format)?;
+        let new = models::Schematic::read(new, new_format)?;
+        let offset = if align { analysis::align_by_bounds(&old, &new) } else { [0, 0, 0] };
+        Ok(analysis::diff(&old, &new, offset))
+    }
+
+    fn create_patch_internal(&self, old: &[u8], old_format: SchematicFormat, new: &[u8], new_format: SchematicFormat, align: bool) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let old = models::Schematic::read(old, old_format)?;
+        let new = models::Schematic::read(new, new_format)?;
+        let offset = if align { analysis::align_by_bounds(&old, &new) } else { [0, 0, 0] };
+        operations::Patch::create(&old, &new, offset).to_bytes()
+    }
+
+    fn apply_patch_internal(&self, base: &[u8], format: SchematicFormat, patch: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let patch = operations::Patch::from_bytes(patch)?;
+        patch.apply(&models::Schematic::read(base, format)?)?.write(format, &ConvertOptions::default())
+    }
+
+    fn upgrade_internal(&self, input: &[u8], format: SchematicFormat, target: i32) -> Result<(Vec<u8>, versions::UpgradeReport), Box<dyn std::error::Error>> {
+        let (upgraded, report) = versions::upgrade(&models::Schematic::read(input, format)?, target)?;
+        Ok((upgraded.write(format, &ConvertOptions::default())?, report))
+    }
+
+    fn downgrade_internal(&self, input: &[u8], format: SchematicFormat, target: i32, options: &versions::DowngradeOptions) -> Result<(Vec<u8>, versions::DowngradeReport), Box<dyn std::error::Error>> {
+        let (downgraded, report) = versions::downgrade(&models::Schematic::read(input, format)?, target, options)?;
+        Ok((downgraded.write(format, &ConvertOptions::default())?, report))
+    }
+
+    fn replace_internal(&self, input: &[u8], format: SchematicFormat, rules: &[operations::ReplaceRule], options: &operations::ReplaceOptions) -> Result<(Vec<u8>, usize), Box<dyn std::error::Error>> {
+        let (replaced, count) = operations::replace_blocks(&models::Schematic::read(input, format)?, rules, options);
+        Ok((replaced.write(format, &ConvertOptions::default())?, count))
+    }
+
+    fn rotate_internal(&self, input: &[u8], format: SchematicFormat, axis: operations::Axis, degrees: i32) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let rotation = operations::Rotation::from_degrees(degrees)
+            .ok_or_else(|| format!("Rotation must be a multiple of 90 degrees, got {}", degrees))?;
+        operations::rotate(&models::Schematic::read(input, format)?, axis, rotation)
+            .write(format, &ConvertOptions::default())
+    }
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// A block state: a namespaced block id and its properties.
+///
+/// Block states are written as `namespace:path[key=value,...]`, with the properties sorted by
+/// key as the game writes them, so equal states always format the same way. A missing
+/// namespace is read as `minecraft:`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockState {
+    name: String,
+    properties: BTreeMap<String, String>,
+}
+
+impl BlockState {
+    /// Creates a block state without properties. Fails when `name` is not a resource location.
+    pub fn new(name: &str) -> Result<Self, String> {
+        let (namespace, path) = name.split_once(':').unwrap_or(("minecraft", name));
+        if !is_valid(namespace, "") || !is_valid(path, "/") {
+            return Err(format!("'{}' is not a valid resource location", name));
+        }
+        Ok(BlockState { name: format!("{}:{}", namespace, path), properties: BTreeMap::new() })
+    }
+
+    /// Reads a block state strictly: the id must be a resource location, the property list must
+    /// be closed and end the text, and every property must be a `key=value` pair with a key that
+    /// appears once.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (name, properties) = match text.split_once('[') {
+            Some((name, rest)) => match rest.strip_suffix(']') {
+                Some(properties) => (name, Some(properties)),
+                None => return Err(format!("'{}' has an unclosed property list", text)),
+            },
+            None => (text, None),
+        };
+        let mut block_state = BlockState::new(name)?;
+        for property in properties.into_iter().filter(|properties| !properties.is_empty()).flat_map(|properties| properties.split(',')) {
+            let Some((key, value)) = property.split_once('=') else {
+                return Err(format!("'{}' has a malformed property '{}'", text, property));
+            };
+            if block_state.properties.contains_key(key) {
+                return Err(format!("'{}' sets '{}' more than once", text, key));
+            }
+            block_state.set_property(key, value)?;
+        }
+        Ok(block_state)
+    }
+
+    /// The namespaced block id.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the same properties on another block. Fails when `name` is not a resource location.
+    pub fn renamed(&self, name: &str) -> Result<Self, String> {
+        Ok(BlockState { properties: self.properties.clone(), ..BlockState::new(name)? })
+    }
+
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.properties.get(key).map(String::as_str)
+    }
+
+    /// The properties sorted by key.
+    pub fn properties(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.properties.iter().map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Sets a property, replacing any value it had.
+    pub fn set_property(&mut self, key: &str, value: &str) -> Result<(), String> {
+        if !is_valid(key, "") || !is_valid(value, "") {
+            return Err(format!("'{}={}' is not a valid property", key, value));
+        }
+        self.properties.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    /// Removes a property, returning the value it had.
+    pub fn remove_property(&mut self, key: &str) -> Option<String> {
+        self.properties.remove(key)
+    }
+}
+
+impl FromStr for BlockState {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        BlockState::parse(text)
+    }
+}
+
+impl fmt::Display for BlockState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if !self.properties.is_empty() {
+            let properties: Vec<String> = self.properties.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+            write!(f, "[{}]", properties.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// Resource location characters: lower case letters, digits, `_`, `-` and `.`, plus `extra`.
+fn is_valid(text: &str, extra: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "_-.".contains(c) || extra.contains(c))
+}
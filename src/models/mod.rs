@@ -0,0 +1,5 @@
+mod block_state;
+mod schematic;
+
+pub use block_state::BlockState;
+pub use schematic::Schematic;
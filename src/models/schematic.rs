@@ -0,0 +1,254 @@
+use std::io::Cursor;
+use quartz_nbt::{NbtCompound, NbtList, NbtTag};
+use crate::converters::{self, encode_varints, set_sponge_offset, to_sponge_block_entity, to_sponge_entity, write_compressed, CompressionKind, ConvertOptions, PENDING_TICK_KEYS};
+use crate::SchematicFormat;
+
+/// A format independent, in-memory schematic.
+///
+/// Blocks are stored as palette indices in `(y * length + z) * width + x` order, which is the
+/// order used by both Sponge `BlockData` and Litematica `BlockStates`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schematic {
+    pub width: usize,
+    pub height: usize,
+    pub length: usize,
+    /// Block states in `name[key=value,...]` form.
+    pub palette: Vec<String>,
+    pub blocks: Vec<u32>,
+    pub block_entities: Vec<NbtCompound>,
+    pub entities: Vec<NbtCompound>,
+    /// Scheduled block ticks in the Litematica layout: `Block`, `Priority`, `SubTick`, `Time`
+    /// and the position as `x`, `y`, `z`.
+    pub block_ticks: Vec<NbtCompound>,
+    /// Scheduled fluid ticks, laid out like `block_ticks` with `Fluid` in place of `Block`.
+    pub fluid_ticks: Vec<NbtCompound>,
+    /// Biome names, indexed by `biomes`.
+    pub biome_palette: Vec<String>,
+    /// Biome palette indices in the same order as `blocks`, or empty when there are no biomes.
+    pub biomes: Vec<u32>,
+    pub data_version: Option<i32>,
+    /// The paste origin in schematic coordinates: the block `//paste` puts at the player and
+    /// Litematica puts at the placement origin. It may lie outside the schematic.
+    pub origin: [i32; 3],
+    /// Sponge `Metadata` compound, kept as-is apart from the WorldEdit offset, which is `origin`.
+    pub metadata: NbtCompound,
+}
+
+impl Schematic {
+    /// Creates a schematic of the given size filled with air.
+    pub fn new(width: usize, height: usize, length: usize) -> Self {
+        Schematic {
+            width,
+            height,
+            length,
+            palette: vec!["minecraft:air".to_string()],
+            blocks: vec![0; width * height * length],
+            block_entities: Vec::new(),
+            entities: Vec::new(),
+            block_ticks: Vec::new(),
+            fluid_ticks: Vec::new(),
+            biome_palette: Vec::new(),
+            biomes: Vec::new(),
+            data_version: None,
+            origin: [0, 0, 0],
+            metadata: NbtCompound::new(),
+        }
+    }
+
+    /// Reads a schematic in any supported format.
+    pub fn read(input: &[u8], format: SchematicFormat) -> Result<Self, Box<dyn std::error::Error>> {
+        crate::parsers::parse_schematic(input, format)
+    }
+
+    pub fn volume(&self) -> usize {
+        self.width * self.height * self.length
+    }
+
+    pub fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        (y * self.length + z) * self.width + x
+    }
+
+    /// Returns the block state at the given position.
+    pub fn block_at(&self, x: usize, y: usize, z: usize) -> &str {
+        &self.palette[self.blocks[self.index(x, y, z)] as usize]
+    }
+
+    /// Returns the palette index of a block state, adding it to the palette if needed.
+    pub fn palette_index(&mut self, block_state: &str) -> u32 {
+        match self.palette.iter().position(|entry| entry == block_state) {
+            Some(position) => position as u32,
+            None => {
+                self.palette.push(block_state.to_string());
+                (self.palette.len() - 1) as u32
+            }
+        }
+    }
+
+    pub fn set_block(&mut self, x: usize, y: usize, z: usize, block_state: &str) {
+        let palette_index = self.palette_index(block_state);
+        let index = self.index(x, y, z);
+        self.blocks[index] = palette_index;
+    }
+
+    /// Returns the biome at the given position, if the schematic has biomes.
+    pub fn biome_at(&self, x: usize, y: usize, z: usize) -> Option<&str> {
+        let biome = *self.biomes.get(self.index(x, y, z))?;
+        Some(&self.biome_palette[biome as usize])
+    }
+
+    /// Returns the biome palette index of a biome, adding it to the biome palette if needed.
+    pub fn biome_palette_index(&mut self, biome: &str) -> u32 {
+        match self.biome_palette.iter().position(|entry| entry == biome) {
+            Some(position) => position as u32,
+            None => {
+                self.biome_palette.push(biome.to_string());
+                (self.biome_palette.len() - 1) as u32
+            }
+        }
+    }
+
+    /// Sets the biome at the given position. A schematic without biomes is first filled with it.
+    pub fn set_biome(&mut self, x: usize, y: usize, z: usize, biome: &str) {
+        let palette_index = self.biome_palette_index(biome);
+        if self.biomes.is_empty() {
+            self.biomes = vec![palette_index; self.volume()];
+        }
+        let index = self.index(x, y, z);
+        self.biomes[index] = palette_index;
+    }
+
+    /// Removes palette entries that no block uses, keeping the order of the others.
+    pub fn compact_palette(&mut self) {
+        let mut used = vec![false; self.palette.len()];
+        for &block in &self.blocks {
+            used[block as usize] = true;
+        }
+
+        let mut remap = vec![0u32; self.palette.len()];
+        let mut palette = Vec::new();
+        for (index, block_state) in self.palette.iter().enumerate() {
+            if used[index] {
+                remap[index] = palette.len() as u32;
+                palette.push(block_state.clone());
+            }
+        }
+        for block in self.blocks.iter_mut() {
+            *block = remap[*block as usize];
+        }
+        self.palette = palette;
+    }
+
+    /// Builds the Sponge schematic (version 2) NBT for this schematic. Fails when a dimension
+    /// is over 65535, the most an unsigned short header can hold.
+    pub fn to_sponge_nbt(&self) -> Result<NbtCompound, Box<dyn std::error::Error>> {
+        let mut nbt = NbtCompound::new();
+        nbt.insert("Version", NbtTag::Int(2));
+        if let Some(data_version) = self.data_version {
+            nbt.insert("DataVersion", NbtTag::Int(data_version));
+        }
+        for (key, length) in [("Width", self.width), ("Height", self.height), ("Length", self.length)] {
+            let length = u16::try_from(length).map_err(|_| format!("{} {} is more than a Sponge schematic can hold", key, length))?;
+            nbt.insert(key, NbtTag::Short(length as i16));
+        }
+
+        let mut palette = NbtCompound::new();
+        for (id, block_state) in self.palette.iter().enumerate() {
+            palette.insert(block_state.clone(), NbtTag::Int(id as i32));
+        }
+        nbt.insert("Palette", NbtTag::Compound(palette));
+        nbt.insert("PaletteMax", NbtTag::Int(self.palette.len() as i32));
+        nbt.insert("BlockData", NbtTag::ByteArray(encode_varints(&self.blocks)));
+
+        let block_entities: Vec<NbtTag> = self.block_entities.iter().map(|block_entity| NbtTag::Compound(to_sponge_block_entity(block_entity))).collect();
+        nbt.insert("BlockEntities", NbtTag::List(NbtList::from(block_entities)));
+        let entities: Vec<NbtTag> = self.entities.iter().map(|entity| NbtTag::Compound(to_sponge_entity(entity))).collect();
+        nbt.insert("Entities", NbtTag::List(NbtList::from(entities)));
+
+        if !self.biomes.is_empty() {
+            let mut biome_palette = NbtCompound::new();
+            for (id, biome) in self.biome_palette.iter().enumerate() {
+                biome_palette.insert(biome.clone(), NbtTag::Int(id as i32));
+            }
+            nbt.insert("BiomePalette", NbtTag::Compound(biome_palette));
+            nbt.insert("BiomePaletteMax", NbtTag::Int(self.biome_palette.len() as i32));
+            nbt.insert("BiomeData", NbtTag::ByteArray(encode_varints(&self.column_biomes())));
+        }
+
+        if !self.metadata.is_empty() {
+            nbt.insert("Metadata", NbtTag::Compound(self.metadata.clone()));
+        }
+        set_sponge_offset(&mut nbt, self.origin.map(|value| -value));
+        Ok(nbt)
+    }
+
+    /// Sponge version 2 keeps one biome per column, so each column gets its most common biome.
+    fn column_biomes(&self) -> Vec<u32> {
+        let mut columns = Vec::with_capacity(self.width * self.length);
+        let mut counts = vec![0usize; self.biome_palette.len()];
+        for z in 0..self.length {
+            for x in 0..self.width {
+                counts.iter_mut().for_each(|count| *count = 0);
+                for y in 0..self.height {
+                    counts[self.biomes[self.index(x, y, z)] as usize] += 1;
+                }
+                // Ties go to the lowest palette index, so uniform columns keep their biome
+                let most_common = counts.iter().enumerate().max_by_key(|&(index, count)| (*count, std::cmp::Reverse(index))).map_or(0, |(index, _)| index);
+                columns.push(most_common as u32);
+            }
+        }
+        columns
+    }
+
+    /// Counts the columns whose biome changes along Y, which `column_biomes` collapses.
+    fn columns_with_mixed_biomes(&self) -> usize {
+        if self.biomes.is_empty() {
+            return 0;
+        }
+        (0..self.length)
+            .flat_map(|z| (0..self.width).map(move |x| (x, z)))
+            .filter(|&(x, z)| (1..self.height).any(|y| self.biomes[self.index(x, y, z)] != self.biomes[self.index(x, 0, z)]))
+            .count()
+    }
+
+    /// Describes what `write` leaves out because the format cannot store it, such as the
+    /// pending ticks that Sponge schematics have no place for, biomes that change along Y in
+    /// a Sponge version 2 file, or biomes in a litematic.
+    pub fn lost_in(&self, format: SchematicFormat) -> Vec<String> {
+        let mut lost = Vec::new();
+        if format != SchematicFormat::Litematic {
+            if !self.block_ticks.is_empty() {
+                lost.push(format!("{} pending block ticks", self.block_ticks.len()));
+            }
+            if !self.fluid_ticks.is_empty() {
+                lost.push(format!("{} pending fluid ticks", self.fluid_ticks.len()));
+            }
+            let mixed = self.columns_with_mixed_biomes();
+            if mixed > 0 {
+                lost.push(format!("biomes that change along Y in {} columns, which keep their most common biome", mixed));
+            }
+        } else if !self.biomes.is_empty() {
+            lost.push("biomes".to_string());
+        }
+        lost
+    }
+
+    /// Writes the schematic in the given format. See `lost_in` for what the format drops.
+    pub fn write(&self, format: SchematicFormat, options: &ConvertOptions) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut nbt = self.to_sponge_nbt()?;
+        if format == SchematicFormat::Litematic {
+            for (key, ticks) in PENDING_TICK_KEYS.into_iter().zip([&self.block_ticks, &self.fluid_ticks]) {
+                nbt.insert(key, NbtTag::List(NbtList::from(ticks.iter().cloned().map(NbtTag::Compound).collect::<Vec<_>>())));
+            }
+        }
+        let mut sponge_data = Vec::new();
+        quartz_nbt::io::write_nbt(&mut sponge_data, None, &nbt, quartz_nbt::io::Flavor::Uncompressed)?;
+
+        let mut output = Vec::new();
+        match format {
+            SchematicFormat::Schematic => write_compressed(&mut output, &sponge_data, options.compression_or(CompressionKind::None), options.level)?,
+            SchematicFormat::Schem => converters::schematic_to_schem_with_options(Cursor::new(sponge_data), &mut output, options)?,
+            SchematicFormat::Litematic => converters::schematic_to_litematic_with_options(Cursor::new(sponge_data), &mut output, options)?,
+        }
+        Ok(output)
+    }
+}
@@ -0,0 +1,166 @@
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+use quartz_nbt::NbtCompound;
+use crate::models::Schematic;
+use crate::render::colors::is_air;
+use super::{block_entity_position, set_block_entity_position, translate_entity};
+
+/// Decides which block is kept when placed schematics overlap.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    /// The schematic placed first keeps its blocks, including air.
+    FirstWins,
+    /// Later schematics overwrite earlier ones, including with air.
+    LastWins,
+    /// Later schematics overwrite earlier ones, but air never replaces a block.
+    #[default]
+    NonAirWins,
+    /// Two different non-air blocks in the same spot are an error.
+    Error,
+}
+
+impl ConflictPolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "first" | "first-wins" => Some(ConflictPolicy::FirstWins),
+            "last" | "last-wins" => Some(ConflictPolicy::LastWins),
+            "non-air" | "non-air-wins" => Some(ConflictPolicy::NonAirWins),
+            "error" => Some(ConflictPolicy::Error),
+            _ => None,
+        }
+    }
+}
+
+/// Places several schematics at the given offsets into a single schematic.
+///
+/// The result covers all placed schematics, with its origin at their lowest corner. Palettes
+/// are unified, block entities follow the block that was kept at their position, and all
+/// entities are carried over. DataVersion and metadata are taken from the first schematic.
+pub fn merge(placements: &[(&Schematic, [i32; 3])], policy: ConflictPolicy) -> Result<Schematic, Box<dyn std::error::Error>> {
+    let Some((first, _)) = placements.first() else {
+        return Err("Nothing to merge".into());
+    };
+
+    let mut min = [i32::MAX; 3];
+    let mut max = [i32::MIN; 3];
+    for (schematic, offset) in placements {
+        let size = [schematic.width, schematic.height, schematic.length];
+        for axis in 0..3 {
+            min[axis] = min[axis].min(offset[axis]);
+            max[axis] = max[axis].max(offset[axis] + size[axis] as i32);
+        }
+    }
+
+    let mut merged = Schematic::new((max[0] - min[0]) as usize, (max[1] - min[1]) as usize, (max[2] - min[2]) as usize);
+    merged.data_version = first.data_version;
+    merged.metadata = first.metadata.clone();
+    // The first schematic keeps its paste origin
+    merged.origin = [0, 1, 2].map(|axis| first.origin[axis] + placements[0].1[axis] - min[axis]);
+
+    // Which placement each block came from, so block entities can follow their block
+    let mut owners: Vec<Option<usize>> = vec![None; merged.volume()];
+    for (placement, (schematic, offset)) in placements.iter().enumerate() {
+        let shift = [offset[0] - min[0], offset[1] - min[1], offset[2] - min[2]].map(|value| value as usize);
+        let palette: Vec<u32> = schematic.palette.iter().map(|block_state| merged.palette_index(block_state)).collect();
+        let air: Vec<bool> = schematic.palette.iter().map(|block_state| is_air(block_state)).collect();
+
+        for y in 0..schematic.height {
+            for z in 0..schematic.length {
+                for x in 0..schematic.width {
+                    let block = schematic.blocks[schematic.index(x, y, z)] as usize;
+                    let index = merged.index(x + shift[0], y + shift[1], z + shift[2]);
+                    let placed = match owners[index] {
+                        None => true,
+                        Some(_) => match policy {
+                            ConflictPolicy::FirstWins => false,
+                            ConflictPolicy::LastWins => true,
+                            ConflictPolicy::NonAirWins => !air[block],
+                            ConflictPolicy::Error => {
+                                let existing = &merged.palette[merged.blocks[index] as usize];
+                                if !air[block] && !is_air(existing) && *existing != schematic.palette[block] {
+                                    return Err(format!(
+                                        "Schematics {} and {} overlap at {},{},{} ({} and {})",
+                                        owners[index].unwrap_or_default(), placement,
+                                        x as i32 + offset[0], y as i32 + offset[1], z as i32 + offset[2],
+                                        existing, schematic.palette[block]
+                                    ).into());
+                                }
+                                !air[block]
+                            }
+                        },
+                    };
+                    if placed {
+                        merged.blocks[index] = palette[block];
+                        owners[index] = Some(placement);
+                    }
+                }
+            }
+        }
+    }
+
+    // Biomes also follow their block; space no placement with biomes covers gets plains
+    if placements.iter().any(|(schematic, _)| !schematic.biomes.is_empty()) {
+        merged.biomes = vec![merged.biome_palette_index("minecraft:plains"); merged.volume()];
+        for (placement, (schematic, offset)) in placements.iter().enumerate() {
+            if schematic.biomes.is_empty() {
+                continue;
+            }
+            let shift = [offset[0] - min[0], offset[1] - min[1], offset[2] - min[2]].map(|value| value as usize);
+            let biomes: Vec<u32> = schematic.biome_palette.iter().map(|biome| merged.biome_palette_index(biome)).collect();
+            for y in 0..schematic.height {
+                for z in 0..schematic.length {
+                    for x in 0..schematic.width {
+                        let index = merged.index(x + shift[0], y + shift[1], z + shift[2]);
+                        if owners[index] == Some(placement) {
+                            merged.biomes[index] = biomes[schematic.biomes[schematic.index(x, y, z)] as usize];
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    for (placement, (schematic, offset)) in placements.iter().enumerate() {
+        let shift = [offset[0] - min[0], offset[1] - min[1], offset[2] - min[2]];
+        // Block entities and ticks are kept for the blocks this placement ended up owning
+        let owned = |merged: &Schematic, compound: &mut NbtCompound| match block_entity_position(compound) {
+            Some(position) => {
+                let moved = [position[0] + shift[0], position[1] + shift[1], position[2] + shift[2]];
+                let owner = match moved.map(|value| value as usize) {
+                    [x, y, z] if moved.iter().all(|&value| value >= 0) && x < merged.width && y < merged.height && z < merged.length => owners[merged.index(x, y, z)],
+                    _ => None,
+                };
+                set_block_entity_position(compound, moved);
+                owner == Some(placement)
+            }
+            None => true,
+        };
+        for block_entity in &schematic.block_entities {
+            let mut block_entity = block_entity.clone();
+            if owned(&merged, &mut block_entity) {
+                merged.block_entities.push(block_entity);
+            }
+        }
+        for tick in &schematic.block_ticks {
+            let mut tick = tick.clone();
+            if owned(&merged, &mut tick) {
+                merged.block_ticks.push(tick);
+            }
+        }
+        for tick in &schematic.fluid_ticks {
+            let mut tick = tick.clone();
+            if owned(&merged, &mut tick) {
+                merged.fluid_ticks.push(tick);
+            }
+        }
+        for entity in &schematic.entities {
+            let mut entity = entity.clone();
+            translate_entity(&mut entity, shift);
+            merged.entities.push(entity);
+        }
+    }
+
+    merged.compact_palette();
+    Ok(merged)
+}
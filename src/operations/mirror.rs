@@ -0,0 +1,17 @@
+use crate::models::Schematic;
+use super::{transform_schematic, Axis, Transform};
+
+/// Mirrors a schematic across the plane perpendicular to `axis`, so `Axis::X` swaps east and
+/// west and `Axis::Z` swaps north and south.
+///
+/// Besides moving blocks, block entities and entities, states are flipped the way Minecraft
+/// mirrors structures: stair shapes and door hinges swap sides, double chests swap halves,
+/// and `facing`, rail shapes and sign or banner `rotation` point the mirrored way.
+pub fn mirror(schematic: &Schematic, axis: Axis) -> Schematic {
+    let transform = Transform::new(match axis {
+        Axis::X => [[-1, 0, 0], [0, 1, 0], [0, 0, 1]],
+        Axis::Y => [[1, 0, 0], [0, -1, 0], [0, 0, 1]],
+        Axis::Z => [[1, 0, 0], [0, 1, 0], [0, 0, -1]],
+    });
+    transform_schematic(schematic, &transform)
+}
@@ -0,0 +1,492 @@
+mod merge;
+mod mirror;
+mod patch;
+mod replace;
+mod resize;
+mod rotate;
+
+pub use merge::{merge, ConflictPolicy};
+pub use mirror::mirror;
+pub use patch::Patch;
+pub use replace::{replace_blocks, ReplaceOptions, ReplaceRule};
+pub(crate) use replace::glob;
+pub use resize::{crop, resize, trim, CropReport};
+pub use rotate::{rotate, Axis, Rotation};
+
+use quartz_nbt::{NbtCompound, NbtList, NbtTag};
+use crate::models::{BlockState, Schematic};
+
+const DIRECTIONS: [(&str, [i32; 3]); 6] = [
+    ("down", [0, -1, 0]),
+    ("up", [0, 1, 0]),
+    ("north", [0, 0, -1]),
+    ("south", [0, 0, 1]),
+    ("west", [-1, 0, 0]),
+    ("east", [1, 0, 0]),
+];
+
+/// Item frame `Facing` values, in the order used by the game.
+const ENTITY_FACINGS: [&str; 6] = ["down", "up", "north", "south", "west", "east"];
+
+/// Painting and legacy hanging entity `Facing` values.
+const HORIZONTAL_FACINGS: [&str; 4] = ["south", "west", "north", "east"];
+
+fn direction_vector(name: &str) -> Option<[i32; 3]> {
+    DIRECTIONS.iter().find(|(direction, _)| *direction == name).map(|(_, vector)| *vector)
+}
+
+fn direction_name(vector: [i32; 3]) -> &'static str {
+    DIRECTIONS.iter().find(|(_, direction)| *direction == vector).map(|(name, _)| *name).unwrap_or("north")
+}
+
+/// A rotation or reflection of the block grid, stored as a signed permutation of the axes.
+///
+/// Row `i` of the matrix gives the new `i` coordinate (x, y, z) in terms of the old ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Transform {
+    matrix: [[i32; 3]; 3],
+}
+
+impl Transform {
+    pub(crate) const IDENTITY: Transform = Transform { matrix: [[1, 0, 0], [0, 1, 0], [0, 0, 1]] };
+
+    pub(crate) fn new(matrix: [[i32; 3]; 3]) -> Self {
+        Transform { matrix }
+    }
+
+    /// Applies `other` after this transform.
+    pub(crate) fn then(&self, other: &Transform) -> Transform {
+        let mut matrix = [[0; 3]; 3];
+        for (i, row) in matrix.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..3).map(|k| other.matrix[i][k] * self.matrix[k][j]).sum();
+            }
+        }
+        Transform { matrix }
+    }
+
+    fn vector(&self, vector: [i32; 3]) -> [i32; 3] {
+        let mut result = [0; 3];
+        for (i, value) in result.iter_mut().enumerate() {
+            *value = (0..3).map(|j| self.matrix[i][j] * vector[j]).sum();
+        }
+        result
+    }
+
+    fn vector_f64(&self, vector: [f64; 3]) -> [f64; 3] {
+        let mut result = [0.0; 3];
+        for (i, value) in result.iter_mut().enumerate() {
+            *value = (0..3).map(|j| self.matrix[i][j] as f64 * vector[j]).sum();
+        }
+        result
+    }
+
+    /// True when the transform keeps up pointing up, so horizontal-only states stay valid.
+    fn is_horizontal(&self) -> bool {
+        self.vector([0, 1, 0]) == [0, 1, 0]
+    }
+
+    fn flips_vertically(&self) -> bool {
+        self.vector([0, 1, 0]) == [0, -1, 0]
+    }
+
+    /// True for reflections, which swap left and right.
+    fn is_mirror(&self) -> bool {
+        let m = &self.matrix;
+        let determinant = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+        determinant < 0
+    }
+
+    /// True when the transform mirrors the horizontal plane, seen from above. A vertical flip
+    /// alone keeps every block's left and right, and undoes the reflection of a horizontal one.
+    fn mirrors_horizontally(&self) -> bool {
+        (self.is_horizontal() || self.flips_vertically()) && self.is_mirror() != self.flips_vertically()
+    }
+
+    fn direction<'a>(&self, name: &'a str) -> &'a str {
+        match direction_vector(name) {
+            Some(vector) => direction_name(self.vector(vector)),
+            None => name,
+        }
+    }
+
+    /// Size of the transformed schematic.
+    fn size(&self, size: [usize; 3]) -> [usize; 3] {
+        let transformed = self.vector([size[0] as i32, size[1] as i32, size[2] as i32]);
+        transformed.map(|value| value.unsigned_abs() as usize)
+    }
+
+    /// Maps a block position, keeping the result inside a schematic of the transformed size.
+    fn block_position(&self, position: [i32; 3], size: [usize; 3]) -> [i32; 3] {
+        let new_size = self.size(size);
+        let mut transformed = self.vector(position);
+        let axis_signs = self.vector([1, 1, 1]);
+        for axis in 0..3 {
+            if axis_signs[axis] < 0 {
+                transformed[axis] += new_size[axis] as i32 - 1;
+            }
+        }
+        transformed
+    }
+
+    /// Maps an entity position, which is continuous rather than a block corner.
+    fn entity_position(&self, position: [f64; 3], size: [usize; 3]) -> [f64; 3] {
+        let new_size = self.size(size);
+        let mut transformed = self.vector_f64(position);
+        let axis_signs = self.vector([1, 1, 1]);
+        for axis in 0..3 {
+            if axis_signs[axis] < 0 {
+                transformed[axis] += new_size[axis] as f64;
+            }
+        }
+        transformed
+    }
+
+    /// Maps an entity yaw, where 0 faces south and 90 faces west.
+    fn yaw(&self, yaw: f32) -> f32 {
+        let radians = (yaw as f64).to_radians();
+        let [x, _, z] = self.vector_f64([-radians.sin(), 0.0, radians.cos()]);
+        // Rounded so quarter turns give exact angles instead of values like 359.99997
+        let degrees = ((-x).atan2(z).to_degrees() * 1000.0).round() / 1000.0;
+        (degrees.rem_euclid(360.0) % 360.0) as f32
+    }
+
+    /// Maps a standing sign or banner `rotation` (0-15, 0 facing south, 4 facing west).
+    fn rotation_16(&self, rotation: u32) -> u32 {
+        ((self.yaw(rotation as f32 * 22.5) / 22.5).round() as u32) % 16
+    }
+}
+
+/// Rewrites the orientation dependent properties of a block state.
+///
+/// Handles `facing`, `axis`, `orientation` (jigsaw and crafter), connection properties such
+/// as `north` or `up`, `half` and `type` when the block is turned upside down, and for
+/// horizontal transforms standing `rotation` and rail `shape`. Stair `shape` is relative to
+/// the stair's facing and only changes under a horizontal reflection, which also swaps door
+/// `hinge` and double chest halves.
+pub(crate) fn transform_block_state(block_state: &str, transform: &Transform) -> String {
+    let Ok(parsed) = BlockState::parse(block_state) else {
+        return block_state.to_string();
+    };
+    let name = parsed.name();
+    let properties: Vec<(&str, &str)> = parsed.properties().collect();
+    if properties.is_empty() {
+        return block_state.to_string();
+    }
+
+    // Connections only move when every target side exists on the block, so a fence rotated
+    // onto its side keeps its state instead of gaining an `up` property
+    let keys: Vec<&str> = properties.iter().map(|(key, _)| *key).collect();
+    let connections: Vec<&str> = keys.iter().copied().filter(|key| direction_vector(key).is_some()).collect();
+    let move_connections = connections.iter().all(|key| keys.contains(&transform.direction(key)));
+
+    let mut rewritten: Vec<(String, String)> = properties.iter()
+        .map(|(key, value)| (key.to_string(), transform_property(name, key, value, transform)))
+        .collect();
+    if move_connections {
+        for (key, value) in rewritten.iter_mut().filter(|(key, _)| connections.contains(&key.as_str())) {
+            if let Some((_, original)) = properties.iter().find(|(original_key, _)| connections.contains(original_key) && transform.direction(original_key) == key) {
+                *value = original.to_string();
+            }
+        }
+    }
+
+    let mut transformed = parsed.clone();
+    for (key, value) in &rewritten {
+        if transformed.set_property(key, value).is_err() {
+            return block_state.to_string();
+        }
+    }
+    transformed.to_string()
+}
+
+fn transform_property(name: &str, key: &str, value: &str, transform: &Transform) -> String {
+    match key {
+        "facing" => transform.direction(value).to_string(),
+        "axis" => {
+            let vector = match value {
+                "x" => [1, 0, 0],
+                "y" => [0, 1, 0],
+                "z" => [0, 0, 1],
+                _ => return value.to_string(),
+            };
+            match transform.vector(vector).map(i32::abs) {
+                [1, 0, 0] => "x".to_string(),
+                [0, 1, 0] => "y".to_string(),
+                _ => "z".to_string(),
+            }
+        }
+        "orientation" => match value.split_once('_') {
+            Some((front, top)) => format!("{}_{}", transform.direction(front), transform.direction(top)),
+            None => value.to_string(),
+        },
+        "rotation" if transform.is_horizontal() => match value.parse::<u32>() {
+            Ok(rotation) => transform.rotation_16(rotation).to_string(),
+            Err(_) => value.to_string(),
+        },
+        "shape" if transform.is_horizontal() && name.ends_with("rail") => transform_rail_shape(value, transform),
+        "shape" if transform.mirrors_horizontally() && name.ends_with("_stairs") => swap_left_right(value),
+        "hinge" if transform.mirrors_horizontally() => swap_left_right(value),
+        "type" if transform.mirrors_horizontally() && name.ends_with("chest") => swap_left_right(value),
+        "half" | "type" if transform.flips_vertically() => match value {
+            "top" => "bottom".to_string(),
+            "bottom" => "top".to_string(),
+            "upper" => "lower".to_string(),
+            "lower" => "upper".to_string(),
+            other => other.to_string(),
+        },
+        _ => value.to_string(),
+    }
+}
+
+/// Swaps `left` and `right` in values such as `inner_left` or a door hinge.
+fn swap_left_right(value: &str) -> String {
+    if let Some(prefix) = value.strip_suffix("left") {
+        format!("{}right", prefix)
+    } else if let Some(prefix) = value.strip_suffix("right") {
+        format!("{}left", prefix)
+    } else {
+        value.to_string()
+    }
+}
+
+/// Maps a rail shape such as `north_east` or `ascending_south`.
+fn transform_rail_shape(shape: &str, transform: &Transform) -> String {
+    if let Some(direction) = shape.strip_prefix("ascending_") {
+        return format!("ascending_{}", transform.direction(direction));
+    }
+    let Some((first, second)) = shape.split_once('_') else {
+        return shape.to_string();
+    };
+    let mut sides = [transform.direction(first), transform.direction(second)];
+    sides.sort_by_key(|side| match *side {
+        "north" => 0,
+        "south" => 1,
+        "east" => 2,
+        _ => 3,
+    });
+    match sides {
+        ["north", "south"] | ["south", "north"] => "north_south".to_string(),
+        ["east", "west"] | ["west", "east"] => "east_west".to_string(),
+        [first, second] => format!("{}_{}", first, second),
+    }
+}
+
+/// Which blocks hold each vanilla block entity, as id globs without the `minecraft:`
+/// namespace. `hanging_sign` comes before `sign`, whose pattern also matches hanging signs, and
+/// `sign` lists the block names from before 1.14.
+const BLOCK_ENTITY_BLOCKS: [(&str, &[&str]); 45] = [
+    ("banner", &["*_banner"]),
+    ("barrel", &["barrel"]),
+    ("beacon", &["beacon"]),
+    ("bed", &["*_bed"]),
+    ("beehive", &["beehive", "bee_nest"]),
+    ("bell", &["bell"]),
+    ("blast_furnace", &["blast_furnace"]),
+    ("brewing_stand", &["brewing_stand"]),
+    ("brushable_block", &["suspicious_*"]),
+    ("calibrated_sculk_sensor", &["calibrated_sculk_sensor"]),
+    ("campfire", &["campfire", "soul_campfire"]),
+    ("chest", &["chest"]),
+    ("chiseled_bookshelf", &["chiseled_bookshelf"]),
+    ("command_block", &["command_block", "chain_command_block", "repeating_command_block"]),
+    ("comparator", &["comparator"]),
+    ("conduit", &["conduit"]),
+    ("crafter", &["crafter"]),
+    ("creaking_heart", &["creaking_heart"]),
+    ("daylight_detector", &["daylight_detector"]),
+    ("decorated_pot", &["decorated_pot"]),
+    ("dispenser", &["dispenser"]),
+    ("dropper", &["dropper"]),
+    ("enchanting_table", &["enchanting_table"]),
+    ("end_gateway", &["end_gateway"]),
+    ("end_portal", &["end_portal"]),
+    ("ender_chest", &["ender_chest"]),
+    ("furnace", &["furnace"]),
+    ("hanging_sign", &["*_hanging_sign"]),
+    ("hopper", &["hopper"]),
+    ("jigsaw", &["jigsaw"]),
+    ("jukebox", &["jukebox"]),
+    ("lectern", &["lectern"]),
+    ("mob_spawner", &["spawner"]),
+    ("piston", &["moving_piston"]),
+    ("sculk_catalyst", &["sculk_catalyst"]),
+    ("sculk_sensor", &["sculk_sensor"]),
+    ("sculk_shrieker", &["sculk_shrieker"]),
+    ("shulker_box", &["shulker_box", "*_shulker_box"]),
+    ("sign", &["*_sign", "sign", "wall_sign"]),
+    ("skull", &["*_skull", "zombie_*head", "player_*head", "creeper_*head", "dragon_*head", "piglin_*head"]),
+    ("smoker", &["smoker"]),
+    ("structure_block", &["structure_block"]),
+    ("trapped_chest", &["trapped_chest"]),
+    ("trial_spawner", &["trial_spawner"]),
+    ("vault", &["vault"]),
+];
+
+/// Returns whether a block can hold the block entity. Block entities from other namespaces
+/// must sit on a block with their own id, and ids from before 1.11 such as `Chest` are not
+/// checked since they do not follow block names.
+pub(crate) fn can_hold(block_state: &BlockState, block_entity_id: &str) -> bool {
+    if block_entity_id.chars().any(|c| c.is_ascii_uppercase()) {
+        return true;
+    }
+    let block = block_state.name().strip_prefix("minecraft:").unwrap_or(block_state.name());
+    let block_entity = block_entity_id.strip_prefix("minecraft:").unwrap_or(block_entity_id);
+    match BLOCK_ENTITY_BLOCKS.iter().find(|(id, _)| *id == block_entity) {
+        Some((_, blocks)) => blocks.iter().any(|pattern| glob(pattern, block).is_some()),
+        None => block == block_entity,
+    }
+}
+
+/// Returns the id of the block entity a block holds, or `None` for vanilla blocks without
+/// one. Blocks from other namespaces are assumed to hold a block entity of their own id.
+pub fn block_entity_of(block_state: &BlockState) -> Option<String> {
+    let Some(block) = block_state.name().strip_prefix("minecraft:") else {
+        return Some(block_state.name().to_string());
+    };
+    BLOCK_ENTITY_BLOCKS.iter()
+        .find(|(_, blocks)| blocks.iter().any(|pattern| glob(pattern, block).is_some()))
+        .map(|(id, _)| format!("minecraft:{}", id))
+}
+
+/// Returns the position of a block entity, which is stored either as a Sponge `Pos` array
+/// or as Litematica style `x`, `y` and `z` ints.
+pub(crate) fn block_entity_position(block_entity: &NbtCompound) -> Option<[i32; 3]> {
+    match block_entity.get::<_, &NbtTag>("Pos") {
+        Ok(NbtTag::IntArray(pos)) if pos.len() == 3 => Some([pos[0], pos[1], pos[2]]),
+        _ => Some([block_entity.get::<_, i32>("x").ok()?, block_entity.get::<_, i32>("y").ok()?, block_entity.get::<_, i32>("z").ok()?]),
+    }
+}
+
+/// Stores the position of a block entity in the same form it was read from.
+pub(crate) fn set_block_entity_position(block_entity: &mut NbtCompound, [x, y, z]: [i32; 3]) {
+    if let Ok(NbtTag::IntArray(_)) = block_entity.get::<_, &NbtTag>("Pos") {
+        block_entity.insert("Pos", NbtTag::IntArray(vec![x, y, z]));
+    } else {
+        block_entity.insert("x", NbtTag::Int(x));
+        block_entity.insert("y", NbtTag::Int(y));
+        block_entity.insert("z", NbtTag::Int(z));
+    }
+}
+
+fn transform_block_entity(block_entity: &mut NbtCompound, transform: &Transform, size: [usize; 3]) {
+    if let Some(position) = block_entity_position(block_entity) {
+        set_block_entity_position(block_entity, transform.block_position(position, size));
+    }
+}
+
+pub(crate) fn entity_position(entity: &NbtCompound) -> Option<[f64; 3]> {
+    double_triple(entity, "Pos")
+}
+
+/// Shifts an entity and the block it hangs on by a whole number of blocks.
+pub(crate) fn translate_entity(entity: &mut NbtCompound, offset: [i32; 3]) {
+    if let Some(position) = entity_position(entity) {
+        let moved: Vec<NbtTag> = position.iter().zip(offset).map(|(value, offset)| NbtTag::Double(value + offset as f64)).collect();
+        entity.insert("Pos", NbtTag::List(NbtList::from(moved)));
+    }
+    for (key, offset) in ["TileX", "TileY", "TileZ"].into_iter().zip(offset) {
+        if let Ok(tile) = entity.get::<_, i32>(key) {
+            entity.insert(key, NbtTag::Int(tile + offset));
+        }
+    }
+}
+
+fn double_triple(compound: &NbtCompound, key: &str) -> Option<[f64; 3]> {
+    match compound.get::<_, &NbtList>(key) {
+        Ok(list) if list.len() == 3 => {
+            let mut values = [0.0; 3];
+            for (value, tag) in values.iter_mut().zip(list.iter()) {
+                match tag {
+                    NbtTag::Double(double) => *value = *double,
+                    _ => return None,
+                }
+            }
+            Some(values)
+        }
+        _ => None,
+    }
+}
+
+/// Moves and turns an entity: its position, the block it hangs on, its yaw and the side
+/// item frames and paintings are attached to.
+fn transform_entity(entity: &mut NbtCompound, transform: &Transform, size: [usize; 3]) {
+    if let Some(position) = entity_position(entity) {
+        let transformed = transform.entity_position(position, size);
+        entity.insert("Pos", NbtTag::List(NbtList::from(transformed.map(NbtTag::Double).to_vec())));
+    }
+    if let Some(motion) = double_triple(entity, "Motion") {
+        let transformed = transform.vector_f64(motion);
+        entity.insert("Motion", NbtTag::List(NbtList::from(transformed.map(NbtTag::Double).to_vec())));
+    }
+
+    if let (Ok(x), Ok(y), Ok(z)) = (entity.get::<_, i32>("TileX"), entity.get::<_, i32>("TileY"), entity.get::<_, i32>("TileZ")) {
+        let [x, y, z] = transform.block_position([x, y, z], size);
+        entity.insert("TileX", NbtTag::Int(x));
+        entity.insert("TileY", NbtTag::Int(y));
+        entity.insert("TileZ", NbtTag::Int(z));
+    }
+
+    if transform.is_horizontal() {
+        if let Ok(rotation) = entity.get::<_, &NbtList>("Rotation") {
+            if let Some(NbtTag::Float(yaw)) = rotation.iter().next() {
+                let mut rotation = rotation.clone();
+                rotation[0] = NbtTag::Float(transform.yaw(*yaw));
+                entity.insert("Rotation", NbtTag::List(rotation));
+            }
+        }
+    }
+
+    let id = entity.get::<_, &str>("id").or_else(|_| entity.get::<_, &str>("Id")).unwrap_or_default();
+    let facings: &[&str] = if id.ends_with("painting") { &HORIZONTAL_FACINGS } else { &ENTITY_FACINGS };
+    for key in ["Facing", "facing"] {
+        if let Ok(NbtTag::Byte(facing)) = entity.get::<_, &NbtTag>(key) {
+            if let Some(direction) = facings.get(*facing as usize) {
+                if let Some(index) = facings.iter().position(|name| *name == transform.direction(direction)) {
+                    entity.insert(key, NbtTag::Byte(index as i8));
+                }
+            }
+        }
+    }
+}
+
+/// Applies a transform to the blocks, biomes, block states, block entities, entities and ticks of a schematic.
+pub(crate) fn transform_schematic(schematic: &Schematic, transform: &Transform) -> Schematic {
+    let size = [schematic.width, schematic.height, schematic.length];
+    let [width, height, length] = transform.size(size);
+
+    let mut transformed = schematic.clone();
+    transformed.width = width;
+    transformed.height = height;
+    transformed.length = length;
+    transformed.palette = schematic.palette.iter().map(|block_state| transform_block_state(block_state, transform)).collect();
+
+    for y in 0..schematic.height {
+        for z in 0..schematic.length {
+            for x in 0..schematic.width {
+                let [new_x, new_y, new_z] = transform.block_position([x as i32, y as i32, z as i32], size);
+                let index = transformed.index(new_x as usize, new_y as usize, new_z as usize);
+                transformed.blocks[index] = schematic.blocks[schematic.index(x, y, z)];
+                if !schematic.biomes.is_empty() {
+                    transformed.biomes[index] = schematic.biomes[schematic.index(x, y, z)];
+                }
+            }
+        }
+    }
+
+    for block_entity in transformed.block_entities.iter_mut() {
+        transform_block_entity(block_entity, transform, size);
+    }
+    for entity in transformed.entities.iter_mut() {
+        transform_entity(entity, transform, size);
+    }
+    // WorldEdit rotates and flips around the paste origin, so it moves with the blocks
+    transformed.origin = transform.block_position(schematic.origin, size);
+    // Ticks store their position as x, y and z, the same as Litematica tile entities
+    for tick in transformed.block_ticks.iter_mut().chain(transformed.fluid_ticks.iter_mut()) {
+        transform_block_entity(tick, transform, size);
+    }
+    transformed
+}
@@ -0,0 +1,298 @@
+use std::io::Cursor;
+use quartz_nbt::io::Flavor;
+use quartz_nbt::{NbtCompound, NbtList, NbtTag};
+use crate::analysis::{diff, ChangeKind};
+use crate::converters::{compound_list, decode_varints, encode_varints, read_decompressed, write_compressed, CompressionKind};
+use crate::models::Schematic;
+use crate::parsers::read_compound_list;
+use super::{block_entity_position, resize, set_block_entity_position, translate_entity};
+
+const PATCH_VERSION: i32 = 1;
+
+/// The changes that turn one revision of a schematic into the next.
+///
+/// Positions are in the coordinates of the new revision, whose origin sits at `offset` in
+/// the base. Block changes are stored as a palette and a sorted list of block indices, which
+/// keeps patches of large builds small.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Patch {
+    pub base_size: [usize; 3],
+    pub size: [usize; 3],
+    pub offset: [i32; 3],
+    pub palette: Vec<String>,
+    /// Block index in the new revision and the palette entry placed there.
+    pub blocks: Vec<(u32, u32)>,
+    /// Positions whose block entity is removed before `block_entities` are added.
+    pub removed_block_entities: Vec<[i32; 3]>,
+    pub block_entities: Vec<NbtCompound>,
+    /// Entities removed from the base, matched by their full NBT.
+    pub removed_entities: Vec<NbtCompound>,
+    pub entities: Vec<NbtCompound>,
+    pub data_version: Option<i32>,
+    /// Metadata entries that are added or replaced.
+    pub metadata: NbtCompound,
+    pub removed_metadata: Vec<String>,
+}
+
+fn read_triple(nbt: &NbtCompound, key: &str) -> Result<[i32; 3], Box<dyn std::error::Error>> {
+    match nbt.get::<_, &[i32]>(key) {
+        Ok([x, y, z]) => Ok([*x, *y, *z]),
+        _ => Err(format!("Missing or invalid {} in patch", key).into()),
+    }
+}
+
+impl Patch {
+    /// Builds the patch turning `old` into `new`, with `new`'s origin at `offset` in `old`.
+    pub fn create(old: &Schematic, new: &Schematic, offset: [i32; 3]) -> Patch {
+        let changes = diff(old, new, offset);
+        let size = [new.width, new.height, new.length];
+        let to_new = |position: [i32; 3]| [0, 1, 2].map(|axis| position[axis] - offset[axis]);
+
+        let mut palette: Vec<String> = Vec::new();
+        let mut blocks = Vec::new();
+        for change in &changes.blocks {
+            let [x, y, z] = to_new(change.position);
+            if x < 0 || y < 0 || z < 0 || x as usize >= size[0] || y as usize >= size[1] || z as usize >= size[2] {
+                // Outside the new revision, so the resize on apply already removes it
+                continue;
+            }
+            let state = match palette.iter().position(|entry| *entry == change.after) {
+                Some(state) => state,
+                None => {
+                    palette.push(change.after.clone());
+                    palette.len() - 1
+                }
+            };
+            blocks.push((new.index(x as usize, y as usize, z as usize) as u32, state as u32));
+        }
+        blocks.sort();
+
+        let mut removed_block_entities = Vec::new();
+        let mut block_entities = Vec::new();
+        for change in &changes.block_entities {
+            if change.kind != ChangeKind::Added {
+                removed_block_entities.push(to_new(change.position));
+            }
+            if let Some(after) = &change.after {
+                let mut after = after.clone();
+                set_block_entity_position(&mut after, to_new(change.position));
+                block_entities.push(after);
+            }
+        }
+
+        let back = offset.map(|value| -value);
+        let moved = |entity: &NbtCompound| {
+            let mut entity = entity.clone();
+            translate_entity(&mut entity, back);
+            entity
+        };
+        let removed_entities = changes.entities.iter().filter_map(|change| change.before.as_ref().map(moved)).collect();
+        let entities = changes.entities.iter().filter_map(|change| change.after.as_ref().map(moved)).collect();
+
+        let mut metadata = NbtCompound::new();
+        for (key, value) in new.metadata.inner() {
+            if old.metadata.get::<_, &NbtTag>(key.as_str()).ok() != Some(value) {
+                metadata.insert(key.clone(), value.clone());
+            }
+        }
+        let removed_metadata = old.metadata.inner().keys()
+            .filter(|key| !new.metadata.contains_key(key.as_str()))
+            .cloned()
+            .collect();
+
+        Patch {
+            base_size: [old.width, old.height, old.length],
+            size,
+            offset,
+            palette,
+            blocks,
+            removed_block_entities,
+            block_entities,
+            removed_entities,
+            entities,
+            data_version: if new.data_version != old.data_version { new.data_version } else { None },
+            metadata,
+            removed_metadata,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.base_size == self.size
+            && self.offset == [0, 0, 0]
+            && self.blocks.is_empty()
+            && self.removed_block_entities.is_empty()
+            && self.block_entities.is_empty()
+            && self.removed_entities.is_empty()
+            && self.entities.is_empty()
+            && self.data_version.is_none()
+            && self.metadata.is_empty()
+            && self.removed_metadata.is_empty()
+    }
+
+    /// Applies the patch to the revision it was created from.
+    pub fn apply(&self, base: &Schematic) -> Result<Schematic, Box<dyn std::error::Error>> {
+        if [base.width, base.height, base.length] != self.base_size {
+            return Err(format!(
+                "Patch expects a {}x{}x{} schematic, got {}x{}x{}",
+                self.base_size[0], self.base_size[1], self.base_size[2], base.width, base.height, base.length
+            ).into());
+        }
+
+        let (mut patched, _) = resize(base, self.offset.map(|value| -value), self.size);
+        let states: Vec<u32> = self.palette.iter().map(|block_state| patched.palette_index(block_state)).collect();
+        for &(index, state) in &self.blocks {
+            let state = *states.get(state as usize).ok_or_else(|| format!("Patch references block {} which is not in its palette", state))?;
+            let block = patched.blocks.get_mut(index as usize).ok_or_else(|| format!("Patch changes block {} outside the schematic", index))?;
+            *block = state;
+        }
+
+        patched.block_entities.retain(|block_entity| match block_entity_position(block_entity) {
+            Some(position) => !self.removed_block_entities.contains(&position),
+            None => true,
+        });
+        patched.block_entities.extend(self.block_entities.iter().cloned());
+
+        for removed in &self.removed_entities {
+            if let Some(index) = patched.entities.iter().position(|entity| entity == removed) {
+                patched.entities.remove(index);
+            }
+        }
+        patched.entities.extend(self.entities.iter().cloned());
+
+        if self.data_version.is_some() {
+            patched.data_version = self.data_version;
+        }
+        for key in &self.removed_metadata {
+            patched.metadata.inner_mut().remove(key);
+        }
+        for (key, value) in self.metadata.inner() {
+            patched.metadata.insert(key.clone(), value.clone());
+        }
+
+        patched.compact_palette();
+        Ok(patched)
+    }
+
+    pub fn to_nbt(&self) -> NbtCompound {
+        let mut nbt = NbtCompound::new();
+        nbt.insert("Version", NbtTag::Int(PATCH_VERSION));
+        let triple = |values: [usize; 3]| NbtTag::IntArray(values.iter().map(|&value| value as i32).collect());
+        nbt.insert("BaseSize", triple(self.base_size));
+        nbt.insert("Size", triple(self.size));
+        nbt.insert("Offset", NbtTag::IntArray(self.offset.to_vec()));
+
+        nbt.insert("Palette", NbtTag::List(NbtList::from(self.palette.iter().cloned().map(NbtTag::String).collect::<Vec<_>>())));
+        // Indices are stored as varint deltas, which are mostly single bytes for clustered edits
+        let mut previous = 0;
+        let deltas: Vec<u32> = self.blocks.iter()
+            .map(|&(index, _)| {
+                let delta = index - previous;
+                previous = index;
+                delta
+            })
+            .collect();
+        nbt.insert("Indices", NbtTag::ByteArray(encode_varints(&deltas)));
+        nbt.insert("States", NbtTag::ByteArray(encode_varints(&self.blocks.iter().map(|&(_, state)| state).collect::<Vec<_>>())));
+
+        let removed_block_entities: Vec<i32> = self.removed_block_entities.iter().flatten().copied().collect();
+        nbt.insert("RemovedBlockEntities", NbtTag::IntArray(removed_block_entities));
+        nbt.insert("BlockEntities", compound_list(self.block_entities.iter().cloned()));
+        nbt.insert("RemovedEntities", compound_list(self.removed_entities.iter().cloned()));
+        nbt.insert("Entities", compound_list(self.entities.iter().cloned()));
+
+        if let Some(data_version) = self.data_version {
+            nbt.insert("DataVersion", NbtTag::Int(data_version));
+        }
+        nbt.insert("Metadata", NbtTag::Compound(self.metadata.clone()));
+        nbt.insert("RemovedMetadata", NbtTag::List(NbtList::from(self.removed_metadata.iter().cloned().map(NbtTag::String).collect::<Vec<_>>())));
+        nbt
+    }
+
+    pub fn from_nbt(nbt: &NbtCompound) -> Result<Patch, Box<dyn std::error::Error>> {
+        match nbt.get::<_, i32>("Version") {
+            Ok(PATCH_VERSION) => {}
+            Ok(version) => return Err(format!("Unsupported patch version {}", version).into()),
+            Err(_) => return Err("Missing patch Version".into()),
+        }
+        let size = |key: &str| -> Result<[usize; 3], Box<dyn std::error::Error>> {
+            let values = read_triple(nbt, key)?;
+            if values.iter().any(|&value| value < 0) {
+                return Err(format!("Invalid {} in patch", key).into());
+            }
+            Ok(values.map(|value| value as usize))
+        };
+
+        let palette: Vec<String> = nbt.get::<_, &NbtList>("Palette")
+            .map_err(|_| "Missing patch Palette")?
+            .iter()
+            .map(|tag| match tag {
+                NbtTag::String(block_state) => Ok(block_state.clone()),
+                _ => Err("Patch palette entries must be strings"),
+            })
+            .collect::<Result<_, _>>()?;
+        let states = match nbt.get::<_, &NbtTag>("States") {
+            Ok(NbtTag::ByteArray(states)) => states,
+            _ => return Err("Missing patch States".into()),
+        };
+        let indices = match nbt.get::<_, &NbtTag>("Indices") {
+            Ok(NbtTag::ByteArray(indices)) => indices,
+            _ => return Err("Missing patch Indices".into()),
+        };
+        // Every varint ends with a byte below 0x80, so counting those gives the entry count
+        let count = states.iter().filter(|&&byte| byte >= 0).count();
+        let states = decode_varints(states, count)?;
+        let mut index = 0u32;
+        let blocks = decode_varints(indices, count)?.into_iter()
+            .zip(states)
+            .map(|(delta, state)| {
+                index = index.checked_add(delta).ok_or("Patch Indices run past the end of the schematic")?;
+                Ok((index, state))
+            })
+            .collect::<Result<_, &str>>()?;
+
+        let removed_block_entities = match nbt.get::<_, &[i32]>("RemovedBlockEntities") {
+            Ok(positions) => positions.chunks_exact(3).map(|position| [position[0], position[1], position[2]]).collect(),
+            Err(_) => Vec::new(),
+        };
+        let removed_metadata = match nbt.get::<_, &NbtList>("RemovedMetadata") {
+            Ok(keys) => keys.iter()
+                .filter_map(|tag| match tag {
+                    NbtTag::String(key) => Some(key.clone()),
+                    _ => None,
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        Ok(Patch {
+            base_size: size("BaseSize")?,
+            size: size("Size")?,
+            offset: read_triple(nbt, "Offset")?,
+            palette,
+            blocks,
+            removed_block_entities,
+            block_entities: read_compound_list(nbt, "BlockEntities"),
+            removed_entities: read_compound_list(nbt, "RemovedEntities"),
+            entities: read_compound_list(nbt, "Entities"),
+            data_version: nbt.get::<_, i32>("DataVersion").ok(),
+            metadata: nbt.get::<_, &NbtCompound>("Metadata").cloned().unwrap_or_default(),
+            removed_metadata,
+        })
+    }
+
+    /// Writes the patch as gzipped NBT.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let mut nbt_data = Vec::new();
+        quartz_nbt::io::write_nbt(&mut nbt_data, Some("Patch"), &self.to_nbt(), Flavor::Uncompressed)?;
+        let mut output = Vec::new();
+        write_compressed(&mut output, &nbt_data, CompressionKind::Gzip, 6)?;
+        Ok(output)
+    }
+
+    /// Reads a patch written by `to_bytes`, compressed or not.
+    pub fn from_bytes(data: &[u8]) -> Result<Patch, Box<dyn std::error::Error>> {
+        let decompressed = read_decompressed(Cursor::new(data))?;
+        let (nbt, _) = quartz_nbt::io::read_nbt(&mut Cursor::new(decompressed), Flavor::Uncompressed)?;
+        Patch::from_nbt(&nbt)
+    }
+}
@@ -0,0 +1,234 @@
+use quartz_nbt::NbtTag;
+use crate::analysis::BoundingBox;
+use crate::models::{BlockState, Schematic};
+use super::{block_entity_of, block_entity_position, can_hold};
+
+/// Replaces blocks matching a pattern, such as `oak_*` → `spruce_*` or `chest` → `barrel[facing]`.
+///
+/// Patterns are a block id, where `*` matches any text, optionally followed by properties
+/// the block must have (`oak_stairs[half=top]`, `*_slab[type=*]`). Ids without a namespace
+/// are in `minecraft:`.
+///
+/// In the replacement, each `*` is filled with the text matched by the pattern's `*` at the
+/// same place. Without brackets the properties of the original block are kept when the
+/// replacement uses a `*` or names the same block, so `oak_* -> spruce_*` keeps stair shapes
+/// while `chest -> stone` gives plain stone. Inside brackets, `key=value` sets a property, a
+/// bare `key` copies it from the original block and `*` copies all remaining ones, so
+/// `barrel[facing,open=false]` only keeps `facing`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplaceRule {
+    pub pattern: String,
+    pub replacement: String,
+    /// Chance from 0 to 1 that a matching block is replaced.
+    pub probability: f64,
+}
+
+/// Where and how replacement rules are applied.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplaceOptions {
+    /// Seed for rules with a probability below 1, so the same seed gives the same result.
+    pub seed: u64,
+    /// Only blocks inside one of these boxes are replaced. Empty means everywhere.
+    pub mask: Vec<BoundingBox>,
+}
+
+impl ReplaceRule {
+    pub fn new(pattern: &str, replacement: &str) -> Self {
+        ReplaceRule {
+            pattern: pattern.to_string(),
+            replacement: replacement.to_string(),
+            probability: 1.0,
+        }
+    }
+
+    pub fn with_probability(mut self, probability: f64) -> Self {
+        self.probability = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Parses a rule written as `pattern -> replacement`, optionally followed by a probability
+    /// such as `stone -> cobblestone 0.25`.
+    pub fn parse(rule: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let (pattern, rest) = rule.split_once("->").ok_or_else(|| format!("Expected 'pattern -> replacement', got '{}'", rule))?;
+        let mut parts = rest.split_whitespace();
+        let replacement = parts.next().ok_or_else(|| format!("Missing replacement in '{}'", rule))?;
+        let probability = match parts.next() {
+            Some(probability) => probability.parse().map_err(|_| format!("Invalid probability in '{}'", rule))?,
+            None => 1.0,
+        };
+        if parts.next().is_some() || pattern.trim().is_empty() {
+            return Err(format!("Expected 'pattern -> replacement [probability]', got '{}'", rule).into());
+        }
+        Ok(ReplaceRule::new(pattern.trim(), replacement).with_probability(probability))
+    }
+
+    /// Returns the replaced block state if `block_state` matches the pattern.
+    pub(crate) fn apply(&self, block_state: &str) -> Option<String> {
+        let block_state = BlockState::parse(block_state).ok()?;
+        let (pattern_id, pattern_properties) = split_rule(&self.pattern);
+        let captures = glob(&with_namespace(pattern_id), block_state.name())?;
+        for (key, value) in pattern_properties.iter().filter_map(|entry| entry.split_once('=')) {
+            match block_state.property(key) {
+                Some(actual) if value == "*" || value == actual => {}
+                _ => return None,
+            }
+        }
+
+        let (replacement_id, replacement_properties) = split_rule(&self.replacement);
+        let mut captures = captures.into_iter();
+        let mut new_id = String::new();
+        for (i, part) in with_namespace(replacement_id).split('*').enumerate() {
+            if i > 0 {
+                new_id.push_str(captures.next().unwrap_or_default());
+            }
+            new_id.push_str(part);
+        }
+
+        let mut new_state = BlockState::new(&new_id).ok()?;
+        let copy_all = replacement_properties.contains(&"*")
+            || (!self.replacement.contains('[') && (replacement_id.contains('*') || new_state.name() == block_state.name()));
+        if copy_all {
+            for (key, value) in block_state.properties() {
+                new_state.set_property(key, value).ok()?;
+            }
+        }
+        for key in replacement_properties.iter().filter(|entry| **entry != "*" && !entry.contains('=')) {
+            if let Some(value) = block_state.property(key) {
+                new_state.set_property(key, value).ok()?;
+            }
+        }
+        for (key, value) in replacement_properties.iter().filter_map(|entry| entry.split_once('=')) {
+            new_state.set_property(key, value).ok()?;
+        }
+        Some(new_state.to_string())
+    }
+}
+
+/// Splits a rule's pattern or replacement into its id and the entries inside its brackets,
+/// which may be `key=value` pairs, bare keys or `*`.
+fn split_rule(text: &str) -> (&str, Vec<&str>) {
+    match text.split_once('[') {
+        Some((id, entries)) => (id, entries.trim_end_matches(']').split(',').map(str::trim).filter(|entry| !entry.is_empty()).collect()),
+        None => (text, Vec::new()),
+    }
+}
+
+fn with_namespace(id: &str) -> String {
+    match id.contains(':') {
+        true => id.to_string(),
+        false => format!("minecraft:{}", id),
+    }
+}
+
+/// Matches `text` against a pattern where `*` matches any run of characters, returning the
+/// text matched by each `*`.
+pub(crate) fn glob<'a>(pattern: &str, text: &'a str) -> Option<Vec<&'a str>> {
+    match pattern.split_once('*') {
+        None => (pattern == text).then(Vec::new),
+        Some((prefix, rest)) => {
+            let remaining = text.strip_prefix(prefix)?;
+            // Shortest match first, so `oak_*_planks`-style patterns capture as little as possible
+            for end in 0..=remaining.len() {
+                if !remaining.is_char_boundary(end) {
+                    continue;
+                }
+                if let Some(mut captures) = glob(rest, &remaining[end..]) {
+                    captures.insert(0, &remaining[..end]);
+                    return Some(captures);
+                }
+            }
+            None
+        }
+    }
+}
+
+/// Deterministic per-block random number in `0.0..1.0`, independent of iteration order.
+fn roll(seed: u64, index: usize, rule: usize) -> f64 {
+    // splitmix64
+    let mut z = seed ^ (index as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ (rule as u64).wrapping_mul(0xD1B5_4A32_D192_ED03);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Applies replacement rules to a schematic and returns it with the number of blocks replaced.
+///
+/// For every block the first matching rule decides; if its probability roll fails the block
+/// stays as it is. Without a mask and with certain rules the palette is remapped directly.
+/// Block entities on replaced blocks are kept when the new block can hold them, and otherwise
+/// become the new block's own block entity, so a chest turned into a barrel keeps its items.
+/// They are dropped when the new block has none, as with a chest turned into stone.
+pub fn replace_blocks(schematic: &Schematic, rules: &[ReplaceRule], options: &ReplaceOptions) -> (Schematic, usize) {
+    let mut replaced = schematic.clone();
+    let targets: Vec<Option<(usize, u32)>> = schematic.palette.iter()
+        .map(|block_state| {
+            rules.iter().enumerate().find_map(|(rule_index, rule)| {
+                rule.apply(block_state).map(|new_state| (rule_index, replaced.palette_index(&new_state)))
+            })
+        })
+        .collect();
+
+    let per_block = !options.mask.is_empty() || rules.iter().any(|rule| rule.probability < 1.0);
+    let mut count = 0;
+    if per_block {
+        for y in 0..schematic.height {
+            for z in 0..schematic.length {
+                for x in 0..schematic.width {
+                    let index = schematic.index(x, y, z);
+                    let Some((rule_index, target)) = targets[schematic.blocks[index] as usize] else {
+                        continue;
+                    };
+                    let in_mask = options.mask.is_empty() || options.mask.iter().any(|bounds| {
+                        (0..3).all(|axis| (bounds.min[axis]..=bounds.max[axis]).contains(&[x, y, z][axis]))
+                    });
+                    if !in_mask || roll(options.seed, index, rule_index) >= rules[rule_index].probability {
+                        continue;
+                    }
+                    if replaced.blocks[index] != target {
+                        replaced.blocks[index] = target;
+                        count += 1;
+                    }
+                }
+            }
+        }
+    } else {
+        let remap: Vec<u32> = targets.iter().enumerate().map(|(index, target)| target.map_or(index as u32, |(_, target)| target)).collect();
+        for block in replaced.blocks.iter_mut() {
+            if remap[*block as usize] != *block {
+                *block = remap[*block as usize];
+                count += 1;
+            }
+        }
+    }
+
+    replaced.block_entities.retain_mut(|block_entity| {
+        let Some([x, y, z]) = block_entity_position(block_entity) else {
+            return true;
+        };
+        if x < 0 || y < 0 || z < 0 || x as usize >= schematic.width || y as usize >= schematic.height || z as usize >= schematic.length {
+            return true;
+        }
+        let index = schematic.index(x as usize, y as usize, z as usize);
+        let parse = |block_state: &str| BlockState::parse(block_state).ok();
+        let (Some(old_state), Some(new_state)) = (parse(&schematic.palette[schematic.blocks[index] as usize]), parse(&replaced.palette[replaced.blocks[index] as usize])) else {
+            return true;
+        };
+        let Some(key) = ["Id", "id"].into_iter().find(|key| block_entity.get::<_, &str>(*key).is_ok()) else {
+            return true;
+        };
+        if old_state.name() == new_state.name() || block_entity.get::<_, &str>(key).is_ok_and(|id| can_hold(&new_state, id)) {
+            return true;
+        }
+        match block_entity_of(&new_state) {
+            Some(new_id) => {
+                block_entity.insert(key, NbtTag::String(new_id));
+                true
+            }
+            None => false,
+        }
+    });
+
+    replaced.compact_palette();
+    (replaced, count)
+}
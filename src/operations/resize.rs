@@ -0,0 +1,144 @@
+use quartz_nbt::NbtCompound;
+use crate::analysis::bounding_box;
+use crate::models::{BlockState, Schematic};
+use crate::render::colors::is_air;
+use super::{block_entity_position, entity_position, set_block_entity_position, translate_entity};
+
+/// What was lost when a schematic was cropped or resized.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CropReport {
+    /// Shift applied to every position, so a block at `p` moved to `p + offset`.
+    pub offset: [i32; 3],
+    /// Block entities that ended up outside the new bounds.
+    pub dropped_block_entities: Vec<NbtCompound>,
+    /// Entities that ended up outside the new bounds.
+    pub dropped_entities: Vec<NbtCompound>,
+    /// Pending block and fluid ticks that ended up outside the new bounds.
+    pub dropped_ticks: Vec<NbtCompound>,
+}
+
+fn inside(position: [f64; 3], size: [usize; 3]) -> bool {
+    position.iter().zip(size).all(|(&value, size)| value >= 0.0 && value < size as f64)
+}
+
+/// Moves the contents of a schematic by `offset` into a schematic of the given size.
+///
+/// Blocks, block entities, entities and ticks outside the new bounds are dropped, and space that
+/// was not covered by the original schematic is filled with air.
+pub fn resize(schematic: &Schematic, offset: [i32; 3], size: [usize; 3]) -> (Schematic, CropReport) {
+    let mut resized = schematic.clone();
+    [resized.width, resized.height, resized.length] = size;
+    let air = resized.palette_index("minecraft:air");
+    resized.blocks = vec![air; resized.volume()];
+    if !resized.biomes.is_empty() {
+        resized.biomes = vec![0; resized.volume()];
+    }
+
+    let old_size = [schematic.width, schematic.height, schematic.length];
+    let range = |axis: usize| {
+        let start = offset[axis].max(0) as usize;
+        let end = (old_size[axis] as i64 + offset[axis] as i64).clamp(0, size[axis] as i64) as usize;
+        start..end.max(start)
+    };
+    for y in range(1) {
+        for z in range(2) {
+            for x in range(0) {
+                let old = schematic.index(
+                    (x as i64 - offset[0] as i64) as usize,
+                    (y as i64 - offset[1] as i64) as usize,
+                    (z as i64 - offset[2] as i64) as usize,
+                );
+                let index = resized.index(x, y, z);
+                resized.blocks[index] = schematic.blocks[old];
+            }
+        }
+    }
+
+    // Biomes are not known outside the original bounds, so new space takes the nearest one
+    if !schematic.biomes.is_empty() {
+        let nearest = |value: usize, axis: usize| (value as i64 - offset[axis] as i64).clamp(0, old_size[axis] as i64 - 1) as usize;
+        for y in 0..size[1] {
+            for z in 0..size[2] {
+                for x in 0..size[0] {
+                    let index = resized.index(x, y, z);
+                    resized.biomes[index] = schematic.biomes[schematic.index(nearest(x, 0), nearest(y, 1), nearest(z, 2))];
+                }
+            }
+        }
+    }
+
+    // The paste origin stays on the same block, so pasting still puts blocks where they were
+    resized.origin = [0, 1, 2].map(|axis| schematic.origin[axis] + offset[axis]);
+
+    let mut report = CropReport { offset, ..CropReport::default() };
+    let block_entities = std::mem::take(&mut resized.block_entities);
+    for mut block_entity in block_entities {
+        if let Some(position) = block_entity_position(&block_entity) {
+            let moved = [position[0] + offset[0], position[1] + offset[1], position[2] + offset[2]];
+            set_block_entity_position(&mut block_entity, moved);
+            if !inside(moved.map(|value| value as f64), size) {
+                report.dropped_block_entities.push(block_entity);
+                continue;
+            }
+        }
+        resized.block_entities.push(block_entity);
+    }
+
+    let entities = std::mem::take(&mut resized.entities);
+    for mut entity in entities {
+        translate_entity(&mut entity, offset);
+        match entity_position(&entity) {
+            Some(position) if !inside(position, size) => report.dropped_entities.push(entity),
+            _ => resized.entities.push(entity),
+        }
+    }
+
+    for ticks in [&mut resized.block_ticks, &mut resized.fluid_ticks] {
+        for mut tick in std::mem::take(ticks) {
+            if let Some(position) = block_entity_position(&tick) {
+                let moved = [position[0] + offset[0], position[1] + offset[1], position[2] + offset[2]];
+                set_block_entity_position(&mut tick, moved);
+                if !inside(moved.map(|value| value as f64), size) {
+                    report.dropped_ticks.push(tick);
+                    continue;
+                }
+            }
+            ticks.push(tick);
+        }
+    }
+
+    resized.compact_palette();
+    (resized, report)
+}
+
+/// Cuts out the box starting at `min` with the given size.
+pub fn crop(schematic: &Schematic, min: [usize; 3], size: [usize; 3]) -> Result<(Schematic, CropReport), Box<dyn std::error::Error>> {
+    let old_size = [schematic.width, schematic.height, schematic.length];
+    if size.contains(&0) || (0..3).any(|axis| min[axis] + size[axis] > old_size[axis]) {
+        return Err(format!(
+            "Crop box {:?} + {:?} does not fit in a {}x{}x{} schematic",
+            min, size, schematic.width, schematic.height, schematic.length
+        ).into());
+    }
+    Ok(resize(schematic, min.map(|value| -(value as i32)), size))
+}
+
+/// Crops a schematic to the blocks that are not air, or not `filler` when one is given.
+///
+/// A filler without properties, such as `minecraft:stone` or `stone`, matches every state of
+/// that block.
+pub fn trim(schematic: &Schematic, filler: Option<&str>) -> Result<(Schematic, CropReport), Box<dyn std::error::Error>> {
+    let filler = filler.map(BlockState::parse).transpose().map_err(|e| format!("Filler {}", e))?;
+    let is_empty = |block: &str| match &filler {
+        Some(filler) => BlockState::parse(block).is_ok_and(|block| match filler.properties().next() {
+            Some(_) => block == *filler,
+            None => block.name() == filler.name(),
+        }),
+        None => is_air(block),
+    };
+
+    match bounding_box(schematic, is_empty) {
+        Some(bounds) => crop(schematic, bounds.min, bounds.size()),
+        None => Err(format!("Schematic only contains {}, nothing is left after trimming", filler.map_or("air".to_string(), |filler| filler.to_string())).into()),
+    }
+}
@@ -0,0 +1,78 @@
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+use crate::models::Schematic;
+use super::{transform_schematic, Transform};
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "x" => Some(Axis::X),
+            "y" => Some(Axis::Y),
+            "z" => Some(Axis::Z),
+            _ => None,
+        }
+    }
+}
+
+/// A rotation in quarter turns, clockwise when looking at the schematic from the positive
+/// end of the axis (from above for `Axis::Y`, matching Minecraft's own rotations).
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    None,
+    Clockwise90,
+    Clockwise180,
+    CounterClockwise90,
+}
+
+impl Rotation {
+    /// Converts a multiple of 90 degrees, which may be negative, into a rotation.
+    pub fn from_degrees(degrees: i32) -> Option<Self> {
+        match degrees.rem_euclid(360) {
+            0 => Some(Rotation::None),
+            90 => Some(Rotation::Clockwise90),
+            180 => Some(Rotation::Clockwise180),
+            270 => Some(Rotation::CounterClockwise90),
+            _ => None,
+        }
+    }
+
+    fn quarter_turns(&self) -> usize {
+        match self {
+            Rotation::None => 0,
+            Rotation::Clockwise90 => 1,
+            Rotation::Clockwise180 => 2,
+            Rotation::CounterClockwise90 => 3,
+        }
+    }
+}
+
+fn quarter_turn(axis: Axis) -> Transform {
+    Transform::new(match axis {
+        // north -> east -> south -> west
+        Axis::Y => [[0, 0, -1], [0, 1, 0], [1, 0, 0]],
+        // up -> north -> down -> south
+        Axis::X => [[1, 0, 0], [0, 0, 1], [0, -1, 0]],
+        // up -> east -> down -> west
+        Axis::Z => [[0, 1, 0], [-1, 0, 0], [0, 0, 1]],
+    })
+}
+
+/// Rotates a schematic around an axis.
+///
+/// Dimensions, block positions, block entity and entity positions are moved, and block
+/// states are rewritten so stairs, logs, rails, signs and connected blocks keep facing the
+/// right way. Turning a schematic around X or Z tips horizontal-only blocks over, so their
+/// `rotation` and rail `shape` are left untouched.
+pub fn rotate(schematic: &Schematic, axis: Axis, rotation: Rotation) -> Schematic {
+    let transform = (0..rotation.quarter_turns()).fold(Transform::IDENTITY, |transform, _| transform.then(&quarter_turn(axis)));
+    transform_schematic(schematic, &transform)
+}
@@ -0,0 +1,128 @@
+use std::io::Cursor;
+use quartz_nbt::io::Flavor;
+use quartz_nbt::{NbtCompound, NbtTag};
+use crate::converters::{decode_varints, litematic_nbt_to_sponge, read_decompressed, rebase_entities, sponge_block_entities, sponge_offset, sponge_v2_layout, strip_we_offset, to_sponge_block_entity, to_sponge_entity};
+use crate::models::Schematic;
+use crate::SchematicFormat;
+
+/// Reads a schematic in any supported format into the in-memory model.
+pub fn parse_schematic(input: &[u8], format: SchematicFormat) -> Result<Schematic, Box<dyn std::error::Error>> {
+    let sponge_data = match format {
+        SchematicFormat::Litematic => {
+            // Converted in memory so the pending ticks, which Sponge cannot store, come along
+            let (litematic, _) = quartz_nbt::io::read_nbt(&mut Cursor::new(read_decompressed(Cursor::new(input))?), Flavor::Uncompressed)?;
+            return parse_sponge_nbt(&litematic_nbt_to_sponge(&litematic)?);
+        }
+        SchematicFormat::Schematic | SchematicFormat::Schem => read_decompressed(Cursor::new(input))?,
+    };
+
+    let (nbt, _) = quartz_nbt::io::read_nbt(&mut Cursor::new(sponge_data), Flavor::Uncompressed)?;
+    parse_sponge_nbt(&nbt)
+}
+
+fn dimension(nbt: &NbtCompound, key: &str) -> Result<usize, Box<dyn std::error::Error>> {
+    match nbt.get::<_, &NbtTag>(key) {
+        Ok(NbtTag::Short(value)) => Ok(*value as u16 as usize),
+        Ok(NbtTag::Int(value)) if *value >= 0 => Ok(*value as usize),
+        _ => Err(format!("Missing or invalid {}", key).into()),
+    }
+}
+
+pub(crate) fn read_compound_list(nbt: &NbtCompound, key: &str) -> Vec<NbtCompound> {
+    match nbt.get::<_, &NbtTag>(key) {
+        Ok(NbtTag::List(list)) => list.iter()
+            .filter_map(|tag| match tag {
+                NbtTag::Compound(compound) => Some(compound.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Builds the model from Sponge schematic (version 1, 2 or 3) NBT.
+pub fn parse_sponge_nbt(nbt: &NbtCompound) -> Result<Schematic, Box<dyn std::error::Error>> {
+    let nbt = &*sponge_v2_layout(nbt);
+    let width = dimension(nbt, "Width")?;
+    let height = dimension(nbt, "Height")?;
+    let length = dimension(nbt, "Length")?;
+
+    let palette_nbt = nbt.get::<_, &NbtCompound>("Palette")
+        .map_err(|_| "Missing or invalid Palette")?;
+    let block_data = match nbt.get::<_, &NbtTag>("BlockData") {
+        Ok(NbtTag::ByteArray(block_data)) => block_data,
+        _ => return Err("Missing or invalid BlockData".into()),
+    };
+    let (palette, blocks) = read_palette_data(palette_nbt, block_data, width * height * length, ("BlockData", "block"))?;
+
+    let (biome_palette, biomes) = read_biomes(nbt, width, height, length)?;
+
+    let mut metadata = nbt.get::<_, &NbtCompound>("Metadata").cloned().unwrap_or_default();
+    strip_we_offset(&mut metadata);
+
+    let block_entities = sponge_block_entities(nbt).iter().map(to_sponge_block_entity).collect();
+    let entities: Vec<NbtCompound> = read_compound_list(nbt, "Entities").iter().map(to_sponge_entity).collect();
+
+    Ok(Schematic {
+        width,
+        height,
+        length,
+        palette,
+        blocks,
+        block_entities,
+        entities: rebase_entities(&entities, [0, 0, 0]),
+        block_ticks: read_compound_list(nbt, "PendingBlockTicks"),
+        fluid_ticks: read_compound_list(nbt, "PendingFluidTicks"),
+        biome_palette,
+        biomes,
+        data_version: nbt.get::<_, i32>("DataVersion").ok(),
+        origin: sponge_offset(nbt).map(|value| -value),
+        metadata,
+    })
+}
+
+/// Decodes varint palette ids and remaps them to positions in a dense palette, since Sponge
+/// palette ids may be sparse.
+fn read_palette_data(palette_nbt: &NbtCompound, data: &[i8], count: usize, (key, what): (&str, &str)) -> Result<(Vec<String>, Vec<u32>), Box<dyn std::error::Error>> {
+    let mut entries: Vec<(i32, &String)> = palette_nbt.inner().iter()
+        .filter_map(|(name, id)| match id {
+            NbtTag::Int(id) => Some((*id, name)),
+            _ => None,
+        })
+        .collect();
+    entries.sort();
+
+    let palette: Vec<String> = entries.iter().map(|(_, name)| name.to_string()).collect();
+    let max_id = entries.last().map(|(id, _)| *id).unwrap_or(0).max(0) as usize;
+    let mut positions = vec![u32::MAX; max_id + 1];
+    for (position, (id, _)) in entries.iter().enumerate() {
+        if *id >= 0 {
+            positions[*id as usize] = position as u32;
+        }
+    }
+
+    let mut values = decode_varints(data, count)?;
+    for value in values.iter_mut() {
+        *value = match positions.get(*value as usize) {
+            Some(&position) if position != u32::MAX => position,
+            _ => return Err(format!("{} references {} id {} which is not in the palette.", key, what, value).into()),
+        };
+    }
+    Ok((palette, values))
+}
+
+/// Reads biomes, either one per column from Sponge version 2 `BiomePalette` and `BiomeData` or
+/// one per block from a version 3 `Biomes` container, into the block order of the model.
+fn read_biomes(nbt: &NbtCompound, width: usize, height: usize, length: usize) -> Result<(Vec<String>, Vec<u32>), Box<dyn std::error::Error>> {
+    if let Ok(container) = nbt.get::<_, &NbtCompound>("Biomes") {
+        if let (Ok(palette), Ok(data)) = (container.get::<_, &NbtCompound>("Palette"), container.get::<_, &[i8]>("Data")) {
+            return read_palette_data(palette, data, width * height * length, ("Biomes", "biome"));
+        }
+    }
+    if let (Ok(palette), Ok(data)) = (nbt.get::<_, &NbtCompound>("BiomePalette"), nbt.get::<_, &[i8]>("BiomeData")) {
+        let (palette, columns) = read_palette_data(palette, data, width * length, ("BiomeData", "biome"))?;
+        let biomes = (0..height).flat_map(|_| columns.iter().copied()).collect();
+        return Ok((palette, biomes));
+    }
+    Ok((Vec::new(), Vec::new()))
+}
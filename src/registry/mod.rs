@@ -0,0 +1,313 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::OnceLock;
+use crate::models::BlockState;
+
+/// The bundled registry, one section per release. See the header of the file for its format.
+const BLOCKS: &str = include_str!("blocks.txt");
+
+/// A block state property and the values it accepts, in the order the game lists them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Property {
+    pub name: String,
+    pub values: Vec<String>,
+    /// Index of the value the block's default state uses.
+    pub default: usize,
+}
+
+impl Property {
+    pub fn default_value(&self) -> &str {
+        &self.values[self.default]
+    }
+}
+
+/// A block as the game registers it in one release.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockInfo {
+    /// Namespaced id, such as `minecraft:oak_stairs`.
+    pub id: String,
+    /// Properties sorted by name, the order block states are written in.
+    pub properties: Vec<Property>,
+    pub has_block_entity: bool,
+    /// Whether the block is a full, opaque cube in every state, hiding the faces next to it.
+    pub opaque: bool,
+}
+
+impl BlockInfo {
+    pub fn property(&self, name: &str) -> Option<&Property> {
+        self.properties.iter().find(|property| property.name == name)
+    }
+
+    /// The block state the game uses when it is placed without properties.
+    pub fn default_state(&self) -> String {
+        if self.properties.is_empty() {
+            return self.id.clone();
+        }
+        let properties: Vec<String> = self.properties.iter().map(|property| format!("{}={}", property.name, property.default_value())).collect();
+        format!("{}[{}]", self.id, properties.join(","))
+    }
+
+    /// Checks that every given property exists on the block and has an allowed value. Missing
+    /// properties are fine, the game fills them in with their defaults.
+    pub fn check_properties(&self, properties: &[(&str, &str)]) -> Result<(), String> {
+        for (name, value) in properties {
+            let Some(property) = self.property(name) else {
+                return Err(format!("{} has no property '{}'", self.id, name));
+            };
+            if !property.values.iter().any(|allowed| allowed == value) {
+                return Err(format!("'{}' is not a value of {} for {}, which takes {}", value, name, self.id, property.values.join(", ")));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes the block as a line of the bundled registry, with no macros or id groups.
+impl fmt::Display for BlockInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flags = match (self.has_block_entity, self.opaque) {
+            (true, true) => "eo",
+            (true, false) => "e",
+            (false, true) => "o",
+            (false, false) => "-",
+        };
+        write!(f, "{} {}", self.id.strip_prefix("minecraft:").unwrap_or(&self.id), flags)?;
+        for property in &self.properties {
+            let values: Vec<String> = property.values.iter().enumerate()
+                .map(|(index, value)| if index == property.default && index != 0 { format!("*{}", value) } else { value.clone() })
+                .collect();
+            write!(f, " {}={}", property.name, values.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// The vanilla blocks of one release: their ids, properties with allowed values and
+/// defaults, whether they have a block entity and whether they are opaque.
+///
+/// The registries are generated from the block reports of the game's data generator and
+/// embedded in the crate with the `registry` feature; [`bundled_versions`] lists the releases
+/// they cover.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockRegistry {
+    data_version: i32,
+    blocks: BTreeMap<String, BlockInfo>,
+}
+
+impl BlockRegistry {
+    /// Returns the registry of the newest bundled release at or before the DataVersion, or
+    /// `None` when no bundled release is that old. Newer DataVersions get the newest registry,
+    /// whose blocks may be missing what later releases added.
+    pub fn for_data_version(data_version: i32) -> Option<&'static BlockRegistry> {
+        newest_at_or_before(registries(), data_version)
+    }
+
+    /// Returns the registry of the newest bundled release, or `None` when none are bundled.
+    pub fn latest() -> Option<&'static BlockRegistry> {
+        registries().last()
+    }
+
+    /// DataVersion of the release the registry describes.
+    pub fn data_version(&self) -> i32 {
+        self.data_version
+    }
+
+    /// Looks up a block by id. The `minecraft:` namespace may be left out.
+    pub fn block(&self, id: &str) -> Option<&BlockInfo> {
+        if id.contains(':') {
+            self.blocks.get(id)
+        } else {
+            self.blocks.get(&format!("minecraft:{}", id))
+        }
+    }
+
+    pub fn blocks(&self) -> impl Iterator<Item = &BlockInfo> {
+        self.blocks.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Checks a block state in `name[key=value,...]` form against the registry.
+    pub fn check_state(&self, block_state: &str) -> Result<(), String> {
+        let block_state = BlockState::parse(block_state)?;
+        match self.block(block_state.name()) {
+            Some(block) => block.check_properties(&block_state.properties().collect::<Vec<_>>()),
+            None => Err(format!("{} is not a block", block_state.name())),
+        }
+    }
+}
+
+/// DataVersions of the releases the bundled registry covers, oldest first.
+pub fn bundled_versions() -> Vec<i32> {
+    registries().iter().map(|registry| registry.data_version).collect()
+}
+
+/// Picks the registry of the newest release at or before the DataVersion from registries
+/// ordered oldest first, as [`parse_registries`] returns them.
+pub fn newest_at_or_before(registries: &[BlockRegistry], data_version: i32) -> Option<&BlockRegistry> {
+    registries.iter().rev().find(|registry| registry.data_version <= data_version)
+}
+
+fn registries() -> &'static [BlockRegistry] {
+    static REGISTRIES: OnceLock<Vec<BlockRegistry>> = OnceLock::new();
+    REGISTRIES.get_or_init(|| parse_registries(BLOCKS).expect("the bundled block registry is invalid"))
+}
+
+/// Reads text in the format of the bundled `blocks.txt` into one registry per `@version`
+/// section, oldest first.
+pub fn parse_registries(text: &str) -> Result<Vec<BlockRegistry>, String> {
+    let mut macros: HashMap<String, String> = HashMap::new();
+    let mut registries: Vec<BlockRegistry> = Vec::new();
+    let mut current: Option<BlockRegistry> = None;
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |message: String| format!("blocks.txt line {}: {}", number + 1, message);
+
+        if let Some(definition) = line.strip_prefix("@define ") {
+            let (name, value) = definition.split_once(' ').ok_or_else(|| error("a macro needs a name and text".to_string()))?;
+            let value = expand_macros(value.trim(), &macros).map_err(error)?;
+            macros.insert(name.to_string(), value);
+            continue;
+        }
+        if let Some(version) = line.strip_prefix("@version ") {
+            let data_version: i32 = version.trim().parse().map_err(|_| error(format!("'{}' is not a DataVersion", version)))?;
+            let mut next = BlockRegistry { data_version, blocks: BTreeMap::new() };
+            if let Some(previous) = current.take() {
+                if data_version <= previous.data_version {
+                    return Err(error(format!("version {} does not follow {}", data_version, previous.data_version)));
+                }
+                next.blocks = previous.blocks.clone();
+                registries.push(previous);
+            }
+            current = Some(next);
+            continue;
+        }
+
+        let registry = current.as_mut().ok_or_else(|| error("blocks come before the first @version".to_string()))?;
+        let line = expand_macros(line, &macros).map_err(error)?;
+        if let Some(ids) = line.strip_prefix('-') {
+            for id in expand_groups(ids).map_err(error)? {
+                let id = namespaced(&id);
+                if registry.blocks.remove(&id).is_none() {
+                    return Err(error(format!("{} is removed but was never added", id)));
+                }
+            }
+            continue;
+        }
+
+        let mut tokens = line.split_whitespace();
+        let ids = tokens.next().unwrap_or_default();
+        let flags = tokens.next().ok_or_else(|| error(format!("{} has no flags", ids)))?;
+        if flags != "-" && !flags.chars().all(|flag| flag == 'e' || flag == 'o') {
+            return Err(error(format!("unknown flags '{}'", flags)));
+        }
+        let mut properties = tokens.map(parse_property).collect::<Result<Vec<_>, _>>().map_err(error)?;
+        properties.sort_by(|a, b| a.name.cmp(&b.name));
+        for id in expand_groups(ids).map_err(error)? {
+            let id = namespaced(&id);
+            registry.blocks.insert(id.clone(), BlockInfo {
+                id,
+                properties: properties.clone(),
+                has_block_entity: flags.contains('e'),
+                opaque: flags.contains('o'),
+            });
+        }
+    }
+
+    registries.extend(current);
+    Ok(registries)
+}
+
+fn namespaced(id: &str) -> String {
+    if id.contains(':') { id.to_string() } else { format!("minecraft:{}", id) }
+}
+
+/// Replaces each `$NAME` with the text of the macro, taking the whole upper case name.
+fn expand_macros(line: &str, macros: &HashMap<String, String>) -> Result<String, String> {
+    let mut expanded = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let end = after.find(|c: char| !(c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')).unwrap_or(after.len());
+        let name = &after[..end];
+        let value = macros.get(name).ok_or_else(|| format!("unknown macro ${}", name))?;
+        expanded.push_str(value);
+        rest = &after[end..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Expands `{a,b}` groups, nested or not, into every combination of alternatives.
+fn expand_groups(pattern: &str) -> Result<Vec<String>, String> {
+    let Some(start) = pattern.find('{') else {
+        return Ok(vec![pattern.to_string()]);
+    };
+    let mut depth = 0;
+    let mut alternatives = Vec::new();
+    let mut alternative_start = start + 1;
+    for (index, c) in pattern.char_indices().skip(start) {
+        match c {
+            '{' => depth += 1,
+            ',' if depth == 1 => {
+                alternatives.push(&pattern[alternative_start..index]);
+                alternative_start = index + 1;
+            }
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    alternatives.push(&pattern[alternative_start..index]);
+                    let (prefix, suffix) = (&pattern[..start], &pattern[index + 1..]);
+                    let mut expanded = Vec::new();
+                    for alternative in alternatives {
+                        expanded.extend(expand_groups(&format!("{}{}{}", prefix, alternative, suffix))?);
+                    }
+                    return Ok(expanded);
+                }
+            }
+            _ => {}
+        }
+    }
+    Err(format!("unclosed group in '{}'", pattern))
+}
+
+/// Reads `name=a,*b,0..3`: comma separated values, integer ranges, and the default marked
+/// with `*` or else first.
+fn parse_property(text: &str) -> Result<Property, String> {
+    let (name, values) = text.split_once('=').ok_or_else(|| format!("'{}' is not a property", text))?;
+    let mut property = Property { name: name.to_string(), values: Vec::new(), default: 0 };
+    for value in values.split(',') {
+        let value = match value.strip_prefix('*') {
+            Some(value) => {
+                property.default = property.values.len();
+                value
+            }
+            None => value,
+        };
+        match value.split_once("..") {
+            Some((low, high)) => {
+                let range = |bound: &str| bound.parse::<i32>().map_err(|_| format!("'{}' is not a range", value));
+                property.values.extend((range(low)?..=range(high)?).map(|number| number.to_string()));
+            }
+            None => property.values.push(value.to_string()),
+        }
+    }
+    if property.values.is_empty() || property.values.iter().any(String::is_empty) {
+        return Err(format!("property {} has an empty value", name));
+    }
+    if let Some(duplicate) = property.values.iter().enumerate().find(|(index, value)| property.values[..*index].contains(value)) {
+        return Err(format!("property {} lists '{}' twice", name, duplicate.1));
+    }
+    Ok(property)
+}
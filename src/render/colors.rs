@@ -0,0 +1,169 @@
+/// Colors of the 16 dye variants, used for wool, concrete, terracotta, glass and friends.
+/// "light_gray" and "light_blue" come before "gray" and "blue" so prefix matching finds them first.
+const DYE_COLORS: [(&str, [u8; 3]); 16] = [
+    ("white", [233, 236, 236]),
+    ("orange", [240, 118, 19]),
+    ("magenta", [189, 68, 179]),
+    ("light_blue", [58, 175, 217]),
+    ("yellow", [248, 198, 39]),
+    ("lime", [112, 185, 25]),
+    ("pink", [237, 141, 172]),
+    ("light_gray", [142, 142, 134]),
+    ("gray", [62, 68, 71]),
+    ("cyan", [21, 137, 145]),
+    ("purple", [121, 42, 172]),
+    ("blue", [53, 57, 157]),
+    ("brown", [114, 71, 40]),
+    ("green", [84, 109, 27]),
+    ("red", [161, 39, 34]),
+    ("black", [20, 21, 25]),
+];
+
+/// Colors of the wood types, keyed by the prefix used in block ids.
+const WOOD_COLORS: [(&str, [u8; 3]); 11] = [
+    ("dark_oak", [66, 43, 20]),
+    ("oak", [162, 130, 78]),
+    ("spruce", [114, 84, 48]),
+    ("birch", [192, 175, 121]),
+    ("jungle", [160, 115, 80]),
+    ("acacia", [168, 90, 50]),
+    ("mangrove", [117, 54, 48]),
+    ("cherry", [226, 178, 172]),
+    ("bamboo", [193, 173, 80]),
+    ("crimson", [101, 48, 70]),
+    ("warped", [43, 104, 99]),
+];
+
+/// Colors of individual blocks, matched against the id without the namespace.
+const BLOCK_COLORS: [(&str, [u8; 3]); 64] = [
+    ("stone", [125, 125, 125]),
+    ("cobblestone", [122, 122, 122]),
+    ("mossy_cobblestone", [110, 118, 94]),
+    ("smooth_stone", [158, 158, 158]),
+    ("stone_bricks", [122, 121, 122]),
+    ("granite", [149, 103, 85]),
+    ("diorite", [188, 188, 188]),
+    ("andesite", [136, 136, 136]),
+    ("deepslate", [80, 80, 82]),
+    ("tuff", [108, 109, 102]),
+    ("calcite", [223, 224, 220]),
+    ("bedrock", [85, 85, 85]),
+    ("grass_block", [127, 178, 56]),
+    ("dirt", [134, 96, 67]),
+    ("coarse_dirt", [119, 85, 59]),
+    ("podzol", [91, 63, 24]),
+    ("mycelium", [111, 99, 105]),
+    ("dirt_path", [148, 121, 65]),
+    ("farmland", [81, 44, 15]),
+    ("mud", [60, 57, 60]),
+    ("clay", [160, 166, 179]),
+    ("gravel", [131, 127, 126]),
+    ("sand", [219, 207, 163]),
+    ("sandstone", [216, 203, 155]),
+    ("red_sand", [190, 102, 33]),
+    ("red_sandstone", [186, 99, 29]),
+    ("snow", [249, 254, 254]),
+    ("snow_block", [249, 254, 254]),
+    ("ice", [145, 183, 253]),
+    ("packed_ice", [141, 180, 250]),
+    ("blue_ice", [116, 167, 253]),
+    ("water", [63, 118, 228]),
+    ("lava", [207, 92, 20]),
+    ("obsidian", [15, 10, 24]),
+    ("netherrack", [97, 38, 38]),
+    ("soul_sand", [81, 62, 50]),
+    ("glowstone", [171, 131, 84]),
+    ("end_stone", [219, 222, 158]),
+    ("purpur_block", [169, 125, 169]),
+    ("quartz_block", [235, 229, 222]),
+    ("prismarine", [99, 171, 158]),
+    ("sea_lantern", [172, 199, 190]),
+    ("bricks", [150, 97, 83]),
+    ("terracotta", [152, 94, 67]),
+    ("glass", [175, 213, 219]),
+    ("iron_block", [220, 220, 220]),
+    ("gold_block", [246, 208, 61]),
+    ("diamond_block", [98, 237, 228]),
+    ("emerald_block", [42, 203, 87]),
+    ("lapis_block", [30, 67, 140]),
+    ("redstone_block", [175, 24, 5]),
+    ("coal_block", [16, 15, 15]),
+    ("copper_block", [192, 107, 79]),
+    ("slime_block", [111, 192, 91]),
+    ("honey_block", [251, 185, 52]),
+    ("observer", [98, 98, 98]),
+    ("piston", [153, 127, 85]),
+    ("sticky_piston", [141, 150, 97]),
+    ("piston_head", [153, 127, 85]),
+    ("redstone_wire", [175, 24, 5]),
+    ("hopper", [67, 67, 67]),
+    ("tnt", [219, 68, 26]),
+    ("pumpkin", [198, 118, 24]),
+    ("melon", [111, 145, 30]),
+];
+
+const DEFAULT_COLOR: [u8; 3] = [128, 128, 128];
+
+fn block_id(block_state: &str) -> &str {
+    let name = block_state.split('[').next().unwrap_or(block_state);
+    name.strip_prefix("minecraft:").unwrap_or(name)
+}
+
+/// Returns true for the air variants.
+pub fn is_air(block_state: &str) -> bool {
+    matches!(block_id(block_state), "air" | "cave_air" | "void_air")
+}
+
+/// Returns the RGBA color used to draw a block, or `None` for air and other invisible blocks.
+///
+/// Colors come from a bundled table of common blocks, then from the dye and wood color
+/// families, and finally fall back to a neutral gray.
+pub fn block_color(block_state: &str) -> Option<[u8; 4]> {
+    let id = block_id(block_state);
+    if is_air(block_state) || matches!(id, "structure_void" | "barrier" | "light") {
+        return None;
+    }
+    let alpha = if id.contains("glass") || id == "water" || id.ends_with("ice") { 160 } else { 255 };
+    let [r, g, b] = base_color(id);
+    Some([r, g, b, alpha])
+}
+
+fn base_color(id: &str) -> [u8; 3] {
+    if let Some((_, color)) = BLOCK_COLORS.iter().find(|(name, _)| *name == id) {
+        return *color;
+    }
+
+    if let Some((_, color)) = DYE_COLORS.iter().find(|(name, _)| id.starts_with(&format!("{}_", name))) {
+        return *color;
+    }
+
+    if let Some((_, color)) = WOOD_COLORS.iter().find(|(name, _)| id.starts_with(&format!("{}_", name)) || id.starts_with(&format!("stripped_{}_", name))) {
+        return if id.ends_with("_leaves") { [72, 118, 36] } else { *color };
+    }
+
+    // Variants such as stairs, slabs and walls share the color of their base block
+    for suffix in ["_stairs", "_slab", "_wall", "_fence", "_fence_gate", "_pressure_plate", "_button"] {
+        if let Some(base) = id.strip_suffix(suffix) {
+            for candidate in [base.to_string(), format!("{}s", base), format!("{}_block", base)] {
+                if let Some((_, color)) = BLOCK_COLORS.iter().find(|(name, _)| *name == candidate) {
+                    return *color;
+                }
+            }
+        }
+    }
+
+    match id {
+        _ if id.ends_with("_ore") => [112, 112, 112],
+        _ if id.contains("leaves") || id.contains("grass") || id.contains("fern") || id.contains("vine") => [72, 118, 36],
+        _ if id.contains("copper") => [192, 107, 79],
+        _ if id.contains("deepslate") => [80, 80, 82],
+        _ if id.contains("blackstone") || id.contains("basalt") => [42, 36, 41],
+        _ if id.contains("nether_brick") => [44, 21, 26],
+        _ if id.contains("quartz") => [235, 229, 222],
+        _ if id.contains("prismarine") => [99, 171, 158],
+        _ if id.contains("sandstone") => [216, 203, 155],
+        _ if id.contains("rail") => [125, 110, 90],
+        _ if id.contains("redstone") || id.contains("repeater") || id.contains("comparator") => [175, 24, 5],
+        _ => DEFAULT_COLOR,
+    }
+}
@@ -0,0 +1,303 @@
+use crate::models::Schematic;
+use super::colors::block_color;
+use super::encode_png;
+use super::resource_pack::{ResourcePack, Texture};
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+/// Camera and output settings for [`render_isometric`].
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IsometricOptions {
+    /// Output image width in pixels.
+    pub width: u32,
+    /// Output image height in pixels.
+    pub height: u32,
+    /// Rotation around the vertical axis in degrees; 45 looks from the north-west corner.
+    pub yaw: f32,
+    /// Downward tilt of the camera in degrees, from 0 (side view) to 90 (top view).
+    pub pitch: f32,
+}
+
+impl Default for IsometricOptions {
+    fn default() -> Self {
+        IsometricOptions {
+            width: 512,
+            height: 512,
+            yaw: 45.0,
+            pitch: 30.0,
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl IsometricOptions {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Face {
+    Top,
+    Bottom,
+    North,
+    South,
+    West,
+    East,
+}
+
+impl Face {
+    const ALL: [Face; 6] = [Face::Top, Face::Bottom, Face::North, Face::South, Face::West, Face::East];
+
+    fn normal(self) -> [i32; 3] {
+        match self {
+            Face::Top => [0, 1, 0],
+            Face::Bottom => [0, -1, 0],
+            Face::North => [0, 0, -1],
+            Face::South => [0, 0, 1],
+            Face::West => [-1, 0, 0],
+            Face::East => [1, 0, 0],
+        }
+    }
+
+    /// Corner of the face relative to the block origin and the two edges spanning it. The
+    /// second edge of side faces points down so that textures are drawn upright.
+    fn geometry(self) -> ([f32; 3], [f32; 3], [f32; 3]) {
+        match self {
+            Face::Top => ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+            Face::Bottom => ([0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+            Face::North => ([1.0, 1.0, 0.0], [-1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+            Face::South => ([0.0, 1.0, 1.0], [1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+            Face::West => ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [0.0, -1.0, 0.0]),
+            Face::East => ([1.0, 1.0, 1.0], [0.0, 0.0, -1.0], [0.0, -1.0, 0.0]),
+        }
+    }
+
+    /// Brightness of the face, matching Minecraft's directional block shading.
+    fn brightness(self) -> f32 {
+        match self {
+            Face::Top => 1.0,
+            Face::Bottom => 0.5,
+            Face::North | Face::South => 0.8,
+            Face::West | Face::East => 0.6,
+        }
+    }
+}
+
+/// Orthographic camera: world positions are projected onto the `right`/`up` screen axes,
+/// and `forward` gives the depth used for hidden surface removal.
+struct Camera {
+    right: [f32; 3],
+    up: [f32; 3],
+    forward: [f32; 3],
+}
+
+impl Camera {
+    fn new(yaw: f32, pitch: f32) -> Self {
+        let (yaw, pitch) = (yaw.to_radians(), pitch.clamp(0.0, 90.0).to_radians());
+        Camera {
+            right: [-yaw.cos(), 0.0, yaw.sin()],
+            up: [yaw.sin() * pitch.sin(), pitch.cos(), yaw.cos() * pitch.sin()],
+            forward: [yaw.sin() * pitch.cos(), -pitch.sin(), yaw.cos() * pitch.cos()],
+        }
+    }
+
+    fn project(&self, point: [f32; 3]) -> [f32; 3] {
+        let dot = |axis: [f32; 3]| axis[0] * point[0] + axis[1] * point[1] + axis[2] * point[2];
+        [dot(self.right), dot(self.up), dot(self.forward)]
+    }
+
+    fn faces_camera(&self, face: Face) -> bool {
+        let [nx, ny, nz] = face.normal();
+        nx as f32 * self.forward[0] + ny as f32 * self.forward[1] + nz as f32 * self.forward[2] < 0.0
+    }
+}
+
+/// Color buffer with a depth buffer for hidden surface removal.
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 4]>,
+    depth: Vec<f32>,
+}
+
+impl Canvas {
+    /// Fills the parallelogram `origin + u * edge_u + v * edge_v` given in screen space, with
+    /// the depth of each point in the third component.
+    fn fill(&mut self, origin: [f32; 3], edge_u: [f32; 3], edge_v: [f32; 3], color: impl Fn(f32, f32) -> Option<[u8; 4]>) {
+        let determinant = edge_u[0] * edge_v[1] - edge_u[1] * edge_v[0];
+        if determinant.abs() < f32::EPSILON {
+            return;
+        }
+
+        let xs = [origin[0], origin[0] + edge_u[0], origin[0] + edge_v[0], origin[0] + edge_u[0] + edge_v[0]];
+        let ys = [origin[1], origin[1] + edge_u[1], origin[1] + edge_v[1], origin[1] + edge_u[1] + edge_v[1]];
+        let min_x = xs.iter().cloned().fold(f32::MAX, f32::min).floor().max(0.0) as usize;
+        let max_x = (xs.iter().cloned().fold(f32::MIN, f32::max).ceil().max(0.0) as usize).min(self.width);
+        let min_y = ys.iter().cloned().fold(f32::MAX, f32::min).floor().max(0.0) as usize;
+        let max_y = (ys.iter().cloned().fold(f32::MIN, f32::max).ceil().max(0.0) as usize).min(self.height);
+
+        for py in min_y..max_y {
+            for px in min_x..max_x {
+                let dx = px as f32 + 0.5 - origin[0];
+                let dy = py as f32 + 0.5 - origin[1];
+                let u = (dx * edge_v[1] - dy * edge_v[0]) / determinant;
+                let v = (edge_u[0] * dy - edge_u[1] * dx) / determinant;
+                if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+                    continue;
+                }
+
+                let depth = origin[2] + u * edge_u[2] + v * edge_v[2];
+                let offset = px + py * self.width;
+                if depth >= self.depth[offset] {
+                    continue;
+                }
+                if let Some(rgba) = color(u, v) {
+                    self.pixels[offset] = rgba;
+                    self.depth[offset] = depth;
+                }
+            }
+        }
+    }
+}
+
+fn shade(rgba: [u8; 4], brightness: f32) -> [u8; 4] {
+    let [r, g, b, _] = rgba;
+    let scale = |channel: u8| (channel as f32 * brightness) as u8;
+    [scale(r), scale(g), scale(b), 255]
+}
+
+/// Textures Minecraft stores in grayscale and tints with the biome color.
+fn is_tinted(texture_name: &str) -> bool {
+    texture_name.ends_with("_leaves") || matches!(texture_name, "grass_block_top" | "short_grass" | "grass" | "tall_grass_top" | "fern" | "vine" | "water_still" | "lily_pad")
+}
+
+/// Picks the texture drawn on a face of a block, trying the face specific textures first.
+fn face_texture<'a>(pack: &'a ResourcePack, block_state: &str, face: Face) -> Option<(String, &'a Texture)> {
+    let name = block_state.split('[').next().unwrap_or(block_state);
+    let id = name.strip_prefix("minecraft:").unwrap_or(name);
+
+    let mut bases = vec![id.to_string()];
+    for suffix in ["_stairs", "_slab", "_wall", "_fence", "_fence_gate"] {
+        if let Some(base) = id.strip_suffix(suffix) {
+            bases.extend([base.to_string(), format!("{}s", base), format!("{}_planks", base)]);
+        }
+    }
+    if id == "water" || id == "lava" {
+        bases.insert(0, format!("{}_still", id));
+    }
+
+    let suffixes: &[&str] = match face {
+        Face::Top => &["_top", ""],
+        Face::Bottom => &["_bottom", "_top", ""],
+        _ => &["_side", "_front", ""],
+    };
+    bases.iter()
+        .flat_map(|base| suffixes.iter().map(move |suffix| format!("{}{}", base, suffix)))
+        .find_map(|candidate| pack.get(&candidate).map(|texture| (candidate, texture)))
+}
+
+/// Renders an isometric (orthographic) view of the whole schematic as a PNG.
+///
+/// Every block face that is exposed and turned towards the camera is drawn with the block's
+/// color from the bundled table, shaded by its direction. When a resource pack is given, its
+/// block textures are sampled instead wherever a matching texture exists.
+pub fn render_isometric(schematic: &Schematic, options: &IsometricOptions, resource_pack: Option<&ResourcePack>) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let (width, height) = (options.width.max(1) as usize, options.height.max(1) as usize);
+    let camera = Camera::new(options.yaw, options.pitch);
+
+    let colors: Vec<Option<[u8; 4]>> = schematic.palette.iter().map(|block_state| block_color(block_state)).collect();
+    let opaque: Vec<bool> = colors.iter().map(|color| matches!(color, Some([_, _, _, 255]))).collect();
+
+    // Fit the projected bounding box of the schematic into the image with a small margin
+    let mut min = [f32::MAX; 2];
+    let mut max = [f32::MIN; 2];
+    for corner in 0..8 {
+        let point = [
+            if corner & 1 == 0 { 0.0 } else { schematic.width as f32 },
+            if corner & 2 == 0 { 0.0 } else { schematic.height as f32 },
+            if corner & 4 == 0 { 0.0 } else { schematic.length as f32 },
+        ];
+        let [sx, sy, _] = camera.project(point);
+        min = [min[0].min(sx), min[1].min(sy)];
+        max = [max[0].max(sx), max[1].max(sy)];
+    }
+    let margin = 0.05 * width.min(height) as f32;
+    let scale = ((width as f32 - 2.0 * margin) / (max[0] - min[0]).max(1.0))
+        .min((height as f32 - 2.0 * margin) / (max[1] - min[1]).max(1.0));
+    let offset = [
+        (width as f32 - (max[0] - min[0]) * scale) / 2.0,
+        (height as f32 - (max[1] - min[1]) * scale) / 2.0,
+    ];
+    let to_screen = |point: [f32; 3]| {
+        let [sx, sy, depth] = camera.project(point);
+        [(sx - min[0]) * scale + offset[0], (max[1] - sy) * scale + offset[1], depth]
+    };
+    let to_screen_edge = |edge: [f32; 3]| {
+        let [sx, sy, depth] = camera.project(edge);
+        [sx * scale, -sy * scale, depth]
+    };
+
+    let mut canvas = Canvas {
+        width,
+        height,
+        pixels: vec![[0; 4]; width * height],
+        depth: vec![f32::MAX; width * height],
+    };
+    let visible_faces: Vec<Face> = Face::ALL.iter().cloned().filter(|face| camera.faces_camera(*face)).collect();
+
+    for y in 0..schematic.height {
+        for z in 0..schematic.length {
+            for x in 0..schematic.width {
+                let index = schematic.blocks[schematic.index(x, y, z)];
+                let color = match colors[index as usize] {
+                    Some(color) => color,
+                    None => continue,
+                };
+
+                for &face in &visible_faces {
+                    let [nx, ny, nz] = face.normal();
+                    let (ax, ay, az) = (x as i32 + nx, y as i32 + ny, z as i32 + nz);
+                    let inside = ax >= 0 && ay >= 0 && az >= 0
+                        && (ax as usize) < schematic.width && (ay as usize) < schematic.height && (az as usize) < schematic.length;
+                    if inside {
+                        let neighbour = schematic.blocks[schematic.index(ax as usize, ay as usize, az as usize)];
+                        if opaque[neighbour as usize] || neighbour == index {
+                            continue;
+                        }
+                    }
+
+                    let (corner, edge_u, edge_v) = face.geometry();
+                    let origin = to_screen([x as f32 + corner[0], y as f32 + corner[1], z as f32 + corner[2]]);
+                    let (edge_u, edge_v) = (to_screen_edge(edge_u), to_screen_edge(edge_v));
+                    let brightness = face.brightness();
+
+                    match resource_pack.and_then(|pack| face_texture(pack, &schematic.palette[index as usize], face)) {
+                        Some((texture_name, texture)) => {
+                            let tint = if is_tinted(&texture_name) { Some(color) } else { None };
+                            canvas.fill(origin, edge_u, edge_v, |u, v| {
+                                let mut texel = texture.sample(u, v);
+                                if texel[3] == 0 {
+                                    return None;
+                                }
+                                if let Some(tint) = tint {
+                                    for channel in 0..3 {
+                                        texel[channel] = (texel[channel] as u32 * tint[channel] as u32 / 255) as u8;
+                                    }
+                                }
+                                Some(shade(texel, brightness))
+                            });
+                        }
+                        None => canvas.fill(origin, edge_u, edge_v, |_, _| Some(shade(color, brightness))),
+                    }
+                }
+            }
+        }
+    }
+
+    let rgba: Vec<u8> = canvas.pixels.iter().flatten().cloned().collect();
+    encode_png(width as u32, height as u32, &rgba)
+}
@@ -0,0 +1,77 @@
+use crate::models::Schematic;
+use super::colors::block_color;
+use super::{encode_png, top_blocks};
+
+/// Brightness factors Minecraft maps use for columns lower than, level with and higher
+/// than their northern neighbour.
+const MAP_SHADES: [u32; 3] = [180, 220, 255];
+
+fn shade(color: [u8; 4], factor: u32) -> [u8; 4] {
+    let [r, g, b, a] = color;
+    let scale = |channel: u8| (channel as u32 * factor / 255) as u8;
+    [scale(r), scale(g), scale(b), a]
+}
+
+/// Scales a `width` by `height` RGBA image up by an integer factor and encodes it as PNG.
+fn encode_scaled(width: usize, height: usize, pixels: &[[u8; 4]], scale: u32) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let scale = scale.max(1) as usize;
+    let mut rgba = Vec::with_capacity(width * height * scale * scale * 4);
+    for row in 0..height * scale {
+        for column in 0..width * scale {
+            rgba.extend_from_slice(&pixels[column / scale + (row / scale) * width]);
+        }
+    }
+    encode_png((width * scale) as u32, (height * scale) as u32, &rgba)
+}
+
+/// Renders a top-down map of the highest visible block of every column as a PNG.
+///
+/// Each block becomes a `scale` by `scale` square with north at the top. Columns are shaded
+/// like Minecraft maps: darker when lower than the block to the north, brighter when higher.
+pub fn render_top_down(schematic: &Schematic, scale: u32) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let (width, length) = (schematic.width, schematic.length);
+    let columns = top_blocks(width, schematic.height, length, &schematic.palette, &schematic.blocks);
+    let colors: Vec<Option<[u8; 4]>> = schematic.palette.iter().map(|block_state| block_color(block_state)).collect();
+
+    let mut pixels = vec![[0u8; 4]; width * length];
+    for z in 0..length {
+        for x in 0..width {
+            if let Some((index, y)) = columns[x + z * width] {
+                if let Some(color) = colors[index as usize] {
+                    let north = if z > 0 { columns[x + (z - 1) * width].map(|(_, north_y)| north_y) } else { None };
+                    let factor = match north {
+                        Some(north_y) if north_y > y => MAP_SHADES[0],
+                        Some(north_y) if north_y < y => MAP_SHADES[2],
+                        _ => MAP_SHADES[1],
+                    };
+                    pixels[x + z * width] = shade(color, factor);
+                }
+            }
+        }
+    }
+    encode_scaled(width, length, &pixels, scale)
+}
+
+/// Renders the horizontal slice at height `y` as a PNG, seen from above with north at the top.
+pub fn render_layer(schematic: &Schematic, y: usize, scale: u32) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if y >= schematic.height {
+        return Err(format!("Layer {} is outside the schematic height of {}.", y, schematic.height).into());
+    }
+    let colors: Vec<Option<[u8; 4]>> = schematic.palette.iter().map(|block_state| block_color(block_state)).collect();
+
+    let mut pixels = vec![[0u8; 4]; schematic.width * schematic.length];
+    for z in 0..schematic.length {
+        for x in 0..schematic.width {
+            let index = schematic.blocks[schematic.index(x, y, z)];
+            if let Some(color) = colors[index as usize] {
+                pixels[x + z * schematic.width] = color;
+            }
+        }
+    }
+    encode_scaled(schematic.width, schematic.length, &pixels, scale)
+}
+
+/// Renders every horizontal slice from the bottom up, one PNG per layer.
+pub fn render_layers(schematic: &Schematic, scale: u32) -> Result<Vec<Vec<u8>>, Box<dyn std::error::Error>> {
+    (0..schematic.height).map(|y| render_layer(schematic, y, scale)).collect()
+}
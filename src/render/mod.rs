@@ -0,0 +1,44 @@
+pub mod colors;
+mod isometric;
+mod map;
+mod preview;
+mod resource_pack;
+
+pub use isometric::{render_isometric, IsometricOptions};
+pub use map::{render_layer, render_layers, render_top_down};
+pub use resource_pack::{ResourcePack, Texture};
+pub use preview::argb_to_png;
+pub(crate) use preview::preview_image_data;
+
+/// Encodes tightly packed RGBA pixels as a PNG image.
+pub(crate) fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut png_data = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut png_data, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(rgba)?;
+    }
+    Ok(png_data)
+}
+
+/// Finds the highest visible block of every column, returned in `x + z * width` order as
+/// `(palette index, y)` pairs.
+pub(crate) fn top_blocks(width: usize, height: usize, length: usize, palette: &[String], blocks: &[u32]) -> Vec<Option<(u32, usize)>> {
+    let solid: Vec<bool> = palette.iter().map(|block_state| colors::block_color(block_state).is_some()).collect();
+    let mut columns = vec![None; width * length];
+
+    for z in 0..length {
+        for x in 0..width {
+            columns[x + z * width] = (0..height).rev().find_map(|y| {
+                let index = blocks[(y * length + z) * width + x];
+                match solid.get(index as usize) {
+                    Some(true) => Some((index, y)),
+                    _ => None,
+                }
+            });
+        }
+    }
+    columns
+}
@@ -0,0 +1,51 @@
+use super::colors::block_color;
+use super::{encode_png, top_blocks};
+
+/// Edge length of the square thumbnails written to Litematica's `PreviewImageData`.
+const PREVIEW_SIZE: usize = 140;
+
+/// Builds a square top-down thumbnail as ARGB pixels for Litematica's `PreviewImageData`.
+///
+/// The build is scaled to fit the square with its aspect ratio kept; columns are shaded by
+/// the height of their top block, and empty columns are left transparent.
+pub(crate) fn preview_image_data(width: usize, height: usize, length: usize, palette: &[String], blocks: &[u32]) -> Vec<i32> {
+    let columns = top_blocks(width, height, length, palette, blocks);
+    let colors: Vec<Option<[u8; 4]>> = palette.iter().map(|block_state| block_color(block_state)).collect();
+    let longest_side = std::cmp::max(width, length).max(1);
+
+    let mut pixels = vec![0i32; PREVIEW_SIZE * PREVIEW_SIZE];
+    for py in 0..PREVIEW_SIZE {
+        for px in 0..PREVIEW_SIZE {
+            let x = px * longest_side / PREVIEW_SIZE;
+            let z = py * longest_side / PREVIEW_SIZE;
+            if x >= width || z >= length {
+                continue;
+            }
+            if let Some((index, y)) = columns[x + z * width] {
+                if let Some([r, g, b, a]) = colors[index as usize] {
+                    let shade = 0.6 + 0.4 * (y + 1) as f32 / height as f32;
+                    let shaded = |channel: u8| (channel as f32 * shade) as u32;
+                    let argb = ((a as u32) << 24) | (shaded(r) << 16) | (shaded(g) << 8) | shaded(b);
+                    pixels[px + py * PREVIEW_SIZE] = argb as i32;
+                }
+            }
+        }
+    }
+    pixels
+}
+
+/// Encodes square ARGB pixel data, such as Litematica's `PreviewImageData`, as a PNG image.
+pub fn argb_to_png(pixels: &[i32]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let size = (pixels.len() as f64).sqrt() as usize;
+    if size == 0 || size * size != pixels.len() {
+        return Err(format!("Preview image data with {} pixels is not a square image.", pixels.len()).into());
+    }
+
+    let rgba: Vec<u8> = pixels.iter()
+        .flat_map(|&pixel| {
+            let [a, r, g, b] = (pixel as u32).to_be_bytes();
+            [r, g, b, a]
+        })
+        .collect();
+    encode_png(size as u32, size as u32, &rgba)
+}
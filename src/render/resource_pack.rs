@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+/// A decoded block texture as tightly packed RGBA pixels.
+#[derive(Debug, Clone)]
+pub struct Texture {
+    pub width: usize,
+    pub height: usize,
+    pub rgba: Vec<u8>,
+}
+
+impl Texture {
+    /// Samples the texel at the given texture coordinates, both in `0.0..=1.0`.
+    pub fn sample(&self, u: f32, v: f32) -> [u8; 4] {
+        let x = ((u * self.width as f32) as usize).min(self.width - 1);
+        let y = ((v * self.height as f32) as usize).min(self.height - 1);
+        let offset = (x + y * self.width) * 4;
+        [self.rgba[offset], self.rgba[offset + 1], self.rgba[offset + 2], self.rgba[offset + 3]]
+    }
+}
+
+/// Block textures taken from a Minecraft resource pack, keyed by texture name such as
+/// `oak_log_top`.
+#[derive(Debug, Clone, Default)]
+pub struct ResourcePack {
+    textures: HashMap<String, Texture>,
+}
+
+impl ResourcePack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, name: &str, texture: Texture) {
+        self.textures.insert(name.to_string(), texture);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Texture> {
+        self.textures.get(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+
+    /// Loads every texture under `assets/minecraft/textures/block/` from a resource pack zip.
+    #[cfg(feature = "resource-pack")]
+    pub fn from_zip(data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        use std::io::Read;
+
+        const BLOCK_TEXTURES: &str = "assets/minecraft/textures/block/";
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data))?;
+        let mut pack = ResourcePack::new();
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i)?;
+            let name = match file.name().strip_prefix(BLOCK_TEXTURES).and_then(|name| name.strip_suffix(".png")) {
+                Some(name) if !name.contains('/') => name.to_string(),
+                _ => continue,
+            };
+            let mut png_data = Vec::new();
+            file.read_to_end(&mut png_data)?;
+            pack.insert(&name, decode_texture(&png_data)?);
+        }
+        Ok(pack)
+    }
+}
+
+/// Decodes a PNG texture to RGBA, keeping only the first frame of animated textures.
+#[cfg(feature = "resource-pack")]
+fn decode_texture(png_data: &[u8]) -> Result<Texture, Box<dyn std::error::Error>> {
+    let mut decoder = png::Decoder::new(png_data);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+
+    let width = info.width as usize;
+    let height = std::cmp::min(info.height as usize, width);
+    let pixels = &buffer[..info.buffer_size()];
+    let rgba: Vec<u8> = match info.color_type {
+        png::ColorType::Rgba => pixels.to_vec(),
+        png::ColorType::Rgb => pixels.chunks(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => pixels.chunks(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => pixels.iter().flat_map(|&p| [p, p, p, 255]).collect(),
+        png::ColorType::Indexed => return Err("Indexed PNG textures should have been expanded".into()),
+    };
+
+    Ok(Texture {
+        width,
+        height,
+        rgba: rgba[..width * height * 4].to_vec(),
+    })
+}
@@ -0,0 +1,618 @@
+use crate::models::{BlockState, Schematic};
+use crate::operations::{block_entity_of, block_entity_position, can_hold, glob, ReplaceRule};
+#[cfg(feature = "registry")]
+use crate::registry::BlockRegistry;
+use super::upgrade::{apply_to_block_entity, apply_to_state, FIXES};
+use super::{block_entity_id, set_block_entity_id};
+
+/// Oldest DataVersion a schematic can be downgraded to. Sponge v2 and Litematica palettes
+/// only exist after the 1.13 flattening.
+const OLDEST_TARGET: i32 = 1519;
+
+/// Release that added each vanilla block, newest first. Patterns are checked in order, so
+/// older blocks that a later pattern would catch are listed at the top.
+const INTRODUCED_BLOCKS: &[(&str, i32)] = &[
+    ("wall_sign", 1519),
+    ("cobblestone_wall", 1519),
+    ("mossy_cobblestone_wall", 1519),
+    ("sculk_sensor", 2724),
+    // 1.21.4
+    ("pale_oak_*", 4189),
+    ("stripped_pale_oak_*", 4189),
+    ("pale_moss_*", 4189),
+    ("pale_hanging_moss", 4189),
+    ("creaking_heart", 4189),
+    ("*resin*", 4189),
+    ("*_eyeblossom", 4189),
+    ("potted_pale_oak_sapling", 4189),
+    // 1.21
+    ("*tuff_*", 3953),
+    ("polished_tuff", 3953),
+    ("chiseled_tuff", 3953),
+    ("*copper_door", 3953),
+    ("*copper_trapdoor", 3953),
+    ("*copper_grate", 3953),
+    ("*copper_bulb", 3953),
+    ("*chiseled_copper", 3953),
+    ("crafter", 3953),
+    ("trial_spawner", 3953),
+    ("vault", 3953),
+    ("heavy_core", 3953),
+    // 1.20
+    ("cherry_*", 3463),
+    ("potted_cherry_sapling", 3463),
+    ("piglin_*head", 3463),
+    ("stripped_cherry_*", 3463),
+    ("*_hanging_sign", 3463),
+    ("bamboo_planks", 3463),
+    ("bamboo_mosaic*", 3463),
+    ("*bamboo_block", 3463),
+    ("bamboo_stairs", 3463),
+    ("bamboo_slab", 3463),
+    ("bamboo_fence*", 3463),
+    ("bamboo_door", 3463),
+    ("bamboo_trapdoor", 3463),
+    ("bamboo_button", 3463),
+    ("bamboo_pressure_plate", 3463),
+    ("bamboo_sign", 3463),
+    ("bamboo_wall_sign", 3463),
+    ("chiseled_bookshelf", 3463),
+    ("decorated_pot", 3463),
+    ("suspicious_*", 3463),
+    ("*torchflower*", 3463),
+    ("pitcher_*", 3463),
+    ("pink_petals", 3463),
+    ("sniffer_egg", 3463),
+    ("calibrated_sculk_sensor", 3463),
+    // 1.19
+    ("mangrove_*", 3105),
+    ("stripped_mangrove_*", 3105),
+    ("potted_mangrove_propagule", 3105),
+    ("*mud*", 3105),
+    ("sculk*", 3105),
+    ("reinforced_deepslate", 3105),
+    ("frogspawn", 3105),
+    ("*_froglight", 3105),
+    // 1.17
+    ("*copper*", 2724),
+    ("*deepslate*", 2724),
+    ("*amethyst*", 2724),
+    ("tuff", 2724),
+    ("calcite", 2724),
+    ("*dripstone*", 2724),
+    ("moss_*", 2724),
+    ("*azalea*", 2724),
+    ("cave_vines*", 2724),
+    ("glow_lichen", 2724),
+    ("hanging_roots", 2724),
+    ("rooted_dirt", 2724),
+    ("spore_blossom", 2724),
+    ("*dripleaf*", 2724),
+    ("powder_snow", 2724),
+    ("*candle*", 2724),
+    ("lightning_rod", 2724),
+    ("raw_*_block", 2724),
+    ("smooth_basalt", 2724),
+    ("tinted_glass", 2724),
+    ("light", 2724),
+    ("lava_cauldron", 2724),
+    ("powder_snow_cauldron", 2724),
+    // 1.16
+    ("crimson_*", 2566),
+    ("warped_*", 2566),
+    ("stripped_crimson_*", 2566),
+    ("stripped_warped_*", 2566),
+    ("potted_crimson_*", 2566),
+    ("potted_warped_*", 2566),
+    ("*blackstone*", 2566),
+    ("netherite_block", 2566),
+    ("ancient_debris", 2566),
+    ("*basalt", 2566),
+    ("soul_fire", 2566),
+    ("soul_*torch", 2566),
+    ("soul_lantern", 2566),
+    ("soul_campfire", 2566),
+    ("soul_soil", 2566),
+    ("nether_gold_ore", 2566),
+    ("crying_obsidian", 2566),
+    ("respawn_anchor", 2566),
+    ("lodestone", 2566),
+    ("target", 2566),
+    ("chain", 2566),
+    ("shroomlight", 2566),
+    ("twisting_vines*", 2566),
+    ("weeping_vines*", 2566),
+    ("nether_sprouts", 2566),
+    ("chiseled_nether_bricks", 2566),
+    ("cracked_nether_bricks", 2566),
+    ("quartz_bricks", 2566),
+    // 1.15
+    ("bee_nest", 2225),
+    ("beehive", 2225),
+    ("honey_block", 2225),
+    ("honeycomb_block", 2225),
+    // 1.14
+    ("*_sign", 1952),
+    ("*_wall", 1952),
+    ("stone_stairs", 1952),
+    ("*granite_stairs", 1952),
+    ("*granite_slab", 1952),
+    ("*diorite_stairs", 1952),
+    ("*diorite_slab", 1952),
+    ("*andesite_stairs", 1952),
+    ("*andesite_slab", 1952),
+    ("mossy_*_stairs", 1952),
+    ("mossy_*_slab", 1952),
+    ("smooth_*_stairs", 1952),
+    ("smooth_*_slab", 1952),
+    ("end_stone_brick_*", 1952),
+    ("red_nether_brick_*", 1952),
+    ("cut_*sandstone_slab", 1952),
+    ("barrel", 1952),
+    ("smoker", 1952),
+    ("blast_furnace", 1952),
+    ("cartography_table", 1952),
+    ("loom", 1952),
+    ("fletching_table", 1952),
+    ("grindstone", 1952),
+    ("lectern", 1952),
+    ("smithing_table", 1952),
+    ("stonecutter", 1952),
+    ("bell", 1952),
+    ("lantern", 1952),
+    ("campfire", 1952),
+    ("scaffolding", 1952),
+    ("composter", 1952),
+    ("jigsaw", 1952),
+    ("sweet_berry_bush", 1952),
+    ("*bamboo*", 1952),
+    ("*cornflower", 1952),
+    ("*lily_of_the_valley", 1952),
+    ("*wither_rose", 1952),
+];
+
+/// Blocks renamed away in a release. `stone_slab` is not listed, since 1.14 reused the name.
+const REMOVED_BLOCKS: &[(&str, i32)] = &[
+    ("sign", 1952),
+    ("wall_sign", 1952),
+    ("grass_path", 2724),
+    ("grass", 3698),
+];
+
+/// Properties and property values added to existing blocks after 1.13, as block pattern,
+/// property, value or `*` for every value, and release.
+const INTRODUCED_PROPERTIES: &[(&str, &str, &str, i32)] = &[
+    ("note_block", "instrument", "iron_xylophone", 1952),
+    ("note_block", "instrument", "cow_bell", 1952),
+    ("note_block", "instrument", "didgeridoo", 1952),
+    ("note_block", "instrument", "bit", 1952),
+    ("note_block", "instrument", "banjo", 1952),
+    ("note_block", "instrument", "pling", 1952),
+    ("*lantern", "waterlogged", "*", 2566),
+    ("*rail", "waterlogged", "*", 2724),
+    ("*_leaves", "waterlogged", "*", 3105),
+    ("note_block", "instrument", "zombie", 3463),
+    ("note_block", "instrument", "skeleton", 3463),
+    ("note_block", "instrument", "creeper", 3463),
+    ("note_block", "instrument", "dragon", 3463),
+    ("note_block", "instrument", "wither_skeleton", 3463),
+    ("note_block", "instrument", "piglin", 3463),
+    ("note_block", "instrument", "custom_head", 3463),
+    ("decorated_pot", "cracked", "*", 3698),
+];
+
+/// Block entities added after 1.13, by id without namespace.
+const INTRODUCED_BLOCK_ENTITIES: &[(&str, i32)] = &[
+    ("barrel", 1952),
+    ("smoker", 1952),
+    ("blast_furnace", 1952),
+    ("lectern", 1952),
+    ("bell", 1952),
+    ("jigsaw", 1952),
+    ("campfire", 1952),
+    ("beehive", 2225),
+    ("sculk_sensor", 2724),
+    ("sculk_catalyst", 3105),
+    ("sculk_shrieker", 3105),
+    ("hanging_sign", 3463),
+    ("chiseled_bookshelf", 3463),
+    ("decorated_pot", 3463),
+    ("brushable_block", 3463),
+    ("calibrated_sculk_sensor", 3463),
+    ("crafter", 3953),
+    ("trial_spawner", 3953),
+    ("vault", 3953),
+    ("creaking_heart", 4189),
+];
+
+/// Older block entities that keep the data of newer ones, such as the text of a hanging sign.
+const BLOCK_ENTITY_SUBSTITUTES: [(&str, &str); 1] = [("minecraft:hanging_sign", "minecraft:sign")];
+
+/// Substitutes tried after the caller's own, most specific first. A substitute that is itself
+/// too new for the target is substituted again, so `cherry_hanging_sign` can end up as `sign`.
+const DEFAULT_SUBSTITUTES: &[(&str, &str)] = &[
+    // Wood families
+    ("pale_oak_*", "dark_oak_*"),
+    ("stripped_pale_oak_*", "stripped_dark_oak_*"),
+    ("cherry_*", "birch_*"),
+    ("stripped_cherry_*", "stripped_birch_*"),
+    ("muddy_mangrove_roots", "dirt[]"),
+    ("mangrove_roots", "jungle_wood[axis=y]"),
+    ("mangrove_propagule", "jungle_sapling[stage=0]"),
+    ("mangrove_*", "jungle_*"),
+    ("stripped_mangrove_*", "stripped_jungle_*"),
+    ("bamboo_mosaic", "jungle_planks"),
+    ("bamboo_mosaic_*", "jungle_*"),
+    ("stripped_bamboo_block", "stripped_jungle_log[axis]"),
+    ("bamboo_block", "jungle_log[axis]"),
+    ("bamboo_*", "jungle_*"),
+    ("crimson_nylium", "netherrack[]"),
+    ("warped_nylium", "netherrack[]"),
+    ("crimson_fungus", "red_mushroom[]"),
+    ("warped_fungus", "brown_mushroom[]"),
+    ("warped_wart_block", "nether_wart_block"),
+    ("*_roots", "dead_bush[]"),
+    ("*crimson_stem", "*spruce_log"),
+    ("*crimson_hyphae", "*spruce_wood"),
+    ("*warped_stem", "*dark_oak_log"),
+    ("*warped_hyphae", "*dark_oak_wood"),
+    ("crimson_*", "spruce_*"),
+    ("warped_*", "dark_oak_*"),
+    // Stone families
+    ("*cut_copper_stairs", "stone_brick_stairs[*]"),
+    ("*cut_copper_slab", "stone_brick_slab[*]"),
+    ("*copper_door", "iron_door[*]"),
+    ("*copper_trapdoor", "iron_trapdoor[*]"),
+    ("*copper_bulb", "redstone_lamp[lit]"),
+    ("*copper_grate", "glass[]"),
+    ("raw_*_block", "*_block"),
+    ("*copper*", "stone_bricks[]"),
+    ("deepslate_*_ore", "*_ore"),
+    ("cobbled_deepslate*", "cobblestone*"),
+    ("deepslate_brick*", "stone_brick*"),
+    ("deepslate_tile*", "stone_brick*"),
+    ("cracked_deepslate_*", "cracked_stone_bricks[]"),
+    ("*deepslate*", "stone[]"),
+    ("tuff_brick*", "stone_brick*"),
+    ("polished_tuff_*", "stone_brick_*"),
+    ("tuff_*", "cobblestone_*"),
+    ("chiseled_tuff*", "chiseled_stone_bricks[]"),
+    ("polished_tuff", "stone_bricks[]"),
+    ("tuff", "andesite[]"),
+    ("calcite", "diorite[]"),
+    ("polished_blackstone_button", "stone_button[*]"),
+    ("polished_blackstone_pressure_plate", "stone_pressure_plate[*]"),
+    ("polished_blackstone_brick*", "stone_brick*"),
+    ("polished_blackstone_*", "stone_brick_*"),
+    ("polished_blackstone", "stone_bricks[]"),
+    ("chiseled_polished_blackstone", "chiseled_stone_bricks[]"),
+    ("cracked_polished_blackstone_bricks", "cracked_stone_bricks[]"),
+    ("blackstone_*", "cobblestone_*"),
+    ("*blackstone", "cobblestone[]"),
+    ("mud_brick*", "brick*"),
+    ("packed_mud", "coarse_dirt[]"),
+    ("mud", "dirt[]"),
+    ("smooth_basalt", "smooth_stone[]"),
+    ("*basalt", "polished_andesite[]"),
+    ("reinforced_deepslate", "obsidian[]"),
+    ("cut_*sandstone_slab", "*sandstone_slab"),
+    // Single blocks
+    ("soul_lantern", "lantern[*]"),
+    ("soul_torch", "torch"),
+    ("soul_wall_torch", "wall_torch[*]"),
+    ("soul_campfire", "campfire[*]"),
+    ("soul_fire", "fire[]"),
+    ("soul_soil", "soul_sand[]"),
+    ("chain", "iron_bars[]"),
+    ("crying_obsidian", "obsidian"),
+    ("netherite_block", "obsidian"),
+    ("respawn_anchor", "obsidian[]"),
+    ("ancient_debris", "netherrack"),
+    ("nether_gold_ore", "nether_quartz_ore"),
+    ("quartz_bricks", "quartz_block"),
+    ("chiseled_nether_bricks", "nether_bricks"),
+    ("cracked_nether_bricks", "nether_bricks"),
+    ("shroomlight", "glowstone"),
+    ("target", "hay_block[]"),
+    ("lodestone", "chiseled_stone_bricks"),
+    ("amethyst_block", "purpur_block"),
+    ("budding_amethyst", "purpur_block"),
+    ("tinted_glass", "black_stained_glass"),
+    ("moss_block", "green_wool"),
+    ("moss_carpet", "green_carpet"),
+    ("rooted_dirt", "coarse_dirt"),
+    ("powder_snow", "snow_block"),
+    ("lightning_rod", "end_rod[facing]"),
+    ("*_froglight", "sea_lantern[]"),
+    ("sculk", "black_concrete"),
+    ("chiseled_bookshelf", "bookshelf[]"),
+    ("decorated_pot", "flower_pot[]"),
+    ("suspicious_sand", "sand[]"),
+    ("suspicious_gravel", "gravel[]"),
+    ("calibrated_sculk_sensor", "sculk_sensor[power,waterlogged]"),
+    ("bamboo", "sugar_cane[]"),
+    ("cornflower", "blue_orchid"),
+    ("lily_of_the_valley", "oxeye_daisy"),
+    ("wither_rose", "poppy"),
+    ("loom", "crafting_table"),
+    ("piglin_*head", "zombie_*head"),
+    ("potted_mangrove_propagule", "potted_jungle_sapling"),
+    ("potted_cherry_sapling", "potted_birch_sapling"),
+    ("potted_pale_oak_sapling", "potted_dark_oak_sapling"),
+    ("potted_*", "flower_pot"),
+    // Shapes, for anything a more specific substitute did not cover
+    ("*_wall_hanging_sign", "*_wall_sign[facing,waterlogged]"),
+    ("*_hanging_sign", "*_sign[rotation,waterlogged]"),
+    ("*_wall_sign", "wall_sign[facing,waterlogged]"),
+    ("*_sign", "sign[rotation,waterlogged]"),
+    ("*_stairs", "stone_brick_stairs[*]"),
+    ("*_slab", "stone_brick_slab[*]"),
+    ("*_wall", "cobblestone_wall[*]"),
+    ("*_fence", "oak_fence[*]"),
+    ("*_fence_gate", "oak_fence_gate[*]"),
+    ("*_door", "oak_door[*]"),
+    ("*_trapdoor", "oak_trapdoor[*]"),
+    ("*_button", "stone_button[*]"),
+    ("*_pressure_plate", "stone_pressure_plate[*]"),
+];
+
+/// How blocks the target version does not know are replaced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DowngradeOptions {
+    /// Substitutes tried before the bundled ones, such as `copper_block -> stone_bricks`.
+    pub substitutes: Vec<ReplaceRule>,
+    /// Block used when no substitute gives a block the target knows.
+    pub fallback: String,
+}
+
+impl Default for DowngradeOptions {
+    fn default() -> Self {
+        DowngradeOptions {
+            substitutes: Vec::new(),
+            fallback: "minecraft:air".to_string(),
+        }
+    }
+}
+
+/// Why a block state was changed, from least to most lossy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SubstitutionReason {
+    /// A block that was renamed or reshaped, written the way the target expects.
+    DataFix,
+    /// Properties the target does not know were removed.
+    Properties,
+    /// A substitute rule replaced a block the target does not know.
+    Substitute,
+    /// No substitute applied, so the fallback block was used.
+    Fallback,
+}
+
+/// A block state the downgrade changed, with how many blocks used it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Substitution {
+    pub original: String,
+    pub replacement: String,
+    pub reason: SubstitutionReason,
+    pub count: u64,
+}
+
+/// What `downgrade` changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DowngradeReport {
+    pub from: i32,
+    pub to: i32,
+    /// Names of the data fixes that were undone, newest first.
+    pub applied: Vec<&'static str>,
+    /// Changed block states, most used first.
+    pub substitutions: Vec<Substitution>,
+    /// Ids of block entities the target does not know, which were removed.
+    pub dropped_block_entities: Vec<String>,
+}
+
+fn vanilla_name(id: &str) -> Option<&str> {
+    id.strip_prefix("minecraft:")
+}
+
+/// Returns the bundled registry to check a target against, when it covers the target.
+#[cfg(feature = "registry")]
+fn target_registry(target: i32) -> Option<&'static BlockRegistry> {
+    let registry = BlockRegistry::for_data_version(target)?;
+    (target <= BlockRegistry::latest()?.data_version()).then_some(registry)
+}
+
+/// Returns whether `target` knows the block id. Blocks from other namespaces are assumed known.
+/// The bundled registry decides when it covers the target, and the tables above otherwise.
+fn knows_block(id: &str, target: i32) -> bool {
+    let Some(name) = vanilla_name(id) else {
+        return true;
+    };
+    #[cfg(feature = "registry")]
+    if let Some(registry) = target_registry(target) {
+        return registry.block(id).is_some();
+    }
+    let introduced = INTRODUCED_BLOCKS.iter()
+        .find(|(pattern, _)| glob(pattern, name).is_some())
+        .map_or(OLDEST_TARGET, |(_, version)| *version);
+    let removed = REMOVED_BLOCKS.iter().any(|(removed, version)| *removed == name && *version <= target);
+    introduced <= target && !removed
+}
+
+fn knows_block_entity(id: &str, target: i32) -> bool {
+    let Some(name) = vanilla_name(id) else {
+        return true;
+    };
+    INTRODUCED_BLOCK_ENTITIES.iter().all(|(known, version)| *known != name || *version <= target)
+}
+
+/// Removes properties, and properties set to values, that `target` does not know for the
+/// block, so the game uses the default instead.
+fn strip_properties(block_state: &BlockState, target: i32) -> BlockState {
+    let name = vanilla_name(block_state.name()).unwrap_or_default();
+    #[cfg(feature = "registry")]
+    let block = target_registry(target).and_then(|registry| registry.block(block_state.name()));
+    let mut stripped = block_state.clone();
+    for (key, value) in block_state.properties() {
+        #[cfg(feature = "registry")]
+        if let Some(block) = block {
+            if !block.property(key).is_some_and(|property| property.values.iter().any(|allowed| allowed == value)) {
+                stripped.remove_property(key);
+            }
+            continue;
+        }
+        let introduced = INTRODUCED_PROPERTIES.iter().any(|(pattern, property, introduced_value, version)| {
+            *property == key && (*introduced_value == "*" || *introduced_value == value) && *version > target && glob(pattern, name).is_some()
+        });
+        if introduced {
+            stripped.remove_property(key);
+        }
+    }
+    stripped
+}
+
+/// Picks a substitute the target knows, substituting again when a substitute is itself too new.
+fn substitute(block_state: &BlockState, rules: &[ReplaceRule], target: i32) -> Option<BlockState> {
+    let mut current = block_state.clone();
+    for _ in 0..8 {
+        let text = current.to_string();
+        let candidates: Vec<BlockState> = rules.iter()
+            .filter_map(|rule| rule.apply(&text))
+            .filter_map(|candidate| BlockState::parse(&candidate).ok())
+            .filter(|candidate| *candidate != current)
+            .collect();
+        if let Some(known) = candidates.iter().find(|candidate| knows_block(candidate.name(), target)) {
+            return Some(known.clone());
+        }
+        current = candidates.into_iter().next()?;
+    }
+    None
+}
+
+/// Returns the bundled substitutes, which are tried after `DowngradeOptions::substitutes`.
+pub fn default_substitutes() -> Vec<ReplaceRule> {
+    DEFAULT_SUBSTITUTES.iter().map(|(pattern, replacement)| ReplaceRule::new(pattern, replacement)).collect()
+}
+
+/// Downgrades a schematic from its DataVersion to the older `target`.
+///
+/// Data fixes newer than the target are undone first. Blocks the target still does not know
+/// are replaced by the first substitute giving a known block, or by the fallback, and
+/// properties the target does not know are removed. Block entities follow their block when
+/// it is substituted, and are dropped when the new block cannot hold one or the target has no
+/// such block entity.
+pub fn downgrade(schematic: &Schematic, target: i32, options: &DowngradeOptions) -> Result<(Schematic, DowngradeReport), Box<dyn std::error::Error>> {
+    let from = schematic.data_version.ok_or("Schematic has no DataVersion to downgrade from")?;
+    if target > from {
+        return Err(format!("Cannot downgrade from DataVersion {} to the newer {}", from, target).into());
+    }
+    if target < OLDEST_TARGET {
+        return Err(format!("Cannot downgrade below DataVersion {} (1.13)", OLDEST_TARGET).into());
+    }
+
+    let mut downgraded = schematic.clone();
+    let mut report = DowngradeReport { from, to: target, ..Default::default() };
+    let reverted: Vec<&_> = FIXES.iter().rev().filter(|data_fix| target < data_fix.version && data_fix.version <= from).collect();
+
+    let fallback = BlockState::parse(&options.fallback).map_err(|e| format!("Fallback {}", e))?;
+    let mut rules = options.substitutes.clone();
+    rules.extend(default_substitutes());
+    let mut changes: Vec<(String, SubstitutionReason)> = Vec::new();
+    for original in &schematic.palette {
+        // Entries that are not block states are left as they are
+        let Ok(parsed) = BlockState::parse(original) else {
+            changes.push((original.clone(), SubstitutionReason::DataFix));
+            continue;
+        };
+        let mut state = parsed.clone();
+        let mut reason = SubstitutionReason::DataFix;
+        for data_fix in &reverted {
+            if let Some(fixed) = apply_to_state(&data_fix.fix, &state, true) {
+                if fixed != state && !report.applied.contains(&data_fix.name) {
+                    report.applied.push(data_fix.name);
+                }
+                state = fixed;
+            }
+        }
+        if !knows_block(state.name(), target) {
+            (state, reason) = match substitute(&state, &rules, target) {
+                Some(replacement) => (replacement, SubstitutionReason::Substitute),
+                None => (fallback.clone(), SubstitutionReason::Fallback),
+            };
+        }
+        let stripped = strip_properties(&state, target);
+        if stripped != state && reason == SubstitutionReason::DataFix {
+            reason = SubstitutionReason::Properties;
+        }
+        match stripped == parsed {
+            true => changes.push((original.clone(), reason)),
+            false => changes.push((stripped.to_string(), reason)),
+        }
+    }
+
+    for block_entity in downgraded.block_entities.iter_mut() {
+        for data_fix in &reverted {
+            if apply_to_block_entity(&data_fix.fix, block_entity, true) && !report.applied.contains(&data_fix.name) {
+                report.applied.push(data_fix.name);
+            }
+        }
+    }
+
+    let mut counts = vec![0u64; schematic.palette.len()];
+    for &block in &schematic.blocks {
+        counts[block as usize] += 1;
+    }
+    for ((original, (replacement, reason)), count) in schematic.palette.iter().zip(&changes).zip(counts) {
+        if original != replacement && count > 0 {
+            report.substitutions.push(Substitution { original: original.clone(), replacement: replacement.clone(), reason: *reason, count });
+        }
+    }
+    report.substitutions.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.original.cmp(&b.original)));
+
+    downgraded.palette.clear();
+    let remap: Vec<u32> = changes.iter().map(|(state, _)| downgraded.palette_index(state)).collect();
+    for block in downgraded.blocks.iter_mut() {
+        *block = remap[*block as usize];
+    }
+
+    // Block entities follow their block the same way as with replace rules: a chest
+    // substituted by a barrel keeps its items, and one that became air is dropped
+    let palette = &schematic.palette;
+    let in_bounds = |[x, y, z]: [i32; 3]| x >= 0 && y >= 0 && z >= 0 && (x as usize) < schematic.width && (y as usize) < schematic.height && (z as usize) < schematic.length;
+    downgraded.block_entities.retain_mut(|block_entity| {
+        let Some(id) = block_entity_id(block_entity).map(str::to_string) else {
+            return true;
+        };
+        if let Some(position) = block_entity_position(block_entity).filter(|position| in_bounds(*position)) {
+            let index = schematic.index(position[0] as usize, position[1] as usize, position[2] as usize);
+            let parse = |block_state: &str| BlockState::parse(block_state).ok();
+            if let (Some(old_state), Some(new_state)) = (parse(&palette[schematic.blocks[index] as usize]), parse(&changes[schematic.blocks[index] as usize].0)) {
+                if old_state.name() != new_state.name() && !can_hold(&new_state, &id) {
+                    match block_entity_of(&new_state) {
+                        Some(new_id) => set_block_entity_id(block_entity, &new_id),
+                        None => {
+                            report.dropped_block_entities.push(id);
+                            return false;
+                        }
+                    }
+                }
+            }
+        }
+        let Some(id) = block_entity_id(block_entity).map(str::to_string) else {
+            return true;
+        };
+        if knows_block_entity(&id, target) {
+            return true;
+        }
+        match BLOCK_ENTITY_SUBSTITUTES.iter().find(|(newer, _)| *newer == id) {
+            Some((_, older)) if knows_block_entity(older, target) => {
+                set_block_entity_id(block_entity, older);
+                true
+            }
+            _ => {
+                report.dropped_block_entities.push(id);
+                false
+            }
+        }
+    });
+    downgraded.data_version = Some(target);
+    Ok((downgraded, report))
+}
@@ -0,0 +1,49 @@
+mod downgrade;
+mod upgrade;
+
+pub use downgrade::{default_substitutes, downgrade, DowngradeOptions, DowngradeReport, Substitution, SubstitutionReason};
+pub use upgrade::{upgrade, UpgradeReport};
+
+/// DataVersion of each release that changed something the fixers here know about, oldest first.
+pub const RELEASES: [(&str, i32); 15] = [
+    ("1.11", 922),
+    ("1.12", 1139),
+    ("1.13", 1519),
+    ("1.14", 1952),
+    ("1.15", 2225),
+    ("1.16", 2566),
+    ("1.17", 2724),
+    ("1.18", 2860),
+    ("1.19", 3105),
+    ("1.20", 3463),
+    ("1.20.3", 3698),
+    ("1.20.5", 3837),
+    ("1.21", 3953),
+    ("1.21.2", 4080),
+    ("1.21.4", 4189),
+];
+
+/// Reads a target version given either as a DataVersion number (`3953`) or a release name
+/// (`1.21`). Unknown patch releases such as `1.20.4` map to the latest known release before them.
+pub fn parse_data_version(version: &str) -> Option<i32> {
+    if let Ok(data_version) = version.parse() {
+        return Some(data_version);
+    }
+    let parts = |name: &str| -> Option<Vec<u32>> { name.split('.').map(|part| part.parse().ok()).collect() };
+    let requested = parts(version)?;
+    RELEASES.iter()
+        .rev()
+        .find(|(name, _)| parts(name).is_some_and(|release| release <= requested))
+        .map(|(_, data_version)| *data_version)
+}
+
+/// Returns the id of a block entity, which Sponge stores as `Id` and Litematica as `id`.
+fn block_entity_id(block_entity: &quartz_nbt::NbtCompound) -> Option<&str> {
+    block_entity.get::<_, &str>("Id").or_else(|_| block_entity.get::<_, &str>("id")).ok()
+}
+
+/// Replaces the id of a block entity under whichever key it already uses.
+fn set_block_entity_id(block_entity: &mut quartz_nbt::NbtCompound, id: &str) {
+    let key = if block_entity.contains_key("Id") { "Id" } else { "id" };
+    block_entity.insert(key, quartz_nbt::NbtTag::String(id.to_string()));
+}
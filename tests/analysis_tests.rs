@@ -0,0 +1,299 @@
+use quartz_nbt::{NbtCompound, NbtList, NbtTag};
+use schematic_converter::analysis::{align_by_bounds, diff, material_list, schematic_stats, validate, validate_schematic, BoundingBox, ChangeKind, Severity, ValidationReport};
+use schematic_converter::models::Schematic;
+use schematic_converter::{SchematicConverter, SchematicFormat};
+
+fn chest_with_items(items: &[(&str, i8)]) -> NbtCompound {
+    let mut list = NbtList::new();
+    for (slot, (id, count)) in items.iter().enumerate() {
+        let mut item = NbtCompound::new();
+        item.insert("Slot", NbtTag::Byte(slot as i8));
+        item.insert("id", NbtTag::String(id.to_string()));
+        item.insert("Count", NbtTag::Byte(*count));
+        list.push(NbtTag::Compound(item));
+    }
+    let mut chest = NbtCompound::new();
+    chest.insert("Id", NbtTag::String("minecraft:chest".to_string()));
+    chest.insert("Pos", NbtTag::IntArray(vec![0, 0, 0]));
+    chest.insert("Items", NbtTag::List(list));
+    chest
+}
+
+#[test]
+fn test_material_list_counts_like_litematica() {
+    let mut schematic = Schematic::new(4, 2, 2);
+    schematic.set_block(0, 0, 0, "minecraft:oak_door[facing=north,half=lower,hinge=left,open=false,powered=false]");
+    schematic.set_block(0, 1, 0, "minecraft:oak_door[facing=north,half=upper,hinge=left,open=false,powered=false]");
+    schematic.set_block(1, 0, 0, "minecraft:red_bed[facing=east,occupied=false,part=foot]");
+    schematic.set_block(2, 0, 0, "minecraft:red_bed[facing=east,occupied=false,part=head]");
+    schematic.set_block(3, 0, 0, "minecraft:stone_slab[type=double,waterlogged=false]");
+    schematic.set_block(3, 1, 0, "minecraft:stone_slab[type=bottom,waterlogged=true]");
+    schematic.set_block(0, 0, 1, "minecraft:water[level=0]");
+    schematic.set_block(1, 0, 1, "minecraft:fire[age=0]");
+    schematic.set_block(2, 0, 1, "minecraft:redstone_wire[east=side,north=none,power=0,south=none,west=side]");
+    schematic.set_block(3, 0, 1, "minecraft:potted_poppy");
+    schematic.set_block(0, 1, 1, "minecraft:chest[facing=north,type=single,waterlogged=false]");
+
+    let list = material_list(&schematic, false);
+    let count = |item: &str| list.entries.iter().find(|entry| entry.item == item).map(|entry| (entry.count, entry.waterlogged));
+
+    assert_eq!(count("minecraft:oak_door"), Some((1, 0)), "Doors count once");
+    assert_eq!(count("minecraft:red_bed"), Some((1, 0)), "Beds count once");
+    assert_eq!(count("minecraft:stone_slab"), Some((3, 1)), "Double slabs count twice and waterlogging is noted");
+    assert_eq!(count("minecraft:redstone"), Some((1, 0)), "Redstone wire needs redstone dust");
+    assert_eq!(count("minecraft:flower_pot"), Some((1, 0)));
+    assert_eq!(count("minecraft:poppy"), Some((1, 0)));
+    assert_eq!(count("minecraft:water"), None, "Water is excluded");
+    assert_eq!(count("minecraft:fire"), None, "Fire is excluded");
+    assert_eq!(count("minecraft:air"), None, "Air is excluded");
+    assert_eq!(list.total(), 9);
+
+    schematic.block_entities.push(chest_with_items(&[("minecraft:diamond", 5), ("minecraft:stone_slab", 4)]));
+    let with_containers = material_list(&schematic, true);
+    let count = |item: &str| with_containers.entries.iter().find(|entry| entry.item == item).map(|entry| entry.count);
+    assert_eq!(count("minecraft:diamond"), Some(5));
+    assert_eq!(count("minecraft:stone_slab"), Some(7));
+}
+
+#[test]
+fn test_material_list_exports() {
+    let mut schematic = Schematic::new(2, 1, 1);
+    schematic.set_block(0, 0, 0, "minecraft:stone");
+    schematic.set_block(1, 0, 0, "minecraft:stone");
+
+    let list = material_list(&schematic, false);
+    assert_eq!(list.to_csv(), "item,count,waterlogged\nminecraft:stone,2,0\n");
+    assert_eq!(list.to_json(), "[{\"item\":\"minecraft:stone\",\"count\":2,\"waterlogged\":0}]");
+}
+
+#[test]
+fn test_schematic_stats() {
+    let mut schematic = Schematic::new(4, 3, 4);
+    schematic.set_block(1, 0, 2, "minecraft:stone");
+    schematic.set_block(2, 1, 1, "minecraft:stone");
+    schematic.set_block(1, 1, 1, "minecraft:chest[facing=north,type=single,waterlogged=false]");
+    schematic.block_entities.push(chest_with_items(&[]));
+    schematic.data_version = Some(3465);
+    schematic.metadata.insert("Name", NbtTag::String("Test".to_string()));
+    schematic.metadata.insert("PreviewImageData", NbtTag::IntArray(vec![0; 4]));
+
+    let stats = schematic_stats(&schematic);
+    assert_eq!((stats.width, stats.height, stats.length), (4, 3, 4));
+    assert_eq!(stats.total_blocks, 48);
+    assert_eq!(stats.non_air_blocks, 3);
+    assert_eq!(stats.bounding_box, Some(BoundingBox { min: [1, 0, 1], max: [2, 1, 2] }));
+    assert_eq!(stats.bounding_box.unwrap().size(), [2, 2, 2]);
+    assert_eq!(stats.palette_size, 3);
+    assert_eq!(stats.block_counts[0], ("minecraft:air".to_string(), 45));
+    assert_eq!(stats.block_counts[1], ("minecraft:stone".to_string(), 2));
+    assert_eq!(stats.block_entity_counts, vec![("minecraft:chest".to_string(), 1)]);
+    assert!(stats.entity_counts.is_empty());
+    assert_eq!(stats.data_version, Some(3465));
+    assert_eq!(stats.metadata, vec![("Name".to_string(), "Test".to_string())], "Arrays are left out of the metadata");
+
+    let json = stats.to_json();
+    assert!(json.contains("\"boundingBox\":{\"min\":[1,0,1],\"max\":[2,1,2]}"));
+    assert!(json.contains("\"metadata\":{\"Name\":\"Test\"}"));
+    assert!(schematic_stats(&Schematic::new(2, 2, 2)).bounding_box.is_none());
+}
+
+#[test]
+fn test_stats_from_litematic() {
+    let sample_litematic = include_bytes!("test_schematics/sample.litematic");
+    let stats = SchematicConverter::new().stats(sample_litematic, SchematicFormat::Litematic).expect("Failed to read stats");
+
+    assert_eq!(stats.total_blocks, (stats.width * stats.height * stats.length) as u64);
+    assert_eq!(stats.block_counts.iter().map(|(_, count)| count).sum::<u64>(), stats.total_blocks);
+    assert!(stats.non_air_blocks > 0);
+    let bounds = stats.bounding_box.expect("Sample has blocks");
+    assert!(bounds.max[0] < stats.width && bounds.max[1] < stats.height && bounds.max[2] < stats.length);
+    assert!(stats.metadata.iter().any(|(key, _)| key == "Name"), "Litematica name is carried over");
+}
+
+#[test]
+fn test_diff_reports_block_changes() {
+    let mut old = Schematic::new(3, 1, 1);
+    old.set_block(0, 0, 0, "minecraft:stone");
+    old.set_block(1, 0, 0, "minecraft:oak_stairs[facing=north,half=bottom]");
+    old.set_block(2, 0, 0, "minecraft:dirt");
+    old.block_entities.push(chest_with_items(&[("minecraft:diamond", 1)]));
+
+    let mut new = Schematic::new(3, 1, 2);
+    new.set_block(0, 0, 0, "minecraft:granite");
+    new.set_block(1, 0, 0, "minecraft:oak_stairs[half=bottom,facing=north]");
+    new.set_block(2, 0, 1, "minecraft:glass");
+    new.block_entities.push(chest_with_items(&[("minecraft:diamond", 2)]));
+
+    let changes = diff(&old, &new, [0, 0, 0]);
+    let summary = changes.summary();
+    assert_eq!((summary.added, summary.removed, summary.changed), (1, 1, 1), "Property order does not count as a change");
+    assert_eq!(changes.blocks[0].kind, ChangeKind::Changed);
+    assert_eq!(changes.blocks[0].before, "minecraft:stone");
+    assert_eq!(changes.blocks[0].after, "minecraft:granite");
+    assert!(changes.blocks.iter().any(|change| change.kind == ChangeKind::Removed && change.position == [2, 0, 0]));
+    assert!(changes.blocks.iter().any(|change| change.kind == ChangeKind::Added && change.position == [2, 0, 1]));
+    assert_eq!(changes.block_entities.len(), 1);
+    assert_eq!(changes.block_entities[0].kind, ChangeKind::Changed);
+
+    let highlight = changes.to_schematic();
+    assert_eq!((highlight.width, highlight.height, highlight.length), (3, 1, 2));
+    assert_eq!(highlight.block_at(0, 0, 0), "minecraft:yellow_concrete");
+    assert_eq!(highlight.block_at(2, 0, 0), "minecraft:red_concrete");
+    assert_eq!(highlight.block_at(2, 0, 1), "minecraft:lime_concrete");
+    assert!(changes.to_json().starts_with("{\"summary\":{\"added\":1,\"removed\":1,\"changed\":1,\"blockEntities\":1,\"entities\":0}"));
+}
+
+#[test]
+fn test_diff_aligns_shifted_revisions() {
+    let mut old = Schematic::new(4, 1, 1);
+    old.set_block(1, 0, 0, "minecraft:stone");
+    old.set_block(2, 0, 0, "minecraft:stone");
+    let mut new = Schematic::new(2, 1, 1);
+    new.set_block(0, 0, 0, "minecraft:stone");
+    new.set_block(1, 0, 0, "minecraft:stone");
+
+    assert_eq!(align_by_bounds(&old, &new), [1, 0, 0]);
+    assert!(diff(&old, &new, [1, 0, 0]).is_empty());
+    assert!(!diff(&old, &new, [0, 0, 0]).is_empty());
+
+    let converter = SchematicConverter::new();
+    let sample_litematic = include_bytes!("test_schematics/sample.litematic");
+    let sample_schem = converter.convert(sample_litematic, SchematicFormat::Litematic, SchematicFormat::Schem).unwrap();
+    let same = converter.diff(sample_litematic, SchematicFormat::Litematic, &sample_schem, SchematicFormat::Schem, false).unwrap();
+    assert!(same.blocks.is_empty(), "A converted schematic has the same blocks");
+}
+
+fn nbt_bytes(nbt: &NbtCompound) -> Vec<u8> {
+    let mut data = Vec::new();
+    quartz_nbt::io::write_nbt(&mut data, None, nbt, quartz_nbt::io::Flavor::GzCompressed).unwrap();
+    data
+}
+
+fn has_finding(report: &ValidationReport, severity: Severity, text: &str) -> bool {
+    report.findings.iter().any(|finding| finding.severity == severity && finding.message.contains(text))
+}
+
+#[test]
+fn test_validate_accepts_sample_files() {
+    for (data, format) in [
+        (&include_bytes!("test_schematics/bulk.litematic")[..], SchematicFormat::Litematic),
+        (&include_bytes!("test_schematics/sample.schem")[..], SchematicFormat::Schem),
+    ] {
+        let report = validate(data, format);
+        assert!(report.findings.is_empty(), "Unexpected findings: {:?}", report.findings);
+        assert!(report.is_valid());
+    }
+    let report = validate(b"not a schematic", SchematicFormat::Schem);
+    assert!(!report.is_valid());
+    assert!(report.to_json().starts_with("{\"valid\":false,\"findings\":[{\"severity\":\"error\""));
+}
+
+#[test]
+fn test_validate_sponge_layout() {
+    let mut schematic = Schematic::new(2, 1, 1);
+    schematic.set_block(1, 0, 0, "minecraft:stone");
+    let mut nbt = schematic.to_sponge_nbt().unwrap();
+    nbt.insert("BlockData", NbtTag::ByteArray(vec![0, 1, 1]));
+    let report = validate(&nbt_bytes(&nbt), SchematicFormat::Schem);
+    assert!(has_finding(&report, Severity::Error, "BlockData does not match the 2x1x1 size"), "{:?}", report.findings);
+
+    let mut palette = NbtCompound::new();
+    palette.insert("minecraft:air", NbtTag::Int(0));
+    palette.insert("minecraft:stone", NbtTag::Int(0));
+    nbt.insert("Palette", NbtTag::Compound(palette));
+    nbt.insert("PaletteMax", NbtTag::Int(3));
+    nbt.insert("BlockData", NbtTag::ByteArray(vec![0, 5]));
+    let report = validate(&nbt_bytes(&nbt), SchematicFormat::Schem);
+    assert!(has_finding(&report, Severity::Error, "share id 0"));
+    assert!(has_finding(&report, Severity::Error, "palette id 5 which is not in the palette"));
+    assert!(has_finding(&report, Severity::Warning, "PaletteMax is 3 but the palette has 2 entries"));
+
+    nbt.inner_mut().remove("Width");
+    assert!(has_finding(&validate(&nbt_bytes(&nbt), SchematicFormat::Schem), Severity::Error, "Width is missing"));
+}
+
+#[test]
+fn test_validate_litematic_layout() {
+    let sample = include_bytes!("test_schematics/sample.litematic");
+    let mut decompressed = Vec::new();
+    std::io::copy(&mut flate2::read::GzDecoder::new(&sample[..]), &mut decompressed).unwrap();
+    let (litematic, _) = quartz_nbt::io::read_nbt(&mut std::io::Cursor::new(decompressed), quartz_nbt::io::Flavor::Uncompressed).unwrap();
+    let region_name = litematic.get::<_, &NbtCompound>("Regions").unwrap().inner().keys().next().unwrap().clone();
+    let with_region = |change: &dyn Fn(&mut NbtCompound)| -> Vec<u8> {
+        let mut changed = litematic.clone();
+        let mut regions = changed.get::<_, &NbtCompound>("Regions").unwrap().clone();
+        let mut region = regions.get::<_, &NbtCompound>(&region_name).unwrap().clone();
+        change(&mut region);
+        regions.insert(region_name.clone(), NbtTag::Compound(region));
+        changed.insert("Regions", NbtTag::Compound(regions));
+        nbt_bytes(&changed)
+    };
+
+    let truncated = with_region(&|region| {
+        let block_states = region.get::<_, &[i64]>("BlockStates").unwrap();
+        let shorter = block_states[..block_states.len() - 1].to_vec();
+        region.insert("BlockStates", NbtTag::LongArray(shorter));
+    });
+    let report = validate(&truncated, SchematicFormat::Litematic);
+    assert!(has_finding(&report, Severity::Error, "BlockStates has"), "{:?}", report.findings);
+
+    let duplicated = with_region(&|region| {
+        let mut palette = region.get::<_, &NbtList>("BlockStatePalette").unwrap().clone();
+        let first = palette.get::<&NbtTag>(0).unwrap().clone();
+        palette.push(first);
+        region.insert("BlockStatePalette", NbtTag::List(palette));
+    });
+    assert!(has_finding(&validate(&duplicated, SchematicFormat::Litematic), Severity::Error, "palette entries 0 and"));
+
+    let empty = with_region(&|region| {
+        let mut size = NbtCompound::new();
+        for axis in ["x", "y", "z"] {
+            size.insert(axis, NbtTag::Int(0));
+        }
+        region.insert("Size", NbtTag::Compound(size));
+    });
+    assert!(has_finding(&validate(&empty, SchematicFormat::Litematic), Severity::Error, "which holds no blocks"));
+
+    let huge = with_region(&|region| {
+        let mut size = NbtCompound::new();
+        for axis in ["x", "y", "z"] {
+            size.insert(axis, NbtTag::Int(i32::MAX));
+        }
+        region.insert("Size", NbtTag::Compound(size));
+    });
+    let report = validate(&huge, SchematicFormat::Litematic);
+    assert!(has_finding(&report, Severity::Error, "more blocks than can be counted"), "{:?}", report.findings);
+    assert!(!report.is_valid());
+}
+
+#[test]
+fn test_validate_schematic_contents() {
+    let mut schematic = Schematic::new(3, 1, 1);
+    schematic.set_block(0, 0, 0, "minecraft:chest[facing=north,type=single,waterlogged=false]");
+    schematic.set_block(1, 0, 0, "minecraft:stone");
+    schematic.palette.push("stone".to_string());
+    schematic.palette.push("Minecraft:Stone Bricks".to_string());
+    schematic.palette.push("create:shaft[axis=x]".to_string());
+    let chest = |position: Vec<i32>| {
+        let mut block_entity = NbtCompound::new();
+        block_entity.insert("Id", NbtTag::String("minecraft:chest".to_string()));
+        block_entity.insert("Pos", NbtTag::IntArray(position));
+        block_entity
+    };
+    schematic.block_entities.push(chest(vec![0, 0, 0]));
+    schematic.block_entities.push(chest(vec![1, 0, 0]));
+    schematic.block_entities.push(chest(vec![2, 0, 0]));
+    schematic.block_entities.push(chest(vec![3, 0, 0]));
+
+    let report = validate_schematic(&schematic);
+    assert!(has_finding(&report, Severity::Warning, "'minecraft:stone' and 'stone' are the same block state"));
+    assert!(has_finding(&report, Severity::Error, "Unknown block id 'Minecraft:Stone Bricks'"));
+    assert!(has_finding(&report, Severity::Info, "'create:shaft' is not a vanilla block"));
+    assert!(has_finding(&report, Severity::Warning, "at [1, 0, 0] sits on minecraft:stone, which cannot hold it"));
+    assert!(has_finding(&report, Severity::Warning, "at [2, 0, 0] sits on air"));
+    assert!(has_finding(&report, Severity::Error, "at [3, 0, 0] is outside the schematic"));
+    assert!(!report.findings.iter().any(|finding| finding.message.contains("[0, 0, 0]")), "The chest on a chest is fine");
+
+    schematic.blocks[2] = 9;
+    assert!(has_finding(&validate_schematic(&schematic), Severity::Error, "Block palette index 9 is out of range"));
+}
@@ -4,8 +4,6 @@ use schematic_converter::{SchematicConverter, SchematicFormat};
 fn test_litematic_to_schematic_to_schem() {
     let sample_litematic = include_bytes!("test_schematics/sample.litematic");
 
-    std::fs::create_dir_all("tests/outputs").unwrap();
-
     let converter = SchematicConverter::new();
 
 
@@ -23,5 +21,9 @@ fn test_litematic_to_schematic_to_schem() {
         SchematicFormat::Schem
     ).expect("Failed to convert Schematic to Schem");
 
-    std::fs::write("tests/outputs/sample.schem", schem_output).unwrap();
+    // Written outside the tree so running the tests leaves the checkout clean
+    let output_path = std::env::temp_dir().join(format!("sample_{}.schem", std::process::id()));
+    std::fs::write(&output_path, &schem_output).unwrap();
+    assert_eq!(std::fs::read(&output_path).unwrap(), schem_output);
+    std::fs::remove_file(&output_path).unwrap();
 }
\ No newline at end of file
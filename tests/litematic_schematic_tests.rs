@@ -1,7 +1,9 @@
 use quartz_nbt::io::Flavor;
-use schematic_converter::converters::{litematic_to_schematic, schematic_to_litematic};
+use schematic_converter::converters::{litematic_preview_png, litematic_to_schematic, schematic_to_litematic};
 use std::io::Cursor;
-use quartz_nbt::{NbtCompound, NbtTag};
+use quartz_nbt::{NbtCompound, NbtList, NbtTag};
+use schematic_converter::models::{BlockState, Schematic};
+use schematic_converter::{ConvertOptions, SchematicFormat};
 
 #[test]
 fn test_litematic_to_schematic_conversion() {
@@ -101,4 +103,255 @@ fn print_nbt_contents(data: &[u8], is_compressed: bool) {
     };
 
     println!("{:#?}", nbt);
-}
\ No newline at end of file
+}
+#[test]
+fn test_block_data_survives_litematic_roundtrip() {
+    let big_quarry = include_bytes!("test_schematics/big_quarry.litematic");
+
+    let mut schematic_data = Vec::new();
+    litematic_to_schematic(Cursor::new(big_quarry), &mut schematic_data).unwrap();
+
+    let mut litematic_data = Vec::new();
+    schematic_to_litematic(Cursor::new(&schematic_data), &mut litematic_data).unwrap();
+
+    let mut roundtrip_data = Vec::new();
+    litematic_to_schematic(Cursor::new(litematic_data), &mut roundtrip_data).unwrap();
+
+    let parse = |data: &[u8]| -> NbtCompound {
+        let (nbt, _) = quartz_nbt::io::read_nbt(&mut Cursor::new(data), Flavor::Uncompressed).unwrap();
+        nbt
+    };
+    let original = parse(&schematic_data);
+    let roundtrip = parse(&roundtrip_data);
+
+    assert_eq!(
+        original.get::<_, &NbtTag>("BlockData").unwrap(),
+        roundtrip.get::<_, &NbtTag>("BlockData").unwrap(),
+        "Block data should be identical after a litematic roundtrip"
+    );
+    assert_eq!(
+        original.get::<_, &NbtCompound>("Palette").unwrap().len(),
+        roundtrip.get::<_, &NbtCompound>("Palette").unwrap().len()
+    );
+}
+
+#[test]
+fn test_oversized_schematic_header_is_rejected() {
+    let mut schematic_nbt = NbtCompound::new();
+    schematic_nbt.insert("Width", NbtTag::Short(4096));
+    schematic_nbt.insert("Height", NbtTag::Short(256));
+    schematic_nbt.insert("Length", NbtTag::Short(4096));
+    let mut palette = NbtCompound::new();
+    palette.insert("minecraft:air", NbtTag::Int(0));
+    schematic_nbt.insert("Palette", NbtTag::Compound(palette));
+    schematic_nbt.insert("BlockData", NbtTag::ByteArray(vec![0]));
+    let mut schematic_data = Vec::new();
+    quartz_nbt::io::write_nbt(&mut schematic_data, None, &schematic_nbt, Flavor::GzCompressed).unwrap();
+
+    let error = schematic_to_litematic(Cursor::new(schematic_data), Vec::new()).unwrap_err();
+    assert!(error.to_string().contains("Region too large"), "{}", error);
+}
+
+#[test]
+fn test_litematic_output_has_full_metadata() {
+    let sample_schem = include_bytes!("test_schematics/sample.schem");
+
+    let mut litematic_output = Vec::new();
+    schematic_to_litematic(Cursor::new(sample_schem), &mut litematic_output).unwrap();
+    assert_eq!(&litematic_output[..2], &[0x1f, 0x8b], "Litematic output should be gzipped");
+
+    let mut decoder = flate2::read::GzDecoder::new(Cursor::new(litematic_output));
+    let mut decompressed = Vec::new();
+    std::io::copy(&mut decoder, &mut decompressed).unwrap();
+    let (nbt, _) = quartz_nbt::io::read_nbt(&mut Cursor::new(decompressed), Flavor::Uncompressed).unwrap();
+
+    assert!(nbt.contains_key("Version"), "Output should contain 'Version' tag");
+    assert!(nbt.contains_key("SubVersion"), "Output should contain 'SubVersion' tag");
+
+    let metadata = nbt.get::<_, &NbtCompound>("Metadata").unwrap();
+    for key in ["Name", "Author", "Description", "RegionCount", "TotalBlocks", "TotalVolume", "TimeCreated", "TimeModified", "EnclosingSize"] {
+        assert!(metadata.contains_key(key), "Metadata should contain '{}' tag", key);
+    }
+    assert_eq!(metadata.get::<_, i32>("RegionCount").unwrap(), 1);
+
+    let enclosing_size = metadata.get::<_, &NbtCompound>("EnclosingSize").unwrap();
+    let volume = enclosing_size.get::<_, i32>("x").unwrap() * enclosing_size.get::<_, i32>("y").unwrap() * enclosing_size.get::<_, i32>("z").unwrap();
+    assert_eq!(metadata.get::<_, i32>("TotalVolume").unwrap(), volume);
+    let total_blocks = metadata.get::<_, i32>("TotalBlocks").unwrap();
+    assert!(total_blocks > 0 && total_blocks <= volume, "TotalBlocks should count the non-air blocks");
+
+    let regions = nbt.get::<_, &NbtCompound>("Regions").unwrap();
+    let region = regions.get::<_, &NbtCompound>("main").unwrap();
+    for key in ["Position", "Size", "BlockStatePalette", "BlockStates", "PendingBlockTicks", "PendingFluidTicks"] {
+        assert!(region.contains_key(key), "Region should contain '{}' tag", key);
+    }
+}
+
+#[test]
+fn test_litematic_output_has_preview_image() {
+    let big_quarry = include_bytes!("test_schematics/big_quarry.litematic");
+
+    let mut schematic_data = Vec::new();
+    litematic_to_schematic(Cursor::new(big_quarry), &mut schematic_data).unwrap();
+
+    let mut litematic_data = Vec::new();
+    schematic_to_litematic(Cursor::new(&schematic_data), &mut litematic_data).unwrap();
+
+    let png = litematic_preview_png(Cursor::new(&litematic_data))
+        .unwrap()
+        .expect("Generated litematic should carry a preview");
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n", "Preview should be encoded as PNG");
+
+    // Converting the litematic again keeps the existing preview rather than drawing a new one
+    let mut second_schematic = Vec::new();
+    litematic_to_schematic(Cursor::new(&litematic_data), &mut second_schematic).unwrap();
+    let mut second_litematic = Vec::new();
+    schematic_to_litematic(Cursor::new(&second_schematic), &mut second_litematic).unwrap();
+    assert_eq!(litematic_preview_png(Cursor::new(&second_litematic)).unwrap(), Some(png));
+}
+
+#[test]
+fn test_block_entities_are_translated_between_layouts() {
+    let bulk = include_bytes!("test_schematics/bulk.litematic");
+    let parse = |data: &[u8]| -> NbtCompound {
+        let (nbt, _) = quartz_nbt::io::read_nbt(&mut Cursor::new(data), Flavor::Uncompressed).unwrap();
+        nbt
+    };
+
+    let mut schematic_data = Vec::new();
+    litematic_to_schematic(Cursor::new(bulk), &mut schematic_data).unwrap();
+    let schematic = parse(&schematic_data);
+    assert!(!schematic.contains_key("TileEntities"), "Sponge v2 stores block entities as 'BlockEntities'");
+    let block_entities = schematic.get::<_, &NbtList>("BlockEntities").unwrap();
+    assert!(!block_entities.is_empty());
+    for tag in block_entities.iter() {
+        let NbtTag::Compound(block_entity) = tag else { panic!("Block entities should be compounds") };
+        assert_eq!(block_entity.get::<_, &[i32]>("Pos").unwrap().len(), 3);
+        assert!(block_entity.get::<_, &str>("Id").unwrap().starts_with("minecraft:"));
+        for key in ["x", "y", "z", "id"] {
+            assert!(!block_entity.contains_key(key), "Litematica key '{}' should be translated", key);
+        }
+    }
+    let chest = block_entities.iter()
+        .find_map(|tag| match tag {
+            NbtTag::Compound(block_entity) if block_entity.get::<_, &str>("Id").ok() == Some("minecraft:chest") => Some(block_entity.clone()),
+            _ => None,
+        })
+        .expect("bulk.litematic has chests");
+
+    let mut litematic_data = Vec::new();
+    schematic_to_litematic(Cursor::new(&schematic_data), &mut litematic_data).unwrap();
+    let mut decoder = flate2::read::GzDecoder::new(Cursor::new(litematic_data));
+    let mut decompressed = Vec::new();
+    std::io::copy(&mut decoder, &mut decompressed).unwrap();
+    let litematic = parse(&decompressed);
+    let region = litematic.get::<_, &NbtCompound>("Regions").unwrap().get::<_, &NbtCompound>("main").unwrap();
+    let tile_entities = region.get::<_, &NbtList>("TileEntities").unwrap();
+    assert_eq!(tile_entities.len(), block_entities.len());
+
+    let position = chest.get::<_, &[i32]>("Pos").unwrap();
+    let tile_entity = tile_entities.iter()
+        .find_map(|tag| match tag {
+            NbtTag::Compound(tile_entity) if ["x", "y", "z"].map(|key| tile_entity.get::<_, i32>(key).ok()) == [0, 1, 2].map(|axis| Some(position[axis])) => Some(tile_entity),
+            _ => None,
+        })
+        .expect("The chest should keep its position");
+    assert_eq!(tile_entity.get::<_, &str>("id").unwrap(), "minecraft:chest");
+    assert!(!tile_entity.contains_key("Pos") && !tile_entity.contains_key("Id"));
+    assert_eq!(tile_entity.get::<_, &NbtTag>("Items").ok(), chest.get::<_, &NbtTag>("Items").ok(), "Chest contents should survive");
+}
+
+#[test]
+fn test_entities_are_rebased_between_frames() {
+    let parse = |data: &[u8]| -> NbtCompound {
+        let (nbt, _) = quartz_nbt::io::read_nbt(&mut Cursor::new(data), Flavor::Uncompressed).unwrap();
+        nbt
+    };
+    let compounds = |list: &NbtList| -> Vec<NbtCompound> {
+        list.iter().filter_map(|tag| match tag {
+            NbtTag::Compound(compound) => Some(compound.clone()),
+            _ => None,
+        }).collect()
+    };
+    let position = |entity: &NbtCompound| -> [f64; 3] {
+        let position: Vec<f64> = entity.get::<_, &NbtList>("Pos").unwrap().iter().map(|tag| match tag {
+            NbtTag::Double(value) => *value,
+            _ => panic!("Entity positions should be doubles"),
+        }).collect();
+        [position[0], position[1], position[2]]
+    };
+
+    // The blast chamber region has negative sizes, so its entity positions are negative
+    let mut schematic_data = Vec::new();
+    litematic_to_schematic(Cursor::new(include_bytes!("test_schematics/big_quarry.litematic")), &mut schematic_data).unwrap();
+    let schematic = parse(&schematic_data);
+    let size = ["Width", "Height", "Length"].map(|key| schematic.get::<_, i16>(key).unwrap() as f64);
+    let entities = compounds(schematic.get::<_, &NbtList>("Entities").unwrap());
+    for entity in &entities {
+        assert!(entity.get::<_, &str>("Id").unwrap().starts_with("minecraft:"));
+        assert!(!entity.contains_key("id"));
+        let position = position(entity);
+        assert!((0..3).all(|axis| position[axis] >= 0.0 && position[axis] <= size[axis]), "Entity at {:?} is outside the schematic {:?}", position, size);
+    }
+
+    // Item frames in bulk.litematic keep world coordinates in TileX/TileY/TileZ
+    let mut schematic_data = Vec::new();
+    litematic_to_schematic(Cursor::new(include_bytes!("test_schematics/bulk.litematic")), &mut schematic_data).unwrap();
+    let schematic = parse(&schematic_data);
+    let item_frames = compounds(schematic.get::<_, &NbtList>("Entities").unwrap());
+    assert_eq!(item_frames.len(), 3);
+    for item_frame in &item_frames {
+        let position = position(item_frame);
+        let tile = ["TileX", "TileY", "TileZ"].map(|key| item_frame.get::<_, i32>(key).unwrap());
+        assert!((0..3).all(|axis| (tile[axis] - position[axis].floor() as i32).abs() <= 1), "Tile {:?} should hold the item frame at {:?}", tile, position);
+    }
+
+    let mut litematic_data = Vec::new();
+    schematic_to_litematic(Cursor::new(&schematic_data), &mut litematic_data).unwrap();
+    let mut decoder = flate2::read::GzDecoder::new(Cursor::new(litematic_data));
+    let mut decompressed = Vec::new();
+    std::io::copy(&mut decoder, &mut decompressed).unwrap();
+    let litematic = parse(&decompressed);
+    let region = litematic.get::<_, &NbtCompound>("Regions").unwrap().get::<_, &NbtCompound>("main").unwrap();
+    let entities = compounds(region.get::<_, &NbtList>("Entities").unwrap());
+    assert_eq!(entities.len(), item_frames.len());
+    for (entity, item_frame) in entities.iter().zip(&item_frames) {
+        assert_eq!(entity.get::<_, &str>("id").unwrap(), "minecraft:item_frame");
+        assert_eq!(position(entity), position(item_frame));
+        assert_eq!(entity.get::<_, i32>("TileX").ok(), item_frame.get::<_, i32>("TileX").ok());
+    }
+}
+
+#[test]
+fn test_palette_entries_are_canonical_block_states() {
+    let bulk = include_bytes!("test_schematics/bulk.litematic");
+    let mut schematic_data = Vec::new();
+    litematic_to_schematic(Cursor::new(bulk), &mut schematic_data).unwrap();
+    let (nbt, _) = quartz_nbt::io::read_nbt(&mut Cursor::new(&schematic_data), Flavor::Uncompressed).unwrap();
+
+    let palette = nbt.get::<_, &NbtCompound>("Palette").unwrap();
+    assert!(palette.inner().keys().any(|key| key.contains(',')), "bulk.litematic has blocks with several properties");
+    for key in palette.inner().keys() {
+        let parsed = BlockState::parse(key).unwrap();
+        assert_eq!(&parsed.to_string(), key, "Properties are written sorted by key");
+    }
+
+    // Litematic palette entries are split back into a name and properties
+    let mut litematic_data = Vec::new();
+    schematic_to_litematic(Cursor::new(&schematic_data), &mut litematic_data).unwrap();
+    let mut roundtrip_data = Vec::new();
+    litematic_to_schematic(Cursor::new(litematic_data), &mut roundtrip_data).unwrap();
+    let (roundtrip, _) = quartz_nbt::io::read_nbt(&mut Cursor::new(&roundtrip_data), Flavor::Uncompressed).unwrap();
+    assert_eq!(roundtrip.get::<_, &NbtCompound>("Palette").unwrap(), palette);
+}
+
+#[test]
+fn test_malformed_palette_entries_are_rejected() {
+    let mut schematic = Schematic::new(1, 1, 1);
+    schematic.set_block(0, 0, 0, "minecraft:oak_stairs[facing=north");
+    let error = schematic.write(SchematicFormat::Litematic, &ConvertOptions::default()).unwrap_err();
+    assert!(error.to_string().contains("Palette entry 'minecraft:oak_stairs[facing=north' has an unclosed property list"), "{}", error);
+
+    schematic.palette[1] = "minecraft:oak_stairs[facing]".to_string();
+    assert!(schematic.write(SchematicFormat::Litematic, &ConvertOptions::default()).is_err());
+}
@@ -0,0 +1,266 @@
+use quartz_nbt::{NbtCompound, NbtList, NbtTag};
+use schematic_converter::analysis::validate;
+use schematic_converter::models::{BlockState, Schematic};
+use schematic_converter::{ConvertOptions, SchematicConverter, SchematicFormat};
+
+#[test]
+fn test_model_roundtrip_through_every_format() {
+    let big_quarry = include_bytes!("test_schematics/big_quarry.litematic");
+    let original = Schematic::read(big_quarry, SchematicFormat::Litematic).unwrap();
+    assert_eq!(original.blocks.len(), original.volume());
+
+    for format in [SchematicFormat::Litematic, SchematicFormat::Schematic, SchematicFormat::Schem] {
+        let written = original.write(format, &ConvertOptions::default()).unwrap();
+        let read_back = Schematic::read(&written, format).unwrap();
+
+        assert_eq!((read_back.width, read_back.height, read_back.length), (original.width, original.height, original.length));
+        for (x, y, z) in [(0, 0, 0), (original.width - 1, original.height - 1, original.length - 1), (original.width / 2, original.height / 2, original.length / 2)] {
+            assert_eq!(read_back.block_at(x, y, z), original.block_at(x, y, z), "{:?} block at {},{},{} should survive", format, x, y, z);
+        }
+    }
+}
+
+#[test]
+fn test_set_block_extends_palette() {
+    let mut schematic = Schematic::new(2, 2, 2);
+    schematic.set_block(1, 0, 1, "minecraft:stone");
+    schematic.set_block(0, 1, 0, "minecraft:stone");
+
+    assert_eq!(schematic.palette, vec!["minecraft:air", "minecraft:stone"]);
+    assert_eq!(schematic.block_at(1, 0, 1), "minecraft:stone");
+    assert_eq!(schematic.block_at(0, 0, 0), "minecraft:air");
+}
+
+#[test]
+fn test_block_state_formats_canonically() {
+    let parsed = BlockState::parse("oak_stairs[waterlogged=false,facing=east,half=top]").unwrap();
+    assert_eq!(parsed.name(), "minecraft:oak_stairs");
+    assert_eq!(parsed.property("facing"), Some("east"));
+    assert_eq!(parsed.to_string(), "minecraft:oak_stairs[facing=east,half=top,waterlogged=false]");
+    assert_eq!(parsed, "minecraft:oak_stairs[half=top,facing=east,waterlogged=false]".parse().unwrap());
+
+    for text in ["minecraft:stone", "minecraft:redstone_wire[east=side,north=none,power=15,south=up,west=none]", "create:fluid_pipe[axis=y]", "mod:path/to/block"] {
+        assert_eq!(BlockState::parse(text).unwrap().to_string(), text, "Canonical states round trip unchanged");
+    }
+    assert_eq!(BlockState::parse("stone[]").unwrap().to_string(), "minecraft:stone");
+
+    let mut block_state = BlockState::new("minecraft:lever").unwrap();
+    block_state.set_property("powered", "true").unwrap();
+    block_state.set_property("face", "wall").unwrap();
+    block_state.set_property("powered", "false").unwrap();
+    assert_eq!(block_state.to_string(), "minecraft:lever[face=wall,powered=false]");
+}
+
+#[test]
+fn test_block_state_parser_is_strict() {
+    for (text, problem) in [
+        ("minecraft:oak_stairs[facing=north", "unclosed property list"),
+        ("minecraft:oak_stairs[facing=north]x", "unclosed property list"),
+        ("minecraft:oak_stairs[facing]", "malformed property 'facing'"),
+        ("minecraft:oak_stairs[facing=north,]", "malformed property ''"),
+        ("minecraft:oak_stairs[facing=north,facing=south]", "sets 'facing' more than once"),
+        ("Minecraft:Stone", "not a valid resource location"),
+        ("minecraft:", "not a valid resource location"),
+        ("minecraft:stone bricks", "not a valid resource location"),
+        ("minecraft:lever[face=Wall]", "'face=Wall' is not a valid property"),
+    ] {
+        let error = BlockState::parse(text).unwrap_err();
+        assert!(error.contains(problem), "{}: {}", text, error);
+    }
+}
+
+#[test]
+fn test_sponge_v3_entities_are_flattened() {
+    let mut data = NbtCompound::new();
+    data.insert("id", NbtTag::String("minecraft:painting".to_string()));
+    data.insert("TileX", NbtTag::Int(102));
+    data.insert("TileY", NbtTag::Int(64));
+    data.insert("TileZ", NbtTag::Int(-20));
+    data.insert("leash", NbtTag::IntArray(vec![103, 64, -20]));
+    let mut entity = NbtCompound::new();
+    entity.insert("Id", NbtTag::String("minecraft:painting".to_string()));
+    entity.insert("Pos", NbtTag::List(NbtList::from(vec![NbtTag::Double(2.5), NbtTag::Double(0.5), NbtTag::Double(0.03125)])));
+    entity.insert("Data", NbtTag::Compound(data));
+
+    let mut schematic = Schematic::new(4, 2, 2);
+    schematic.entities.push(entity);
+    let written = schematic.write(SchematicFormat::Schem, &Default::default()).unwrap();
+    let read = Schematic::read(&written, SchematicFormat::Schem).unwrap();
+
+    let entity = &read.entities[0];
+    assert!(!entity.contains_key("Data"));
+    assert!(!entity.contains_key("id"));
+    assert_eq!(entity.get::<_, &str>("Id").unwrap(), "minecraft:painting");
+    assert_eq!(["TileX", "TileY", "TileZ"].map(|key| entity.get::<_, i32>(key).unwrap()), [2, 0, 0], "World tile coordinates move into the schematic frame");
+    assert_eq!(entity.get::<_, &[i32]>("leash").unwrap(), &[3, 0, 0]);
+}
+
+fn tick(key: &str, block: &str, [x, y, z]: [i32; 3]) -> NbtCompound {
+    let mut tick = NbtCompound::new();
+    tick.insert(key, NbtTag::String(block.to_string()));
+    tick.insert("Priority", NbtTag::Int(0));
+    tick.insert("SubTick", NbtTag::Long(7));
+    tick.insert("Time", NbtTag::Int(2));
+    tick.insert("x", NbtTag::Int(x));
+    tick.insert("y", NbtTag::Int(y));
+    tick.insert("z", NbtTag::Int(z));
+    tick
+}
+
+#[test]
+fn test_pending_ticks_survive_litematic_and_are_reported_lost_in_sponge() {
+    let mut schematic = Schematic::new(3, 1, 1);
+    schematic.set_block(0, 0, 0, "minecraft:repeater[delay=2,facing=west,locked=false,powered=false]");
+    schematic.set_block(2, 0, 0, "minecraft:water[level=1]");
+    schematic.block_ticks.push(tick("Block", "minecraft:repeater", [0, 0, 0]));
+    schematic.fluid_ticks.push(tick("Fluid", "minecraft:water", [2, 0, 0]));
+
+    assert!(schematic.lost_in(SchematicFormat::Litematic).is_empty());
+    let litematic = schematic.write(SchematicFormat::Litematic, &Default::default()).unwrap();
+    let read = Schematic::read(&litematic, SchematicFormat::Litematic).unwrap();
+    assert_eq!(read.block_ticks, schematic.block_ticks);
+    assert_eq!(read.fluid_ticks, schematic.fluid_ticks);
+
+    assert_eq!(read.lost_in(SchematicFormat::Schem), vec!["1 pending block ticks", "1 pending fluid ticks"]);
+    let schem = SchematicConverter::new().convert(&litematic, SchematicFormat::Litematic, SchematicFormat::Schem).unwrap();
+    let read = Schematic::read(&schem, SchematicFormat::Schem).unwrap();
+    assert!(read.block_ticks.is_empty() && read.fluid_ticks.is_empty());
+}
+
+#[test]
+fn test_biomes_are_read_and_written_per_column() {
+    let mut schematic = Schematic::new(2, 3, 1);
+    schematic.set_biome(0, 0, 0, "minecraft:plains");
+    schematic.set_biome(1, 0, 0, "minecraft:swamp");
+    schematic.set_biome(1, 1, 0, "minecraft:swamp");
+    schematic.set_biome(1, 2, 0, "minecraft:deep_dark");
+    assert_eq!(schematic.biome_at(1, 2, 0), Some("minecraft:deep_dark"));
+    assert_eq!(schematic.lost_in(SchematicFormat::Schem), vec!["biomes that change along Y in 1 columns, which keep their most common biome"]);
+
+    let written = schematic.write(SchematicFormat::Schem, &Default::default()).unwrap();
+    let read = Schematic::read(&written, SchematicFormat::Schem).unwrap();
+    for y in 0..3 {
+        assert_eq!(read.biome_at(0, y, 0), Some("minecraft:plains"));
+        assert_eq!(read.biome_at(1, y, 0), Some("minecraft:swamp"), "Sponge v2 keeps the most common biome of each column");
+    }
+    assert!(read.lost_in(SchematicFormat::Schem).is_empty(), "Uniform columns lose nothing");
+
+    assert_eq!(read.lost_in(SchematicFormat::Litematic), vec!["biomes"]);
+    let litematic = read.write(SchematicFormat::Litematic, &Default::default()).unwrap();
+    assert!(Schematic::read(&litematic, SchematicFormat::Litematic).unwrap().biomes.is_empty());
+    assert!(Schematic::new(1, 1, 1).biome_at(0, 0, 0).is_none());
+}
+
+/// A Sponge version 3 schematic as WorldEdit writes it, with everything in a `Schematic` compound.
+fn sponge_v3_schematic() -> NbtCompound {
+    let mut block_palette = NbtCompound::new();
+    block_palette.insert("minecraft:air", NbtTag::Int(0));
+    block_palette.insert("minecraft:chest[facing=north,type=single,waterlogged=false]", NbtTag::Int(1));
+    let mut chest_data = NbtCompound::new();
+    chest_data.insert("Items", NbtTag::List(NbtList::new()));
+    chest_data.insert("CustomName", NbtTag::String("\"Loot\"".to_string()));
+    let mut chest = NbtCompound::new();
+    chest.insert("Id", NbtTag::String("minecraft:chest".to_string()));
+    chest.insert("Pos", NbtTag::IntArray(vec![0, 1, 0]));
+    chest.insert("Data", NbtTag::Compound(chest_data));
+    let mut blocks = NbtCompound::new();
+    blocks.insert("Palette", NbtTag::Compound(block_palette));
+    blocks.insert("Data", NbtTag::ByteArray(vec![0, 1]));
+    blocks.insert("BlockEntities", NbtTag::List(NbtList::from(vec![NbtTag::Compound(chest)])));
+
+    let mut biome_palette = NbtCompound::new();
+    biome_palette.insert("minecraft:desert", NbtTag::Int(3));
+    biome_palette.insert("minecraft:lush_caves", NbtTag::Int(5));
+    let mut biomes = NbtCompound::new();
+    biomes.insert("Palette", NbtTag::Compound(biome_palette));
+    biomes.insert("Data", NbtTag::ByteArray(vec![5, 3]));
+
+    let mut schematic = NbtCompound::new();
+    schematic.insert("Version", NbtTag::Int(3));
+    schematic.insert("DataVersion", NbtTag::Int(3953));
+    schematic.insert("Width", NbtTag::Short(1));
+    schematic.insert("Height", NbtTag::Short(2));
+    schematic.insert("Length", NbtTag::Short(1));
+    schematic.insert("Offset", NbtTag::IntArray(vec![-1, 0, 2]));
+    schematic.insert("Blocks", NbtTag::Compound(blocks));
+    schematic.insert("Biomes", NbtTag::Compound(biomes));
+    let mut root = NbtCompound::new();
+    root.insert("Schematic", NbtTag::Compound(schematic));
+    root
+}
+
+#[test]
+fn test_sponge_v3_schematics_are_read() {
+    let mut data = Vec::new();
+    quartz_nbt::io::write_nbt(&mut data, None, &sponge_v3_schematic(), quartz_nbt::io::Flavor::GzCompressed).unwrap();
+
+    let read = Schematic::read(&data, SchematicFormat::Schem).unwrap();
+    assert_eq!((read.width, read.height, read.length), (1, 2, 1));
+    assert_eq!(read.data_version, Some(3953));
+    assert_eq!(read.origin, [1, 0, -2]);
+    assert_eq!(read.block_at(0, 1, 0), "minecraft:chest[facing=north,type=single,waterlogged=false]");
+    assert_eq!(read.biome_at(0, 0, 0), Some("minecraft:lush_caves"));
+    assert_eq!(read.biome_at(0, 1, 0), Some("minecraft:desert"));
+    assert_eq!(read.lost_in(SchematicFormat::Schem).len(), 1, "Writing version 2 collapses the column's biomes");
+    let chest = &read.block_entities[0];
+    assert_eq!(chest.get::<_, &[i32]>("Pos").unwrap(), &[0, 1, 0]);
+    assert!(chest.contains_key("Items") && !chest.contains_key("Data"), "The block entity data is flattened");
+
+    assert!(validate(&data, SchematicFormat::Schem).is_valid());
+    let litematic = SchematicConverter::new().convert(&data, SchematicFormat::Schem, SchematicFormat::Litematic).unwrap();
+    let converted = Schematic::read(&litematic, SchematicFormat::Litematic).unwrap();
+    assert_eq!(converted.block_at(0, 1, 0), read.block_at(0, 1, 0));
+    assert_eq!(converted.block_entities.len(), 1);
+}
+
+#[test]
+fn test_paste_origin_maps_to_sponge_offsets_and_litematic_position() {
+    let mut schematic = Schematic::new(3, 2, 2);
+    schematic.origin = [2, 0, -1];
+
+    let nbt = schematic.to_sponge_nbt().unwrap();
+    assert_eq!(nbt.get::<_, &[i32]>("Offset").unwrap(), &[-2, 0, 1]);
+    let metadata = nbt.get::<_, &NbtCompound>("Metadata").unwrap();
+    assert_eq!(["WEOffsetX", "WEOffsetY", "WEOffsetZ"].map(|key| metadata.get::<_, i32>(key).unwrap()), [-2, 0, 1]);
+
+    let schem = schematic.write(SchematicFormat::Schem, &Default::default()).unwrap();
+    let read = Schematic::read(&schem, SchematicFormat::Schem).unwrap();
+    assert_eq!(read.origin, [2, 0, -1]);
+    assert!(!read.metadata.contains_key("WEOffsetX"), "The WorldEdit offset lives in origin");
+
+    let litematic = schematic.write(SchematicFormat::Litematic, &Default::default()).unwrap();
+    let mut decompressed = Vec::new();
+    std::io::copy(&mut flate2::read::GzDecoder::new(litematic.as_slice()), &mut decompressed).unwrap();
+    let (nbt, _) = quartz_nbt::io::read_nbt(&mut std::io::Cursor::new(decompressed), quartz_nbt::io::Flavor::Uncompressed).unwrap();
+    let region = nbt.get::<_, &NbtCompound>("Regions").unwrap().get::<_, &NbtCompound>("main").unwrap();
+    let position = region.get::<_, &NbtCompound>("Position").unwrap();
+    assert_eq!(["x", "y", "z"].map(|key| position.get::<_, i32>(key).unwrap()), [-2, 0, 1]);
+    assert_eq!(Schematic::read(&litematic, SchematicFormat::Litematic).unwrap().origin, [2, 0, -1]);
+}
+
+#[test]
+fn test_oversized_dimensions_are_not_truncated() {
+    let schematic = Schematic::new(70000, 1, 1);
+    let error = schematic.to_sponge_nbt().unwrap_err();
+    assert!(error.to_string().contains("Width 70000"), "{}", error);
+    assert!(schematic.write(SchematicFormat::Schem, &Default::default()).is_err());
+
+    let nbt = Schematic::new(65535, 1, 1).to_sponge_nbt().unwrap();
+    assert_eq!(nbt.get::<_, i16>("Width").unwrap() as u16, 65535);
+}
+
+#[test]
+fn test_worldedit_offset_wins_over_world_position() {
+    let mut nbt = Schematic::new(1, 1, 1).to_sponge_nbt().unwrap();
+    nbt.insert("Offset", NbtTag::IntArray(vec![1000, 64, -300]));
+    let mut metadata = NbtCompound::new();
+    metadata.insert("WEOffsetX", NbtTag::Int(-2));
+    metadata.insert("WEOffsetY", NbtTag::Int(0));
+    metadata.insert("WEOffsetZ", NbtTag::Int(-3));
+    nbt.insert("Metadata", NbtTag::Compound(metadata));
+    assert_eq!(schematic_converter::parsers::parse_sponge_nbt(&nbt).unwrap().origin, [2, 0, 3]);
+
+    // A region with a negative size has its position at the far corner
+    let litematic = include_bytes!("test_schematics/test_coordinate_thingy.litematic");
+    assert_eq!(Schematic::read(litematic, SchematicFormat::Litematic).unwrap().origin, [0, 0, 0]);
+}
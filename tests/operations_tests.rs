@@ -0,0 +1,492 @@
+use quartz_nbt::{NbtCompound, NbtList, NbtTag};
+use schematic_converter::models::Schematic;
+use schematic_converter::analysis::BoundingBox;
+use schematic_converter::operations::{crop, merge, mirror, replace_blocks, resize, rotate, trim, Axis, ConflictPolicy, Patch, ReplaceOptions, ReplaceRule, Rotation};
+use schematic_converter::{SchematicConverter, SchematicFormat, SchematicMerger};
+
+fn block_entity_at(x: i32, y: i32, z: i32) -> NbtCompound {
+    let mut block_entity = NbtCompound::new();
+    block_entity.insert("Id", NbtTag::String("minecraft:chest".to_string()));
+    block_entity.insert("Pos", NbtTag::IntArray(vec![x, y, z]));
+    block_entity
+}
+
+fn entity_at(x: f64, y: f64, z: f64, yaw: f32) -> NbtCompound {
+    let mut entity = NbtCompound::new();
+    entity.insert("Id", NbtTag::String("minecraft:armor_stand".to_string()));
+    entity.insert("Pos", NbtTag::List(NbtList::from(vec![NbtTag::Double(x), NbtTag::Double(y), NbtTag::Double(z)])));
+    entity.insert("Rotation", NbtTag::List(NbtList::from(vec![NbtTag::Float(yaw), NbtTag::Float(0.0)])));
+    entity
+}
+
+fn entity_position(entity: &NbtCompound) -> Vec<f64> {
+    entity.get::<_, &NbtList>("Pos").unwrap().iter()
+        .map(|tag| match tag {
+            NbtTag::Double(value) => *value,
+            _ => panic!("Pos should hold doubles"),
+        })
+        .collect()
+}
+
+#[test]
+fn test_rotate_moves_blocks_and_rewrites_states() {
+    let mut schematic = Schematic::new(3, 2, 2);
+    schematic.set_block(0, 0, 0, "minecraft:oak_stairs[facing=north,half=bottom,shape=inner_left,waterlogged=false]");
+    schematic.set_block(1, 0, 0, "minecraft:oak_log[axis=x]");
+    schematic.set_block(2, 0, 0, "minecraft:oak_sign[rotation=0,waterlogged=false]");
+    schematic.set_block(0, 0, 1, "minecraft:rail[shape=north_east,waterlogged=false]");
+    schematic.set_block(1, 0, 1, "minecraft:powered_rail[powered=false,shape=ascending_north,waterlogged=false]");
+    schematic.set_block(2, 0, 1, "minecraft:oak_fence[east=false,north=true,south=false,waterlogged=false,west=false]");
+    schematic.set_block(0, 1, 0, "minecraft:redstone_wire[east=none,north=side,power=0,south=up,west=none]");
+    schematic.block_entities.push(block_entity_at(2, 0, 0));
+    schematic.entities.push(entity_at(0.5, 0.0, 0.5, 0.0));
+
+    let rotated = rotate(&schematic, Axis::Y, Rotation::Clockwise90);
+    assert_eq!((rotated.width, rotated.height, rotated.length), (2, 2, 3));
+
+    // Clockwise from above, the north west corner ends up in the north east corner
+    assert_eq!(rotated.block_at(1, 0, 0), "minecraft:oak_stairs[facing=east,half=bottom,shape=inner_left,waterlogged=false]");
+    assert_eq!(rotated.block_at(1, 0, 1), "minecraft:oak_log[axis=z]");
+    assert_eq!(rotated.block_at(1, 0, 2), "minecraft:oak_sign[rotation=4,waterlogged=false]");
+    assert_eq!(rotated.block_at(0, 0, 0), "minecraft:rail[shape=south_east,waterlogged=false]");
+    assert_eq!(rotated.block_at(0, 0, 1), "minecraft:powered_rail[powered=false,shape=ascending_east,waterlogged=false]");
+    assert_eq!(rotated.block_at(0, 0, 2), "minecraft:oak_fence[east=true,north=false,south=false,waterlogged=false,west=false]");
+    assert_eq!(rotated.block_at(1, 1, 0), "minecraft:redstone_wire[east=side,north=none,power=0,south=none,west=up]");
+
+    assert_eq!(rotated.block_entities[0].get::<_, &[i32]>("Pos").unwrap(), &[1, 0, 2]);
+    assert_eq!(entity_position(&rotated.entities[0]), vec![1.5, 0.0, 0.5]);
+    match rotated.entities[0].get::<_, &NbtList>("Rotation").unwrap().iter().next() {
+        Some(NbtTag::Float(yaw)) => assert_eq!(*yaw, 90.0, "Facing south turns to facing west"),
+        other => panic!("Unexpected rotation {:?}", other),
+    }
+}
+
+#[test]
+fn test_four_quarter_turns_restore_the_schematic() {
+    let big_quarry = include_bytes!("test_schematics/big_quarry.litematic");
+    let original = Schematic::read(big_quarry, SchematicFormat::Litematic).unwrap();
+
+    for axis in [Axis::X, Axis::Y, Axis::Z] {
+        let restored = (0..4).fold(original.clone(), |schematic, _| rotate(&schematic, axis, Rotation::Clockwise90));
+        assert_eq!(restored, original, "Rotating around {:?} four times should change nothing", axis);
+    }
+    let half_turn = rotate(&original, Axis::Y, Rotation::Clockwise180);
+    assert_eq!(rotate(&half_turn, Axis::Y, Rotation::Clockwise180), original);
+    assert_eq!(rotate(&original, Axis::Y, Rotation::None), original);
+}
+
+#[test]
+fn test_rotate_around_horizontal_axes() {
+    let mut schematic = Schematic::new(1, 2, 1);
+    schematic.set_block(0, 0, 0, "minecraft:oak_log[axis=y]");
+    schematic.set_block(0, 1, 0, "minecraft:stone_slab[type=bottom,waterlogged=false]");
+
+    let tipped = rotate(&schematic, Axis::X, Rotation::Clockwise90);
+    assert_eq!((tipped.width, tipped.height, tipped.length), (1, 1, 2));
+    assert_eq!(tipped.block_at(0, 0, 1), "minecraft:oak_log[axis=z]");
+
+    let upside_down = rotate(&schematic, Axis::Z, Rotation::Clockwise180);
+    assert_eq!(upside_down.block_at(0, 1, 0), "minecraft:oak_log[axis=y]");
+    assert_eq!(upside_down.block_at(0, 0, 0), "minecraft:stone_slab[type=top,waterlogged=false]");
+}
+
+#[test]
+fn test_rotate_from_converter() {
+    let sample_litematic = include_bytes!("test_schematics/sample.litematic");
+    let converter = SchematicConverter::new();
+    let rotated = converter.rotate(sample_litematic, SchematicFormat::Litematic, Axis::Y, -90).unwrap();
+    let rotated = Schematic::read(&rotated, SchematicFormat::Litematic).unwrap();
+    let original = Schematic::read(sample_litematic, SchematicFormat::Litematic).unwrap();
+
+    assert_eq!((rotated.width, rotated.length), (original.length, original.width));
+    assert!(converter.rotate(sample_litematic, SchematicFormat::Litematic, Axis::Y, 45).is_err());
+    assert_eq!(Rotation::from_degrees(270), Some(Rotation::CounterClockwise90));
+}
+
+#[test]
+fn test_mirror_flips_positions_and_handedness() {
+    let mut schematic = Schematic::new(3, 1, 2);
+    schematic.set_block(0, 0, 0, "minecraft:oak_stairs[facing=east,half=bottom,shape=inner_left,waterlogged=false]");
+    schematic.set_block(1, 0, 0, "minecraft:oak_door[facing=north,half=lower,hinge=left,open=false,powered=false]");
+    schematic.set_block(2, 0, 0, "minecraft:chest[facing=north,type=left,waterlogged=false]");
+    schematic.set_block(0, 0, 1, "minecraft:white_banner[rotation=3]");
+    schematic.set_block(1, 0, 1, "minecraft:rail[shape=south_west,waterlogged=false]");
+    schematic.block_entities.push(block_entity_at(0, 0, 1));
+    schematic.entities.push(entity_at(0.25, 0.0, 1.5, 45.0));
+
+    let mirrored = mirror(&schematic, Axis::X);
+    assert_eq!((mirrored.width, mirrored.height, mirrored.length), (3, 1, 2));
+    assert_eq!(mirrored.block_at(2, 0, 0), "minecraft:oak_stairs[facing=west,half=bottom,shape=inner_right,waterlogged=false]");
+    assert_eq!(mirrored.block_at(1, 0, 0), "minecraft:oak_door[facing=north,half=lower,hinge=right,open=false,powered=false]");
+    assert_eq!(mirrored.block_at(0, 0, 0), "minecraft:chest[facing=north,type=right,waterlogged=false]");
+    assert_eq!(mirrored.block_at(2, 0, 1), "minecraft:white_banner[rotation=13]");
+    assert_eq!(mirrored.block_at(1, 0, 1), "minecraft:rail[shape=south_east,waterlogged=false]");
+
+    assert_eq!(mirrored.block_entities[0].get::<_, &[i32]>("Pos").unwrap(), &[2, 0, 1]);
+    assert_eq!(entity_position(&mirrored.entities[0]), vec![2.75, 0.0, 1.5]);
+    match mirrored.entities[0].get::<_, &NbtList>("Rotation").unwrap().iter().next() {
+        Some(NbtTag::Float(yaw)) => assert!((*yaw - 315.0).abs() < 1e-3, "South west turns to south east, got {}", yaw),
+        other => panic!("Unexpected rotation {:?}", other),
+    }
+
+    let along_z = mirror(&schematic, Axis::Z);
+    assert_eq!(along_z.block_at(1, 0, 1), "minecraft:oak_door[facing=south,half=lower,hinge=right,open=false,powered=false]");
+    assert_eq!(along_z.block_at(0, 0, 0), "minecraft:white_banner[rotation=5]");
+    assert_eq!(mirror(&along_z, Axis::Z), schematic, "Mirroring twice restores the schematic");
+}
+
+#[test]
+fn test_mirror_across_y_keeps_handedness() {
+    let mut schematic = Schematic::new(2, 2, 2);
+    schematic.set_block(0, 0, 0, "minecraft:chest[facing=north,type=left,waterlogged=false]");
+    schematic.set_block(1, 0, 0, "minecraft:chest[facing=north,type=right,waterlogged=false]");
+    schematic.set_block(0, 0, 1, "minecraft:oak_door[facing=north,half=lower,hinge=left,open=false,powered=false]");
+    schematic.set_block(0, 1, 1, "minecraft:oak_door[facing=north,half=upper,hinge=left,open=false,powered=false]");
+    schematic.set_block(1, 0, 1, "minecraft:oak_stairs[facing=east,half=bottom,shape=inner_left,waterlogged=false]");
+
+    let flipped = mirror(&schematic, Axis::Y);
+    assert_eq!(flipped.block_at(0, 1, 0), "minecraft:chest[facing=north,type=left,waterlogged=false]");
+    assert_eq!(flipped.block_at(1, 1, 0), "minecraft:chest[facing=north,type=right,waterlogged=false]");
+    assert_eq!(flipped.block_at(0, 1, 1), "minecraft:oak_door[facing=north,half=upper,hinge=left,open=false,powered=false]");
+    assert_eq!(flipped.block_at(0, 0, 1), "minecraft:oak_door[facing=north,half=lower,hinge=left,open=false,powered=false]");
+    assert_eq!(flipped.block_at(1, 1, 1), "minecraft:oak_stairs[facing=east,half=top,shape=inner_left,waterlogged=false]");
+
+    let both = mirror(&flipped, Axis::X);
+    assert_eq!(both.block_at(1, 1, 0), "minecraft:chest[facing=north,type=right,waterlogged=false]");
+    assert_eq!(both.block_at(1, 1, 1), "minecraft:oak_door[facing=north,half=upper,hinge=right,open=false,powered=false]");
+}
+
+#[test]
+fn test_trim_shrinks_to_the_build_and_reports_dropped_entities() {
+    let mut schematic = Schematic::new(5, 4, 5);
+    schematic.set_block(1, 1, 2, "minecraft:stone");
+    schematic.set_block(3, 2, 3, "minecraft:chest[facing=north,type=single,waterlogged=false]");
+    schematic.block_entities.push(block_entity_at(3, 2, 3));
+    schematic.entities.push(entity_at(2.5, 1.0, 2.5, 0.0));
+    schematic.entities.push(entity_at(0.5, 3.0, 0.5, 0.0));
+
+    let (trimmed, report) = trim(&schematic, None).unwrap();
+    assert_eq!((trimmed.width, trimmed.height, trimmed.length), (3, 2, 2));
+    assert_eq!(report.offset, [-1, -1, -2]);
+    assert_eq!(trimmed.block_at(0, 0, 0), "minecraft:stone");
+    assert_eq!(trimmed.block_at(2, 1, 1), "minecraft:chest[facing=north,type=single,waterlogged=false]");
+    assert_eq!(trimmed.block_entities[0].get::<_, &[i32]>("Pos").unwrap(), &[2, 1, 1]);
+    assert_eq!(trimmed.entities.len(), 1);
+    assert_eq!(entity_position(&trimmed.entities[0]), vec![1.5, 0.0, 0.5]);
+    assert_eq!(report.dropped_entities.len(), 1, "The entity above the build is dropped");
+    assert!(report.dropped_block_entities.is_empty());
+
+    assert!(trim(&Schematic::new(2, 2, 2), None).is_err(), "An empty schematic cannot be trimmed");
+}
+
+#[test]
+fn test_trim_with_filler_and_crop_bounds() {
+    let mut schematic = Schematic::new(4, 1, 1);
+    for x in 0..4 {
+        schematic.set_block(x, 0, 0, "minecraft:stone");
+    }
+    schematic.set_block(2, 0, 0, "minecraft:gold_block");
+
+    let (trimmed, _) = trim(&schematic, Some("stone")).unwrap();
+    assert_eq!((trimmed.width, trimmed.height, trimmed.length), (1, 1, 1));
+    assert_eq!(trimmed.palette, vec!["minecraft:gold_block"], "Unused palette entries are removed");
+
+    let (cropped, report) = crop(&schematic, [1, 0, 0], [2, 1, 1]).unwrap();
+    assert_eq!(cropped.block_at(1, 0, 0), "minecraft:gold_block");
+    assert_eq!(report.offset, [-1, 0, 0]);
+    assert!(crop(&schematic, [3, 0, 0], [2, 1, 1]).is_err(), "The crop box must fit");
+}
+
+#[test]
+fn test_resize_pads_with_air() {
+    let mut schematic = Schematic::new(2, 1, 1);
+    schematic.set_block(0, 0, 0, "minecraft:stone");
+    schematic.set_block(1, 0, 0, "minecraft:dirt");
+    schematic.block_entities.push(block_entity_at(1, 0, 0));
+
+    let (resized, report) = resize(&schematic, [2, 1, 0], [3, 2, 2]);
+    assert_eq!((resized.width, resized.height, resized.length), (3, 2, 2));
+    assert_eq!(resized.block_at(2, 1, 0), "minecraft:stone");
+    assert_eq!(resized.block_at(0, 0, 0), "minecraft:air");
+    assert_eq!(resized.block_at(2, 1, 1), "minecraft:air");
+    assert_eq!(report.dropped_block_entities.len(), 1, "The block entity moved out of bounds");
+    assert!(resized.block_entities.is_empty());
+}
+
+#[test]
+fn test_merge_places_schematics_at_offsets() {
+    let mut first = Schematic::new(2, 1, 1);
+    first.set_block(0, 0, 0, "minecraft:stone");
+    first.set_block(1, 0, 0, "minecraft:chest[facing=north,type=single,waterlogged=false]");
+    first.block_entities.push(block_entity_at(1, 0, 0));
+    first.entities.push(entity_at(0.5, 0.0, 0.5, 0.0));
+
+    let mut second = Schematic::new(2, 1, 1);
+    second.set_block(0, 0, 0, "minecraft:dirt");
+
+    let merged = merge(&[(&first, [0, 0, 0]), (&second, [1, 0, -1])], ConflictPolicy::NonAirWins).unwrap();
+    assert_eq!((merged.width, merged.height, merged.length), (3, 1, 2));
+    assert_eq!(merged.block_at(0, 0, 1), "minecraft:stone");
+    assert_eq!(merged.block_at(1, 0, 0), "minecraft:dirt");
+    assert_eq!(merged.block_at(1, 0, 1), "minecraft:chest[facing=north,type=single,waterlogged=false]");
+    assert_eq!(merged.block_at(2, 0, 0), "minecraft:air");
+    assert_eq!(merged.block_entities[0].get::<_, &[i32]>("Pos").unwrap(), &[1, 0, 1]);
+    assert_eq!(entity_position(&merged.entities[0]), vec![0.5, 0.0, 1.5]);
+}
+
+#[test]
+fn test_merge_conflict_policies() {
+    let mut first = Schematic::new(2, 1, 1);
+    first.set_block(0, 0, 0, "minecraft:stone");
+    first.set_block(1, 0, 0, "minecraft:chest[facing=north,type=single,waterlogged=false]");
+    first.block_entities.push(block_entity_at(1, 0, 0));
+    let mut second = Schematic::new(2, 1, 1);
+    second.set_block(0, 0, 0, "minecraft:dirt");
+    let placements = [(&first, [0, 0, 0]), (&second, [0, 0, 0])];
+
+    let first_wins = merge(&placements, ConflictPolicy::FirstWins).unwrap();
+    assert_eq!(first_wins.block_at(0, 0, 0), "minecraft:stone");
+    assert_eq!(first_wins.block_entities.len(), 1);
+
+    let non_air_wins = merge(&placements, ConflictPolicy::NonAirWins).unwrap();
+    assert_eq!(non_air_wins.block_at(0, 0, 0), "minecraft:dirt");
+    assert_eq!(non_air_wins.block_at(1, 0, 0), "minecraft:chest[facing=north,type=single,waterlogged=false]");
+    assert_eq!(non_air_wins.block_entities.len(), 1);
+
+    let last_wins = merge(&placements, ConflictPolicy::LastWins).unwrap();
+    assert_eq!(last_wins.block_at(1, 0, 0), "minecraft:air");
+    assert!(last_wins.block_entities.is_empty(), "The chest's block entity goes with the chest");
+    assert_eq!(last_wins.palette, vec!["minecraft:air", "minecraft:dirt"]);
+
+    assert!(merge(&placements, ConflictPolicy::Error).is_err());
+    assert!(merge(&[(&first, [0, 0, 0]), (&second, [2, 0, 0])], ConflictPolicy::Error).is_ok());
+}
+
+#[test]
+fn test_merger_reads_any_format() {
+    let sample_litematic = include_bytes!("test_schematics/sample.litematic");
+    let sample_schem = include_bytes!("test_schematics/sample.schem");
+    let mut merger = SchematicMerger::new(ConflictPolicy::NonAirWins);
+    merger.add(sample_litematic, SchematicFormat::Litematic, [0, 0, 0]).unwrap();
+    merger.add(sample_schem, SchematicFormat::Schem, [100, 0, 0]).unwrap();
+
+    let merged = Schematic::read(&merger.finish(SchematicFormat::Schem).unwrap(), SchematicFormat::Schem).unwrap();
+    let schem = Schematic::read(sample_schem, SchematicFormat::Schem).unwrap();
+    assert_eq!(merged.width, 100 + schem.width);
+    assert_eq!(merged.block_at(100, 0, 0), schem.block_at(0, 0, 0));
+}
+
+#[test]
+fn test_patch_replays_a_revision() {
+    let mut old = Schematic::new(3, 2, 3);
+    old.set_block(0, 0, 0, "minecraft:stone");
+    old.set_block(1, 0, 1, "minecraft:chest[facing=north,type=single,waterlogged=false]");
+    old.block_entities.push(block_entity_at(1, 0, 1));
+    old.entities.push(entity_at(0.5, 1.0, 0.5, 0.0));
+    old.metadata.insert("Name", NbtTag::String("House".to_string()));
+    old.metadata.insert("Author", NbtTag::String("Nano_".to_string()));
+
+    // The revision grows by one block towards negative x and replaces the chest
+    let (mut new, _) = resize(&old, [1, 0, 0], [4, 2, 3]);
+    new.set_block(0, 0, 0, "minecraft:oak_planks");
+    new.set_block(2, 0, 1, "minecraft:barrel[facing=up,open=false]");
+    new.block_entities[0].insert("Id", NbtTag::String("minecraft:barrel".to_string()));
+    new.entities.push(entity_at(3.5, 0.0, 2.5, 90.0));
+    new.metadata.insert("Name", NbtTag::String("House v2".to_string()));
+    new.metadata.inner_mut().remove("Author");
+    new.data_version = Some(3700);
+
+    let patch = Patch::create(&old, &new, [-1, 0, 0]);
+    assert_eq!(patch.blocks.len(), 2);
+    let patch = Patch::from_bytes(&patch.to_bytes().unwrap()).unwrap();
+    let patched = patch.apply(&old).unwrap();
+
+    assert_eq!((patched.width, patched.height, patched.length), (4, 2, 3));
+    for y in 0..2 {
+        for z in 0..3 {
+            for x in 0..4 {
+                assert_eq!(patched.block_at(x, y, z), new.block_at(x, y, z), "Block at {},{},{}", x, y, z);
+            }
+        }
+    }
+    assert_eq!(patched.block_entities, new.block_entities);
+    assert_eq!(patched.entities, new.entities);
+    assert_eq!(patched.metadata, new.metadata);
+    assert_eq!(patched.data_version, Some(3700));
+
+    assert!(patch.apply(&new).is_err(), "A patch only applies to the revision it was made from");
+    assert!(Patch::create(&old, &old, [0, 0, 0]).is_empty());
+}
+
+#[test]
+fn test_patch_with_overflowing_indices_is_rejected() {
+    let old = Schematic::new(2, 1, 1);
+    let mut new = old.clone();
+    new.set_block(0, 0, 0, "minecraft:stone");
+    new.set_block(1, 0, 0, "minecraft:stone");
+    let mut nbt = Patch::create(&old, &new, [0, 0, 0]).to_nbt();
+
+    // Two index deltas that add up past u32::MAX, as varints
+    nbt.insert("Indices", NbtTag::ByteArray(vec![-1, -1, -1, -1, 0x0f, 0x05]));
+    let error = Patch::from_nbt(&nbt).unwrap_err();
+    assert!(error.to_string().contains("Indices"), "{}", error);
+}
+
+#[test]
+fn test_patch_through_converter() {
+    let converter = SchematicConverter::new();
+    let big_quarry = include_bytes!("test_schematics/big_quarry.litematic");
+    let old = Schematic::read(big_quarry, SchematicFormat::Litematic).unwrap();
+    let mut new = old.clone();
+    new.set_block(0, 0, 0, "minecraft:diamond_block");
+    let new_data = new.write(SchematicFormat::Schem, &Default::default()).unwrap();
+    let old_data = old.write(SchematicFormat::Schem, &Default::default()).unwrap();
+
+    let patch = converter.create_patch(&old_data, SchematicFormat::Schem, &new_data, SchematicFormat::Schem, false).unwrap();
+    assert!(patch.len() < new_data.len(), "A one block patch is smaller than the schematic");
+    let patched = converter.apply_patch(&old_data, SchematicFormat::Schem, &patch).unwrap();
+    let patched = Schematic::read(&patched, SchematicFormat::Schem).unwrap();
+    assert_eq!(patched.block_at(0, 0, 0), "minecraft:diamond_block");
+    assert_eq!(patched.blocks.len(), old.blocks.len());
+}
+
+#[test]
+fn test_replace_rules_on_palette() {
+    let mut schematic = Schematic::new(4, 1, 1);
+    schematic.set_block(0, 0, 0, "minecraft:oak_stairs[facing=east,half=top]");
+    schematic.set_block(1, 0, 0, "minecraft:oak_planks");
+    schematic.set_block(2, 0, 0, "minecraft:chest[facing=west,type=single,waterlogged=false]");
+    schematic.set_block(3, 0, 0, "minecraft:stone");
+    schematic.block_entities.push(block_entity_at(2, 0, 0));
+
+    let rules = [
+        ReplaceRule::new("oak_*", "spruce_*"),
+        ReplaceRule::new("chest", "barrel[open=false,facing]"),
+        ReplaceRule::parse("minecraft:stone -> cobblestone").unwrap(),
+    ];
+    let (replaced, count) = replace_blocks(&schematic, &rules, &ReplaceOptions::default());
+    assert_eq!(count, 4);
+    assert_eq!(replaced.block_at(0, 0, 0), "minecraft:spruce_stairs[facing=east,half=top]");
+    assert_eq!(replaced.block_at(1, 0, 0), "minecraft:spruce_planks");
+    assert_eq!(replaced.block_at(2, 0, 0), "minecraft:barrel[facing=west,open=false]");
+    assert_eq!(replaced.block_at(3, 0, 0), "minecraft:cobblestone");
+    assert_eq!(replaced.block_entities[0].get::<_, &str>("Id").unwrap(), "minecraft:barrel", "The chest contents move to the barrel");
+    assert!(!replaced.palette.iter().any(|block| block.contains("oak")), "Unused palette entries are dropped");
+
+    let (unchanged, count) = replace_blocks(&schematic, &[ReplaceRule::new("*_stairs[half=bottom]", "stone")], &ReplaceOptions::default());
+    assert_eq!(count, 0, "Property filters must match");
+    assert_eq!(unchanged.block_at(0, 0, 0), schematic.block_at(0, 0, 0));
+}
+
+#[test]
+fn test_replace_block_entities_and_implicit_properties() {
+    let mut schematic = Schematic::new(4, 1, 1);
+    for x in 0..3 {
+        schematic.set_block(x, 0, 0, "minecraft:chest[facing=north,type=single,waterlogged=false]");
+        schematic.block_entities.push(block_entity_at(x as i32, 0, 0));
+    }
+    schematic.set_block(3, 0, 0, "minecraft:oak_stairs[facing=east,half=top,shape=straight,waterlogged=false]");
+    let mut items = NbtCompound::new();
+    items.insert("Id", NbtTag::String("minecraft:chest".to_string()));
+    items.insert("Pos", NbtTag::IntArray(vec![2, 0, 0]));
+    items.insert("Items", NbtTag::List(NbtList::new()));
+    schematic.block_entities[2] = items;
+
+    let mask = |x: usize| vec![BoundingBox { min: [x, 0, 0], max: [x, 0, 0] }];
+    let (stone, _) = replace_blocks(&schematic, &[ReplaceRule::new("chest", "stone")], &ReplaceOptions { mask: mask(0), ..Default::default() });
+    assert_eq!(stone.block_at(0, 0, 0), "minecraft:stone", "Another block does not take the chest's properties");
+    assert_eq!(stone.block_entities.len(), 2, "Stone cannot hold the chest's block entity");
+    assert!(stone.block_entities.iter().all(|block_entity| block_entity.get::<_, &[i32]>("Pos").unwrap() != [0, 0, 0]));
+
+    let (trapped, _) = replace_blocks(&schematic, &[ReplaceRule::new("chest", "trapped_chest[*]")], &ReplaceOptions { mask: mask(2), ..Default::default() });
+    assert_eq!(trapped.block_at(2, 0, 0), "minecraft:trapped_chest[facing=north,type=single,waterlogged=false]");
+    assert_eq!(trapped.block_entities[2].get::<_, &str>("Id").unwrap(), "minecraft:trapped_chest");
+    assert!(trapped.block_entities[2].contains_key("Items"), "The block entity keeps its contents");
+
+    let (same_block, _) = replace_blocks(&schematic, &[ReplaceRule::new("chest[facing=north]", "chest[facing=south]"), ReplaceRule::new("*_stairs", "stone_stairs")], &ReplaceOptions::default());
+    assert_eq!(same_block.block_at(0, 0, 0), "minecraft:chest[facing=south]", "Brackets only keep what they list");
+    assert_eq!(same_block.block_entities.len(), 3);
+    assert_eq!(same_block.block_at(3, 0, 0), "minecraft:stone_stairs", "A `*` in the pattern alone keeps no properties");
+
+    let (same_id, _) = replace_blocks(&schematic, &[ReplaceRule::new("minecraft:oak_stairs", "oak_stairs"), ReplaceRule::new("chest", "minecraft:chest")], &ReplaceOptions::default());
+    assert_eq!(same_id.block_at(0, 0, 0), schematic.block_at(0, 0, 0), "Replacing a block with itself keeps its properties");
+    assert_eq!(same_id.block_at(3, 0, 0), schematic.block_at(3, 0, 0));
+}
+
+#[test]
+fn test_replace_with_probability_and_mask() {
+    let mut schematic = Schematic::new(10, 10, 10);
+    for index in 0..schematic.blocks.len() {
+        schematic.blocks[index] = schematic.palette_index("minecraft:stone");
+    }
+    let rules = [ReplaceRule::parse("stone -> cobblestone 0.3").unwrap()];
+    let options = ReplaceOptions { seed: 42, ..Default::default() };
+
+    let (first, count) = replace_blocks(&schematic, &rules, &options);
+    let (second, _) = replace_blocks(&schematic, &rules, &options);
+    assert_eq!(first, second, "The same seed gives the same result");
+    assert!((200..400).contains(&count), "About 30% of 1000 blocks, got {}", count);
+    let (other_seed, _) = replace_blocks(&schematic, &rules, &ReplaceOptions { seed: 7, ..Default::default() });
+    assert_ne!(first, other_seed);
+
+    let mask = vec![BoundingBox { min: [0, 0, 0], max: [1, 1, 1] }];
+    let (masked, count) = replace_blocks(&schematic, &[ReplaceRule::new("stone", "glass")], &ReplaceOptions { seed: 0, mask });
+    assert_eq!(count, 8);
+    assert_eq!(masked.block_at(1, 1, 1), "minecraft:glass");
+    assert_eq!(masked.block_at(2, 1, 1), "minecraft:stone");
+}
+
+#[test]
+fn test_pending_ticks_follow_their_blocks() {
+    let mut schematic = Schematic::new(3, 1, 2);
+    schematic.set_block(0, 0, 0, "minecraft:observer[facing=up,powered=false]");
+    let mut tick = NbtCompound::new();
+    tick.insert("Block", NbtTag::String("minecraft:observer".to_string()));
+    tick.insert("x", NbtTag::Int(0));
+    tick.insert("y", NbtTag::Int(0));
+    tick.insert("z", NbtTag::Int(0));
+    schematic.block_ticks.push(tick);
+    let position = |schematic: &Schematic| ["x", "y", "z"].map(|key| schematic.block_ticks[0].get::<_, i32>(key).unwrap());
+
+    let rotated = rotate(&schematic, Axis::Y, Rotation::Clockwise180);
+    assert!(rotated.block_at(2, 0, 1).starts_with("minecraft:observer"));
+    assert_eq!(position(&rotated), [2, 0, 1]);
+
+    let (cropped, report) = crop(&schematic, [1, 0, 0], [2, 1, 2]).unwrap();
+    assert!(cropped.block_ticks.is_empty());
+    assert_eq!(report.dropped_ticks.len(), 1);
+
+    let (kept, _) = crop(&rotated, [1, 0, 1], [2, 1, 1]).unwrap();
+    assert_eq!(position(&kept), [1, 0, 0]);
+}
+
+#[test]
+fn test_biomes_follow_operations() {
+    let mut schematic = Schematic::new(2, 1, 1);
+    schematic.set_biome(0, 0, 0, "minecraft:desert");
+    schematic.set_biome(1, 0, 0, "minecraft:jungle");
+
+    let mirrored = mirror(&schematic, Axis::X);
+    assert_eq!(mirrored.biome_at(0, 0, 0), Some("minecraft:jungle"));
+
+    let (grown, _) = resize(&schematic, [1, 0, 0], [4, 1, 1]);
+    let biomes: Vec<_> = (0..4).map(|x| grown.biome_at(x, 0, 0).unwrap()).collect();
+    assert_eq!(biomes, ["minecraft:desert", "minecraft:desert", "minecraft:jungle", "minecraft:jungle"], "New space takes the nearest biome");
+
+    let merged = merge(&[(&schematic, [0, 0, 0]), (&Schematic::new(1, 1, 1), [3, 0, 0])], ConflictPolicy::FirstWins).unwrap();
+    assert_eq!(merged.biome_at(1, 0, 0), Some("minecraft:jungle"));
+    assert_eq!(merged.biome_at(3, 0, 0), Some("minecraft:plains"));
+}
+
+#[test]
+fn test_paste_origin_follows_operations() {
+    let mut schematic = Schematic::new(3, 1, 2);
+    schematic.origin = [0, 0, -1];
+
+    assert_eq!(rotate(&schematic, Axis::Y, Rotation::Clockwise180).origin, [2, 0, 2]);
+    assert_eq!(mirror(&schematic, Axis::X).origin, [2, 0, -1]);
+    let (cropped, _) = crop(&schematic, [1, 0, 0], [2, 1, 2]).unwrap();
+    assert_eq!(cropped.origin, [-1, 0, -1]);
+    let merged = merge(&[(&schematic, [0, 0, 0]), (&Schematic::new(1, 1, 1), [-2, 0, 0])], ConflictPolicy::FirstWins).unwrap();
+    assert_eq!(merged.origin, [2, 0, -1]);
+}
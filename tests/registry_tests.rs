@@ -0,0 +1,137 @@
+#![cfg(feature = "registry")]
+use schematic_converter::registry::{bundled_versions, newest_at_or_before, parse_registries, BlockRegistry};
+
+const REGISTRY: &str = "
+@define FACING facing=north,south,west,east
+@version 2566
+{oak,spruce}_stairs - $FACING half=top,*bottom shape=straight,inner_left,inner_right,outer_left,outer_right waterlogged=true,*false
+chest e $FACING type=single,left,right waterlogged=true,*false
+grass -
+stone o
+cobblestone_wall - east=*none,low,tall up=true,false
+light - level=0..14,*15
+@version 3698
+-grass
+short_grass -
+cherry_planks o
+";
+
+#[test]
+fn test_registry_lookup_by_data_version() {
+    let registries = parse_registries(REGISTRY).unwrap();
+    assert_eq!(registries.iter().map(BlockRegistry::data_version).collect::<Vec<_>>(), vec![2566, 3698]);
+    assert_eq!(newest_at_or_before(&registries, 2600).unwrap().data_version(), 2566, "Patch releases use the release before them");
+    assert_eq!(newest_at_or_before(&registries, i32::MAX).unwrap().data_version(), 3698);
+    assert!(newest_at_or_before(&registries, 2565).is_none());
+
+    let (old, new) = (&registries[0], &registries[1]);
+    assert!(old.block("minecraft:grass").is_some() && old.block("short_grass").is_none());
+    assert!(new.block("short_grass").is_some() && new.block("minecraft:grass").is_none());
+    assert!(old.block("cherry_planks").is_none() && new.block("cherry_planks").is_some());
+    assert_eq!(old.len() + 1, new.len());
+    assert_eq!(old.block("spruce_stairs").unwrap().id, "minecraft:spruce_stairs");
+}
+
+#[test]
+fn test_block_properties_and_defaults() {
+    let registries = parse_registries(REGISTRY).unwrap();
+    let registry = &registries[1];
+    let stairs = registry.block("oak_stairs").unwrap();
+    assert_eq!(stairs.default_state(), "minecraft:oak_stairs[facing=north,half=bottom,shape=straight,waterlogged=false]");
+    assert_eq!(stairs.property("shape").unwrap().values.len(), 5);
+    assert_eq!(registry.block("stone").unwrap().default_state(), "minecraft:stone");
+    assert_eq!(registry.block("light").unwrap().property("level").unwrap().values.len(), 16);
+    assert_eq!(registry.block("light").unwrap().property("level").unwrap().default_value(), "15");
+    assert_eq!(registry.block("chest").unwrap().to_string(), "chest e facing=north,south,west,east type=single,left,right waterlogged=true,*false");
+
+    assert!(registry.block("chest").unwrap().has_block_entity);
+    assert!(!registry.block("stone").unwrap().has_block_entity);
+    assert!(registry.block("stone").unwrap().opaque);
+    assert!(!registry.block("chest").unwrap().opaque);
+
+    assert!(registry.check_state("minecraft:oak_stairs[facing=east,half=top]").is_ok());
+    assert!(registry.check_state("minecraft:oak_stairs[facing=up]").unwrap_err().contains("not a value of facing"));
+    assert!(registry.check_state("minecraft:stone[lit=true]").unwrap_err().contains("has no property 'lit'"));
+    assert!(registry.check_state("minecraft:cobblestone_wall[east=low,up=true]").is_ok());
+    assert!(registry.check_state("minecraft:not_a_block").is_err());
+}
+
+#[test]
+fn test_malformed_registry_data_is_rejected() {
+    for (text, problem) in [
+        ("stone o", "before the first @version"),
+        ("@version 2566\n@version 1519", "does not follow"),
+        ("@version 2566\n-stone", "never added"),
+        ("@version 2566\nstone x", "unknown flags"),
+        ("@version 2566\nstone - lit=true,true", "twice"),
+        ("@version 2566\n$WOOD_planks o", "unknown macro"),
+        ("@version 2566\n{oak,spruce_planks o", "unclosed group"),
+    ] {
+        let error = parse_registries(text).unwrap_err();
+        assert!(error.contains(problem), "{}: {}", text, error);
+    }
+}
+
+#[test]
+fn test_bundled_registry_is_consistent() {
+    let versions = bundled_versions();
+    assert!(versions.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(BlockRegistry::latest().map(BlockRegistry::data_version), versions.last().copied());
+    for data_version in versions {
+        assert_eq!(BlockRegistry::for_data_version(data_version).unwrap().data_version(), data_version);
+        assert!(BlockRegistry::for_data_version(data_version - 1).is_none_or(|registry| registry.data_version() < data_version));
+    }
+}
+
+#[test]
+#[ignore = "blocks.txt has no generated sections yet; run registry-gen for versions::RELEASES"]
+fn test_bundled_registry_has_vanilla_releases() {
+    for (release, data_version) in schematic_converter::versions::RELEASES {
+        let registry = BlockRegistry::for_data_version(data_version).unwrap_or_else(|| panic!("{} is not bundled", release));
+        assert_eq!(registry.data_version(), data_version, "{} has its own section", release);
+        assert!(registry.block("stone").unwrap().opaque);
+    }
+    let registry = BlockRegistry::for_data_version(3953).unwrap();
+    let chest = registry.block("minecraft:chest").unwrap();
+    assert!(chest.has_block_entity && !chest.opaque);
+    assert_eq!(chest.default_state(), "minecraft:chest[facing=north,type=single,waterlogged=false]");
+    assert!(registry.check_state("minecraft:rail[shape=north_south,waterlogged=true]").is_ok());
+    assert!(registry.check_state("minecraft:trial_spawner[ominous=true,trial_spawner_state=active]").is_ok());
+    assert!(registry.block("pale_oak_log").is_none());
+    assert!(BlockRegistry::for_data_version(4189).unwrap().block("pale_oak_log").is_some());
+}
+
+#[cfg(feature = "registry-gen")]
+#[test]
+fn test_registry_gen_writes_sections_from_block_reports() {
+    use std::process::Command;
+    let registry_gen = |release: &str, registry: &std::path::Path| {
+        let output = Command::new(env!("CARGO_BIN_EXE_registry-gen"))
+            .args(["tests/test_registry/blocks_1.20.3.json", release, registry.to_str().unwrap()])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    // With no earlier release every block is written, with block entities taken from the table
+    let path = std::env::temp_dir().join(format!("registry_gen_{}.txt", std::process::id()));
+    std::fs::write(&path, "").unwrap();
+    let section = registry_gen("1.20.3", &path);
+    assert_eq!(section.lines().next(), Some("@version 3698"));
+    let registries = parse_registries(&section).unwrap();
+    let registry = &registries[0];
+    assert_eq!(registry.len(), 4);
+    assert_eq!(registry.block("chest").unwrap().default_state(), "minecraft:chest[facing=north,type=single,waterlogged=false]");
+    assert!(registry.block("chest").unwrap().has_block_entity);
+    assert!(!registry.block("stone").unwrap().has_block_entity && !registry.block("stone").unwrap().opaque);
+    assert!(registry.check_state("minecraft:rail[shape=north_south,waterlogged=false]").is_ok());
+
+    // Against an earlier release only the changes are written, and flags carry over
+    std::fs::write(&path, "@version 2566\nchest e facing=north,south,west,east type=single,left,right waterlogged=true,*false\ngrass -\nstone o\nrail - shape=north_south,east_west,ascending_east,ascending_west,ascending_north,ascending_south,south_east,south_west,north_west,north_east\n").unwrap();
+    let section = registry_gen("1.20.3", &path);
+    let mut lines: Vec<&str> = section.lines().collect();
+    lines.sort();
+    assert_eq!(lines, vec!["-grass", "@version 3698", "rail - shape=north_south,east_west,ascending_east,ascending_west,ascending_north,ascending_south,south_east,south_west,north_west,north_east waterlogged=true,*false", "short_grass -"]);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,97 @@
+use schematic_converter::models::Schematic;
+use schematic_converter::render::{render_isometric, render_layer, render_layers, render_top_down, IsometricOptions, ResourcePack, Texture};
+use schematic_converter::SchematicFormat;
+
+fn png_size(data: &[u8]) -> (u32, u32) {
+    let decoder = png::Decoder::new(data);
+    let reader = decoder.read_info().unwrap();
+    let info = reader.info();
+    (info.width, info.height)
+}
+
+fn png_pixel(data: &[u8], x: usize, y: usize) -> [u8; 4] {
+    let decoder = png::Decoder::new(data);
+    let mut reader = decoder.read_info().unwrap();
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).unwrap();
+    let offset = (x + y * info.width as usize) * 4;
+    [buffer[offset], buffer[offset + 1], buffer[offset + 2], buffer[offset + 3]]
+}
+
+#[test]
+fn test_top_down_render_of_litematic() {
+    let big_quarry = include_bytes!("test_schematics/big_quarry.litematic");
+    let schematic = Schematic::read(big_quarry, SchematicFormat::Litematic).unwrap();
+
+    let png = render_top_down(&schematic, 2).unwrap();
+    assert_eq!(png_size(&png), (schematic.width as u32 * 2, schematic.length as u32 * 2));
+}
+
+#[test]
+fn test_layer_renders_of_schem() {
+    let sample_schem = include_bytes!("test_schematics/sample.schem");
+    let schematic = Schematic::read(sample_schem, SchematicFormat::Schem).unwrap();
+
+    let layers = render_layers(&schematic, 1).unwrap();
+    assert_eq!(layers.len(), schematic.height);
+    for layer in &layers {
+        assert_eq!(png_size(layer), (schematic.width as u32, schematic.length as u32));
+    }
+
+    assert!(render_layer(&schematic, schematic.height, 1).is_err(), "Layers above the schematic should be rejected");
+}
+
+#[test]
+fn test_isometric_render_of_litematic() {
+    let big_quarry = include_bytes!("test_schematics/big_quarry.litematic");
+    let schematic = Schematic::read(big_quarry, SchematicFormat::Litematic).unwrap();
+
+    let options = IsometricOptions { width: 320, height: 200, yaw: 135.0, pitch: 40.0 };
+    let png = render_isometric(&schematic, &options, None).unwrap();
+    assert_eq!(png_size(&png), (320, 200));
+    assert_eq!(png_pixel(&png, 0, 0)[3], 0, "Background should be transparent");
+}
+
+#[test]
+fn test_isometric_render_samples_resource_pack_textures() {
+    let mut schematic = Schematic::new(1, 1, 1);
+    schematic.set_block(0, 0, 0, "minecraft:stone");
+
+    // Seen straight from above only the top face is visible, filling the middle of the image
+    let options = IsometricOptions { width: 64, height: 64, yaw: 0.0, pitch: 90.0 };
+    let untextured = render_isometric(&schematic, &options, None).unwrap();
+    assert_eq!(png_pixel(&untextured, 32, 32), [125, 125, 125, 255]);
+
+    let mut pack = ResourcePack::new();
+    pack.insert("stone", Texture { width: 1, height: 1, rgba: vec![0, 0, 255, 255] });
+    let textured = render_isometric(&schematic, &options, Some(&pack)).unwrap();
+    assert_eq!(png_pixel(&textured, 32, 32), [0, 0, 255, 255]);
+}
+
+#[cfg(feature = "resource-pack")]
+#[test]
+fn test_resource_pack_loads_block_textures_from_zip() {
+    use std::io::Write;
+
+    let mut texture = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut texture, 2, 2);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.write_header().unwrap().write_image_data(&[255, 0, 0].repeat(4)).unwrap();
+    }
+
+    let mut zip_data = std::io::Cursor::new(Vec::new());
+    {
+        let mut writer = zip::ZipWriter::new(&mut zip_data);
+        let options = zip::write::SimpleFileOptions::default();
+        writer.start_file("assets/minecraft/textures/block/stone.png", options).unwrap();
+        writer.write_all(&texture).unwrap();
+        writer.start_file("assets/minecraft/textures/item/stick.png", options).unwrap();
+        writer.write_all(&texture).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let pack = ResourcePack::from_zip(zip_data.get_ref()).unwrap();
+    assert_eq!(pack.len(), 1, "Only block textures should be loaded");
+    assert_eq!(pack.get("stone").unwrap().sample(0.5, 0.5), [255, 0, 0, 255]);
+}
@@ -1,5 +1,5 @@
 use quartz_nbt::io::Flavor;
-use schematic_converter::converters::{schem_to_schematic, schematic_to_schem};
+use schematic_converter::converters::{schem_to_schematic, schematic_to_schem, schematic_to_schem_with_options, CompressionKind, ConvertOptions};
 use std::io::Cursor;
 use quartz_nbt::{NbtCompound, NbtTag};
 
@@ -83,4 +83,27 @@ fn test_roundtrip_conversion() {
     assert_eq!(original_nbt, roundtrip_nbt, "Roundtrip conversion should preserve NBT structure");
 }
 
+#[test]
+fn test_schem_output_honors_compression_options() {
+    let sample_schem = include_bytes!("test_schematics/sample.schem");
+
+    let mut schematic_data = Vec::new();
+    schem_to_schematic(Cursor::new(sample_schem), &mut schematic_data).unwrap();
 
+    for compression in [CompressionKind::None, CompressionKind::Gzip, CompressionKind::Zlib] {
+        let options = ConvertOptions::new().with_compression(compression).with_level(1);
+        let mut schem_output = Vec::new();
+        schematic_to_schem_with_options(Cursor::new(&schematic_data), &mut schem_output, &options).unwrap();
+
+        match compression {
+            CompressionKind::None => assert_eq!(schem_output, schematic_data),
+            CompressionKind::Gzip => assert_eq!(&schem_output[..2], &[0x1f, 0x8b]),
+            CompressionKind::Zlib => assert_eq!(schem_output[0], 0x78),
+        }
+
+        // Readers detect the compression on their own
+        let mut decompressed = Vec::new();
+        schem_to_schematic(Cursor::new(&schem_output), &mut decompressed).unwrap();
+        assert_eq!(decompressed, schematic_data, "{:?} output should decompress to the original NBT", compression);
+    }
+}
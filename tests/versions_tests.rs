@@ -0,0 +1,253 @@
+use quartz_nbt::{NbtCompound, NbtList, NbtTag};
+use schematic_converter::models::Schematic;
+use schematic_converter::operations::ReplaceRule;
+use schematic_converter::versions::{downgrade, parse_data_version, upgrade, DowngradeOptions, SubstitutionReason};
+use schematic_converter::{SchematicConverter, SchematicFormat};
+
+fn sign(text: &str) -> NbtCompound {
+    let mut block_entity = NbtCompound::new();
+    block_entity.insert("Id", NbtTag::String("minecraft:sign".to_string()));
+    block_entity.insert("Pos", NbtTag::IntArray(vec![0, 0, 0]));
+    block_entity.insert("Text1", NbtTag::String(text.to_string()));
+    block_entity.insert("Color", NbtTag::String("red".to_string()));
+    block_entity
+}
+
+#[test]
+fn test_parse_data_version() {
+    assert_eq!(parse_data_version("3953"), Some(3953));
+    assert_eq!(parse_data_version("1.21"), Some(3953));
+    assert_eq!(parse_data_version("1.20.4"), Some(3698), "Patch releases use the release before them");
+    assert_eq!(parse_data_version("1.9"), None);
+    assert_eq!(parse_data_version("latest"), None);
+}
+
+#[test]
+fn test_upgrade_block_states() {
+    let mut schematic = Schematic::new(6, 1, 1);
+    schematic.data_version = Some(2230);
+    schematic.set_block(0, 0, 0, "minecraft:grass");
+    schematic.set_block(1, 0, 0, "minecraft:cobblestone_wall[east=true,north=false,south=false,up=true,waterlogged=false,west=true]");
+    schematic.set_block(2, 0, 0, "minecraft:grass_path");
+    schematic.set_block(3, 0, 0, "minecraft:cauldron[level=2]");
+    schematic.set_block(4, 0, 0, "minecraft:redstone_wire[east=none,north=none,power=0,south=none,west=none]");
+    schematic.set_block(5, 0, 0, "minecraft:grass_block[snowy=false]");
+
+    let (upgraded, report) = upgrade(&schematic, 3953).unwrap();
+    assert_eq!(upgraded.data_version, Some(3953));
+    assert_eq!(upgraded.block_at(0, 0, 0), "minecraft:short_grass");
+    assert_eq!(upgraded.block_at(1, 0, 0), "minecraft:cobblestone_wall[east=low,north=none,south=none,up=true,waterlogged=false,west=low]");
+    assert_eq!(upgraded.block_at(2, 0, 0), "minecraft:dirt_path");
+    assert_eq!(upgraded.block_at(3, 0, 0), "minecraft:water_cauldron[level=2]");
+    assert_eq!(upgraded.block_at(4, 0, 0), "minecraft:redstone_wire[east=side,north=side,power=0,south=side,west=side]");
+    assert_eq!(upgraded.block_at(5, 0, 0), "minecraft:grass_block[snowy=false]");
+    assert_eq!(report.block_states, 5);
+    assert!(!report.applied.contains(&"oak signs"), "Fixes older than the source version are skipped");
+
+    let (partial, _) = upgrade(&schematic, 2724).unwrap();
+    assert_eq!(partial.block_at(0, 0, 0), "minecraft:grass", "Fixes newer than the target are skipped");
+    assert_eq!(partial.block_at(2, 0, 0), "minecraft:dirt_path");
+
+    assert!(upgrade(&schematic, 1952).is_err(), "Upgrading to an older version is an error");
+}
+
+#[test]
+fn test_upgrade_merges_palette_entries() {
+    let mut schematic = Schematic::new(2, 1, 1);
+    schematic.data_version = Some(1631);
+    schematic.set_block(0, 0, 0, "minecraft:cauldron[level=0]");
+    schematic.set_block(1, 0, 0, "minecraft:cauldron");
+
+    let (upgraded, _) = upgrade(&schematic, 2724).unwrap();
+    assert_eq!(upgraded.blocks[0], upgraded.blocks[1]);
+    assert_eq!(upgraded.palette.iter().filter(|block| *block == "minecraft:cauldron").count(), 1);
+}
+
+#[test]
+fn test_upgrade_block_entities() {
+    let mut schematic = Schematic::new(1, 1, 1);
+    schematic.data_version = Some(1343);
+    schematic.set_block(0, 0, 0, "minecraft:sign[rotation=0,waterlogged=false]");
+    schematic.block_entities.push(sign("{\"text\":\"Hello\"}"));
+
+    let (upgraded, report) = upgrade(&schematic, 3463).unwrap();
+    assert_eq!(upgraded.block_at(0, 0, 0), "minecraft:oak_sign[rotation=0,waterlogged=false]");
+    assert_eq!(report.block_entities, 1);
+
+    let block_entity = &upgraded.block_entities[0];
+    assert!(!block_entity.contains_key("Text1"));
+    let front = block_entity.get::<_, &NbtCompound>("front_text").unwrap();
+    assert_eq!(front.get::<_, &str>("color").unwrap(), "red");
+    let messages: Vec<&NbtTag> = front.get::<_, &NbtList>("messages").unwrap().iter().collect();
+    assert_eq!(messages.len(), 4);
+    assert_eq!(messages[0], &NbtTag::String("{\"text\":\"Hello\"}".to_string()));
+    assert!(block_entity.contains_key("back_text"));
+}
+
+#[test]
+fn test_upgrade_through_converter() {
+    let mut schematic = Schematic::new(1, 1, 1);
+    schematic.data_version = Some(2586);
+    schematic.set_block(0, 0, 0, "minecraft:grass");
+    let input = schematic.write(SchematicFormat::Schem, &Default::default()).unwrap();
+
+    let (output, report) = SchematicConverter::new().upgrade(&input, SchematicFormat::Schem, 3700).unwrap();
+    assert_eq!(report.applied, vec!["short grass"]);
+    let upgraded = Schematic::read(&output, SchematicFormat::Schem).unwrap();
+    assert_eq!(upgraded.block_at(0, 0, 0), "minecraft:short_grass");
+    assert_eq!(upgraded.data_version, Some(3700));
+
+    let mut unversioned = schematic.clone();
+    unversioned.data_version = None;
+    let input = unversioned.write(SchematicFormat::Schem, &Default::default()).unwrap();
+    assert!(SchematicConverter::new().upgrade(&input, SchematicFormat::Schem, 3700).is_err());
+}
+
+#[test]
+fn test_downgrade_substitutes_newer_blocks() {
+    let mut schematic = Schematic::new(6, 1, 1);
+    schematic.data_version = Some(3953);
+    schematic.set_block(0, 0, 0, "minecraft:cherry_stairs[facing=east,half=bottom,shape=straight,waterlogged=false]");
+    schematic.set_block(1, 0, 0, "minecraft:copper_block");
+    schematic.set_block(2, 0, 0, "minecraft:short_grass");
+    schematic.set_block(3, 0, 0, "minecraft:oak_leaves[distance=7,persistent=true,waterlogged=false]");
+    schematic.set_block(4, 0, 0, "minecraft:cobblestone_wall[east=low,north=none,south=none,up=true,waterlogged=false,west=tall]");
+    schematic.set_block(5, 0, 0, "minecraft:pink_petals[facing=north,flower_amount=1]");
+
+    let (downgraded, report) = downgrade(&schematic, 2566, &DowngradeOptions::default()).unwrap();
+    assert_eq!(downgraded.data_version, Some(2566));
+    assert_eq!(downgraded.block_at(0, 0, 0), "minecraft:birch_stairs[facing=east,half=bottom,shape=straight,waterlogged=false]");
+    assert_eq!(downgraded.block_at(1, 0, 0), "minecraft:stone_bricks");
+    assert_eq!(downgraded.block_at(2, 0, 0), "minecraft:grass");
+    assert_eq!(downgraded.block_at(3, 0, 0), "minecraft:oak_leaves[distance=7,persistent=true]");
+    assert_eq!(downgraded.block_at(4, 0, 0), "minecraft:cobblestone_wall[east=low,north=none,south=none,up=true,waterlogged=false,west=tall]");
+    assert_eq!(downgraded.block_at(5, 0, 0), "minecraft:air");
+
+    let reason = |original: &str| report.substitutions.iter().find(|substitution| substitution.original == original).map(|substitution| substitution.reason);
+    assert_eq!(reason("minecraft:copper_block"), Some(SubstitutionReason::Substitute));
+    assert_eq!(reason("minecraft:short_grass"), Some(SubstitutionReason::DataFix));
+    assert_eq!(reason("minecraft:oak_leaves[distance=7,persistent=true,waterlogged=false]"), Some(SubstitutionReason::Properties));
+    assert_eq!(reason("minecraft:pink_petals[facing=north,flower_amount=1]"), Some(SubstitutionReason::Fallback));
+    assert_eq!(reason("minecraft:cobblestone_wall[east=low,north=none,south=none,up=true,waterlogged=false,west=tall]"), None, "1.16 already has wall heights");
+
+    let (older, _) = downgrade(&schematic, 2225, &DowngradeOptions::default()).unwrap();
+    assert_eq!(older.block_at(4, 0, 0), "minecraft:cobblestone_wall[east=true,north=false,south=false,up=true,waterlogged=false,west=true]");
+
+    assert!(downgrade(&schematic, 4189, &DowngradeOptions::default()).is_err(), "Downgrading to a newer version is an error");
+}
+
+#[test]
+fn test_downgrade_custom_substitutes() {
+    let mut schematic = Schematic::new(3, 1, 1);
+    schematic.data_version = Some(3700);
+    schematic.set_block(0, 0, 0, "minecraft:cherry_planks");
+    schematic.set_block(1, 0, 0, "minecraft:cherry_hanging_sign[attached=false,rotation=4,waterlogged=false]");
+    schematic.set_block(2, 0, 0, "minecraft:pink_petals[facing=north,flower_amount=1]");
+    let mut sign = NbtCompound::new();
+    sign.insert("Id", NbtTag::String("minecraft:hanging_sign".to_string()));
+    sign.insert("Pos", NbtTag::IntArray(vec![1, 0, 0]));
+    schematic.block_entities.push(sign);
+
+    let options = DowngradeOptions {
+        substitutes: vec![ReplaceRule::new("cherry_planks", "acacia_planks")],
+        fallback: "minecraft:pink_wool".to_string(),
+    };
+    let (downgraded, report) = downgrade(&schematic, 3105, &options).unwrap();
+    assert_eq!(downgraded.block_at(0, 0, 0), "minecraft:acacia_planks", "Caller substitutes come first");
+    assert_eq!(downgraded.block_at(1, 0, 0), "minecraft:birch_sign[rotation=4,waterlogged=false]", "Substitutes are chained");
+    assert_eq!(downgraded.block_at(2, 0, 0), "minecraft:pink_wool");
+    assert_eq!(downgraded.block_entities[0].get::<_, &str>("Id").unwrap(), "minecraft:sign", "Hanging sign text moves to a sign");
+    assert!(report.dropped_block_entities.is_empty());
+}
+
+#[test]
+fn test_downgrade_drops_block_entities_of_fallback_blocks() {
+    let mut schematic = Schematic::new(2, 1, 1);
+    schematic.data_version = Some(3953);
+    schematic.set_block(0, 0, 0, "minecraft:barrel[facing=up,open=false]");
+    schematic.set_block(1, 0, 0, "minecraft:chest[facing=north,type=single,waterlogged=false]");
+    for (x, id) in [(0, "minecraft:barrel"), (1, "minecraft:chest")] {
+        let mut block_entity = NbtCompound::new();
+        block_entity.insert("Id", NbtTag::String(id.to_string()));
+        block_entity.insert("Pos", NbtTag::IntArray(vec![x, 0, 0]));
+        schematic.block_entities.push(block_entity);
+    }
+
+    let (downgraded, report) = downgrade(&schematic, 1519, &DowngradeOptions::default()).unwrap();
+    assert_eq!(downgraded.block_at(0, 0, 0), "minecraft:air");
+    assert_eq!(downgraded.block_entities.len(), 1, "Air cannot hold the barrel's block entity");
+    assert_eq!(downgraded.block_entities[0].get::<_, &str>("Id").unwrap(), "minecraft:chest");
+    assert_eq!(report.dropped_block_entities, vec!["minecraft:barrel".to_string()]);
+
+    let options = DowngradeOptions { fallback: "minecraft:dispenser[facing=up,triggered=false]".to_string(), ..Default::default() };
+    let (downgraded, report) = downgrade(&schematic, 1519, &options).unwrap();
+    assert_eq!(downgraded.block_entities[0].get::<_, &str>("Id").unwrap(), "minecraft:dispenser", "A fallback with a block entity keeps the contents");
+    assert!(report.dropped_block_entities.is_empty());
+}
+
+#[test]
+fn test_downgrade_covers_newer_blocks_and_values() {
+    let mut schematic = Schematic::new(8, 1, 1);
+    schematic.data_version = Some(4189);
+    schematic.set_block(0, 0, 0, "minecraft:loom[facing=north]");
+    schematic.set_block(1, 0, 0, "minecraft:cut_red_sandstone_slab[type=top,waterlogged=false]");
+    schematic.set_block(2, 0, 0, "minecraft:piglin_wall_head[facing=east,powered=false]");
+    schematic.set_block(3, 0, 0, "minecraft:potted_mangrove_propagule");
+    schematic.set_block(4, 0, 0, "minecraft:potted_pale_oak_sapling");
+    schematic.set_block(5, 0, 0, "minecraft:note_block[instrument=zombie,note=3,powered=false]");
+    schematic.set_block(6, 0, 0, "minecraft:note_block[instrument=bit,note=3,powered=false]");
+    schematic.set_block(7, 0, 0, "minecraft:lantern[hanging=true,waterlogged=false]");
+
+    let (downgraded, _) = downgrade(&schematic, 2566, &DowngradeOptions::default()).unwrap();
+    assert_eq!(downgraded.block_at(2, 0, 0), "minecraft:zombie_wall_head[facing=east,powered=false]");
+    assert_eq!(downgraded.block_at(3, 0, 0), "minecraft:potted_jungle_sapling");
+    assert_eq!(downgraded.block_at(4, 0, 0), "minecraft:potted_dark_oak_sapling");
+    assert_eq!(downgraded.block_at(5, 0, 0), "minecraft:note_block[note=3,powered=false]", "1.16 has no zombie instrument");
+    assert_eq!(downgraded.block_at(6, 0, 0), "minecraft:note_block[instrument=bit,note=3,powered=false]");
+    assert_eq!(downgraded.block_at(7, 0, 0), "minecraft:lantern[hanging=true,waterlogged=false]", "Lanterns are waterloggable since 1.16");
+
+    let (oldest, _) = downgrade(&schematic, 1519, &DowngradeOptions::default()).unwrap();
+    assert_eq!(oldest.block_at(0, 0, 0), "minecraft:crafting_table");
+    assert_eq!(oldest.block_at(1, 0, 0), "minecraft:red_sandstone_slab[type=top,waterlogged=false]");
+    assert_eq!(oldest.block_at(6, 0, 0), "minecraft:note_block[note=3,powered=false]");
+    assert_eq!(oldest.block_at(7, 0, 0), "minecraft:air", "Lanterns are newer than 1.13");
+}
+
+#[test]
+fn test_upgrade_then_downgrade_round_trip() {
+    let mut schematic = Schematic::new(2, 1, 1);
+    schematic.data_version = Some(1343);
+    schematic.set_block(0, 0, 0, "minecraft:sign[rotation=0,waterlogged=false]");
+    schematic.set_block(1, 0, 0, "minecraft:grass_path");
+    schematic.block_entities.push(sign("{\"text\":\"Hello\"}"));
+
+    let (upgraded, _) = upgrade(&schematic, 3953).unwrap();
+    let (restored, report) = downgrade(&upgraded, 1631, &DowngradeOptions::default()).unwrap();
+    assert_eq!(restored.block_at(0, 0, 0), "minecraft:sign[rotation=0,waterlogged=false]");
+    assert_eq!(restored.block_at(1, 0, 0), "minecraft:grass_path");
+    let block_entity = &restored.block_entities[0];
+    assert_eq!(block_entity.get::<_, &str>("Text1").unwrap(), "{\"text\":\"Hello\"}");
+    assert_eq!(block_entity.get::<_, &str>("Color").unwrap(), "red");
+    assert!(!block_entity.contains_key("front_text"));
+    assert!(report.applied.contains(&"double sided signs"));
+}
+
+#[test]
+fn test_rewritten_states_are_canonical() {
+    let mut schematic = Schematic::new(3, 1, 1);
+    schematic.data_version = Some(2230);
+    schematic.set_block(0, 0, 0, "minecraft:cobblestone_wall[west=true,waterlogged=false,up=true,south=false,north=false,east=true]");
+    schematic.set_block(1, 0, 0, "minecraft:jigsaw[facing=up]");
+    schematic.set_block(2, 0, 0, "minecraft:stone[]");
+
+    let (upgraded, report) = upgrade(&schematic, 2566).unwrap();
+    assert_eq!(upgraded.block_at(0, 0, 0), "minecraft:cobblestone_wall[east=low,north=none,south=none,up=true,waterlogged=false,west=low]");
+    assert_eq!(upgraded.block_at(1, 0, 0), "minecraft:jigsaw[orientation=up_north]");
+    assert_eq!(upgraded.block_at(2, 0, 0), "minecraft:stone[]", "Unchanged entries keep their text");
+    assert_eq!(report.block_states, 2);
+
+    let (downgraded, report) = downgrade(&upgraded, 2230, &DowngradeOptions::default()).unwrap();
+    assert_eq!(downgraded.block_at(0, 0, 0), "minecraft:cobblestone_wall[east=true,north=false,south=false,up=true,waterlogged=false,west=true]");
+    assert_eq!(downgraded.block_at(1, 0, 0), "minecraft:jigsaw[facing=up]");
+    assert_eq!(report.substitutions.len(), 2);
+}